@@ -1,9 +1,20 @@
-use crate::data::{self, Snapshot};
+use crate::clipboard::{self, Copied};
+use crate::config::Config;
+use crate::data::{self, BootLineage, ConfigFs, DirEntry, ExportFormat, Snapshot, StatusRange};
+use crate::filter::Filter;
+use crate::history;
+use crate::keys::KeyMap;
+use crate::labels::{self, Labels};
+use crate::tasks::{TaskError, TaskId, TaskQueue};
+use crate::theme::{self, Theme, THEMES};
+use ratatui::layout::{Position, Rect};
 use ratatui::widgets::TableState;
-use std::sync::mpsc::Receiver;
-use std::collections::HashSet;
-use tachyonfx::Effect;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tachyonfx::{Effect, Shader};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SortKey {
     Number,
     Type,
@@ -12,123 +23,1244 @@ pub enum SortKey {
     UsedSpace,
 }
 
-pub enum InputMode {
-    Normal,
-    Editing,
-    Filtering,
+impl SortKey {
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Number => "number",
+            SortKey::Type => "type",
+            SortKey::Date => "date",
+            SortKey::User => "user",
+            SortKey::UsedSpace => "used_space",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SortKey> {
+        match name {
+            "number" => Some(SortKey::Number),
+            "type" => Some(SortKey::Type),
+            "date" => Some(SortKey::Date),
+            "user" => Some(SortKey::User),
+            "used_space" => Some(SortKey::UsedSpace),
+            _ => None,
+        }
+    }
+}
+
+/// A column of the snapshot table, as named in the `columns` config list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Number,
+    Type,
+    Date,
+    User,
+    Space,
+    Delta,
+    Label,
+    Description,
+    Config,
+    Cleanup,
+    Subvolume,
+}
+
+pub const COLUMNS: [Column; 11] = [
+    Column::Number,
+    Column::Type,
+    Column::Date,
+    Column::User,
+    Column::Space,
+    Column::Delta,
+    Column::Label,
+    Column::Description,
+    Column::Config,
+    Column::Cleanup,
+    Column::Subvolume,
+];
+
+/// The table's columns when `columns` isn't set.
+pub const DEFAULT_COLUMNS: [Column; 8] = [
+    Column::Number,
+    Column::Type,
+    Column::Date,
+    Column::User,
+    Column::Space,
+    Column::Delta,
+    Column::Label,
+    Column::Description,
+];
+
+impl Column {
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Number => "number",
+            Column::Type => "type",
+            Column::Date => "date",
+            Column::User => "user",
+            Column::Space => "space",
+            Column::Delta => "delta",
+            Column::Label => "label",
+            Column::Description => "description",
+            Column::Config => "config",
+            Column::Cleanup => "cleanup",
+            Column::Subvolume => "subvolume",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Column> {
+        COLUMNS.into_iter().find(|column| column.name() == name)
+    }
+
+    /// What clicking the column's header sorts by, if anything.
+    pub fn sort_key(self) -> Option<SortKey> {
+        match self {
+            Column::Number => Some(SortKey::Number),
+            Column::Type => Some(SortKey::Type),
+            Column::Date => Some(SortKey::Date),
+            Column::User => Some(SortKey::User),
+            Column::Space => Some(SortKey::UsedSpace),
+            _ => None,
+        }
+    }
+}
+
+const STATUS_DEBOUNCE: Duration = Duration::from_millis(250);
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+// Older toasts are dropped early once this many are stacked
+const MAX_TOASTS: usize = 4;
+pub const SPLASH_DURATION: Duration = Duration::from_secs(2);
+
+/// How often an idle screen is redrawn anyway, for clocks like "refreshed
+/// 5s ago" and relative dates.
+pub const IDLE_REDRAW: Duration = Duration::from_secs(1);
+
+/// Loading spinner presets selectable with `spinner_style`.
+pub const SPINNERS: [(&str, &[&str]); 4] = [
+    ("braille", &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
+    ("line", &["-", "\\", "|", "/"]),
+    ("arc", &["◜", "◠", "◝", "◞", "◡", "◟"]),
+    ("dots", &[".  ", ".. ", "...", "   "]),
+];
+
+/// How a toast is coloured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
 }
 
+/// A notification's text, when it was raised and its severity.
+pub type Toast = (String, Instant, Severity);
+
+/// `get-config` parameters of a snapper config, or why they couldn't be read.
+pub type ConfigValues = Result<HashMap<String, String>, String>;
+
 pub enum AsyncResult {
     Snapshots(Vec<Snapshot>),
-    Delete { success: usize, fail: usize },
-    Create(String),
-    Apply(u32),
-    Status(String),
+    /// A reload started by the auto-refresh timer rather than the user.
+    AutoRefresh(Vec<Snapshot>),
+    /// Snapshots a bulk delete removed, and the ones it couldn't with why.
+    Delete { succeeded: Vec<u32>, failed: Vec<(u32, String)> },
+    /// `done` of the `total` snapshots of a bulk delete have been handled.
+    DeleteProgress { done: usize, total: usize },
+    /// What was created, and the number snapper gave it.
+    Create(data::NewSnapshot, Option<u32>),
+    /// A rollback of the system root to snapshot `number`.
+    Apply { number: u32 },
+    Status { range: StatusRange, text: String },
+    /// Lines of a status listing that is still being produced.
+    StatusLines { range: StatusRange, lines: Vec<String> },
+    Diff { path: String, text: String },
+    Undo { success: usize, fail: usize },
+    /// `(used, total)` bytes, or `None` when the subvolume isn't on btrfs.
+    Usage(Option<(u64, u64)>),
+    /// `None` when `/` isn't a btrfs filesystem managed by the root config.
+    Lineage(Option<BootLineage>),
+    /// `snapper --version`, or why it couldn't be found out.
+    SnapperVersion(Result<String, String>),
+    Modify(u32),
+    ConfigInfo { name: String, values: ConfigValues },
+    /// Filesystem of config `name`, `None` when it couldn't be found out.
+    ConfigFs { name: String, fs: Option<ConfigFs> },
+    Cleanup { config: String, algorithm: &'static str, removed: usize },
+    /// `snapper setup-quota` succeeded for this config.
+    QuotaSetUp(String),
+    /// A snapshot was mounted for browsing; `entries` lists its root.
+    Mounted { config: String, number: u32, root: PathBuf, entries: Vec<DirEntry> },
+    Listing { dir: PathBuf, entries: Vec<DirEntry> },
+    FileView { path: PathBuf, text: String },
+    Unmounted(u32),
+}
+
+/// A mounted snapshot being browsed in the right-hand panel.
+#[derive(Debug, Clone)]
+pub struct Browse {
+    pub config: String,
+    pub number: u32,
+    /// Mount point of the snapshot; browsing never goes above it.
+    pub root: PathBuf,
+    pub dir: PathBuf,
+    pub entries: Vec<DirEntry>,
+    pub cursor: usize,
+    /// File being previewed instead of the listing.
+    pub file: Option<(PathBuf, String)>,
+    pub file_scroll: u16,
+}
+
+/// The buttons of the footer's action bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FooterAction {
+    Create,
+    Delete,
+    Apply,
+    Filter,
+    Status,
+    Refresh,
+    Quit,
+}
+
+/// Where things ended up on screen in the last draw, so mouse clicks are
+/// mapped against the real layout whatever the terminal size.
+#[derive(Debug, Clone, Default)]
+pub struct HitAreas {
+    pub table: Rect,
+    /// Top of the right panel: details or config info.
+    pub details: Rect,
+    /// Bottom of the right panel: status or the file browser.
+    pub status: Rect,
+    pub footer_buttons: Vec<(Rect, FooterAction)>,
+    /// Header cells of the table's sortable columns.
+    pub sort_headers: Vec<(Rect, SortKey)>,
+}
+
+impl HitAreas {
+    pub fn footer_action_at(&self, column: u16, row: u16) -> Option<FooterAction> {
+        self.footer_buttons
+            .iter()
+            .find(|(rect, _)| rect.contains(Position::new(column, row)))
+            .map(|&(_, action)| action)
+    }
+
+    pub fn sort_key_at(&self, column: u16, row: u16) -> Option<SortKey> {
+        self.sort_headers
+            .iter()
+            .find(|(rect, _)| rect.contains(Position::new(column, row)))
+            .map(|&(_, key)| key)
+    }
+
+    /// Snapshot rows the table showed last frame, inside its borders and
+    /// below the header; 0 before it has been drawn.
+    pub fn table_rows(&self) -> usize {
+        self.table.height.saturating_sub(3) as usize
+    }
+}
+
+/// Userdata value, under `Config::created_tag_key`, of snapshots created here.
+pub const CREATED_TAG_VALUE: &str = "snapper-tui";
+
+/// Snapshot types `Alt-1`..`Alt-3` hide and show, in key order.
+pub const HIDEABLE_TYPES: [&str; 3] = ["single", "pre", "post"];
+
+pub const MODIFY_FIELDS: [&str; 3] = ["Description", "Cleanup", "Userdata"];
+
+pub const CREATE_FIELDS: [&str; 4] = ["Description", "Type (single, pre or post)", "Cleanup", "Userdata"];
+
+/// Contents of the create popup.
+#[derive(Debug, Clone, Default)]
+pub struct CreateForm {
+    pub values: [String; 4],
+    pub field: usize,
+}
+
+/// Contents of the edit popup, next to the values it was opened with so
+/// only edited fields are sent to `snapper modify`.
+#[derive(Debug, Clone, Default)]
+pub struct ModifyForm {
+    pub config: String,
+    pub number: u32,
+    pub values: [String; 3],
+    pub original: [String; 3],
+    pub field: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifyChanges {
+    pub config: String,
+    pub number: u32,
+    pub description: Option<String>,
+    pub cleanup: Option<String>,
+    pub userdata: Option<HashMap<String, String>>,
+}
+
+/// Popups and modes that take keys ahead of the main view. Esc closes the
+/// topmost one, whichever it is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Modal {
+    Quit,
+    Help,
+    About,
+    History,
+    Theme,
+    Quota,
+    Cleanup,
+    Report,
+    Diff,
+    Undo,
+    Delete,
+    Apply,
+    Modify,
+    Goto,
+    Label,
+    Export,
+    Create,
+    Browse,
+    Filter,
+}
+
+/// The main-screen panel the arrow and page keys act on.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Panel {
+    #[default]
+    Table,
+    /// The details panel, or the config or cleanup panel shown in its place.
+    Details,
+    /// The status panel, or the file browser shown in its place.
+    Status,
+}
+
+// Columns Left/Right scroll unwrapped text by
+const HSCROLL_STEP: u16 = 8;
+
+// Lines PageUp/PageDown scroll the side panels by, and rows they move the
+// table by before it has been drawn
+const PAGE_STEP: u16 = 10;
+
+/// One line of the snapshot table.
+#[derive(Debug, Clone, Copy)]
+pub enum DisplayRow<'a> {
+    Single(&'a Snapshot),
+    /// A `pre`/`post` pair folded into one row. When `expanded`, the two
+    /// members follow it as `Single` rows.
+    Pair { pre: &'a Snapshot, post: &'a Snapshot, expanded: bool },
+}
+
+impl<'a> DisplayRow<'a> {
+    pub fn snapshots(&self) -> Vec<&'a Snapshot> {
+        match *self {
+            DisplayRow::Single(snap) => vec![snap],
+            DisplayRow::Pair { pre, post, .. } => vec![pre, post],
+        }
+    }
+
+    /// The snapshot single-target actions apply to. For a pair that is the
+    /// post snapshot, whose status range covers the whole pair.
+    pub fn primary(&self) -> &'a Snapshot {
+        match *self {
+            DisplayRow::Single(snap) => snap,
+            DisplayRow::Pair { post, .. } => post,
+        }
+    }
+}
+
+/// Launch options from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct AppOptions {
+    /// Start filtered to this snapper config. Applies to this session only
+    /// and is not saved as `default_config`.
+    pub config: Option<String>,
+    pub no_splash: bool,
+    /// How long the splash stays up; `None` keeps the default.
+    pub splash_duration: Option<Duration>,
+    pub no_mouse: bool,
+    /// `user@host` whose snapper is managed instead of this machine's.
+    pub ssh: Option<String>,
+    /// Draw without colours, from `--no-color` or a non-empty `NO_COLOR`.
+    pub no_color: bool,
 }
 
 pub struct App {
+    pub config: Config,
+    pub active_config: Option<String>,
+    pub theme: Theme,
+    pub show_theme_popup: bool,
+    pub theme_cursor: usize,
     pub snapshots: Vec<Snapshot>,
+    /// Bumped whenever the listed snapshots or their labels change.
+    pub list_generation: u64,
+    /// Column widths of the table and what they were measured from.
+    pub table_widths: Option<(crate::ui::WidthsKey, Vec<u16>)>,
+    /// Used space of each `(config, number)` minus that of the snapshot
+    /// taken before it in the same config, where both sizes are known.
+    pub space_deltas: HashMap<(String, u32), i64>,
+    pub fs_usage: Option<(u64, u64)>,
+    pub boot_lineage: Option<BootLineage>,
+    /// Filesystem per config name; `None` while being fetched or unknown,
+    /// in which case btrfs is assumed.
+    pub config_fs: HashMap<String, Option<ConfigFs>>,
+    pub show_config_info: bool,
+    /// Parsed `get-config` output per config name; `None` while being fetched.
+    pub config_info: HashMap<String, Option<ConfigValues>>,
     pub table_state: TableState,
     pub message: String,
+    /// Results of finished operations, newest last, shown until they expire
+    /// so later messages don't overwrite them.
+    pub toasts: Vec<Toast>,
     pub loading: bool,
     pub loading_message: String,
-    pub input_mode: InputMode,
+    /// `(done, total)` of the running operation, drawn as a gauge.
+    pub loading_progress: Option<(usize, usize)>,
+    /// When the current spell of `loading` began, noticed on the next tick.
+    pub loading_start: Option<Instant>,
+    /// Unrecoverable startup problem shown full-screen instead of the UI.
+    pub fatal_error: Option<String>,
     pub status_text: String,
+    pub status_range: Option<StatusRange>,
+    /// `status_range` spans two snapshots picked for comparison rather
+    /// than a single snapshot's own changes.
+    pub status_compare: bool,
+    pub status_cursor: usize,
+    pub status_marked: HashSet<usize>,
+    pub show_undo_popup: bool,
+    pub undo_targets: Vec<String>,
+    pub show_diff_popup: bool,
+    pub diff_path: String,
+    pub diff_text: String,
+    pub diff_scroll: u16,
+    /// Columns the diff is scrolled sideways by; its lines are never wrapped.
+    pub diff_hscroll: u16,
+    pub diff_max_hscroll: u16,
+    pub focused_panel: Panel,
+    /// Which of details and status a narrow terminal shows under the
+    /// table; follows the focus between the two.
+    pub side_tab: Panel,
+    /// No way to gain root was found at startup, so commands that change
+    /// snapshots are refused up front.
+    pub read_only: bool,
+    /// The `--ssh` destination whose snapshots are shown, if not this machine's.
+    pub remote: Option<String>,
+    /// Themes picked this session are drawn without their colours.
+    pub no_color: bool,
     pub details_scroll: u16,
+    pub details_max_scroll: u16,
     pub status_scroll: u16,
+    pub status_max_scroll: u16,
+    /// Wrap long status lines; off, they run past the edge and the panel
+    /// scrolls sideways instead (`F`).
+    pub status_wrap: bool,
+    pub status_hscroll: u16,
+    pub status_max_hscroll: u16,
     pub spinner_state: usize,
     pub spinner_frames: Vec<&'static str>,
     pub show_delete_popup: bool,
+    /// The extra warning about deleting the active or default snapshot
+    /// was acknowledged, so Enter goes ahead.
+    pub delete_acknowledged: bool,
+    /// The orphans the cleanup picked for the delete popup, by config and
+    /// number; empty for an ordinary delete of the selection or highlight.
+    pub orphan_targets: Vec<(String, u32)>,
+    pub delete_scroll: u16,
+    pub delete_max_scroll: u16,
     pub show_create_popup: bool,
-    pub create_input: String,
+    pub show_export_popup: bool,
+    pub show_goto_popup: bool,
+    pub show_label_popup: bool,
+    pub show_report_popup: bool,
+    /// The report popup saves the open diff rather than the status listing.
+    pub report_is_diff: bool,
+    pub show_modify_popup: bool,
+    pub show_cleanup_popup: bool,
+    /// Config and number of a `pre` snapshot created here whose `post`
+    /// hasn't been taken yet.
+    pub pending_pre: Option<(String, u32)>,
+    /// Asking to run `snapper setup-quota` for this config.
+    pub quota_popup: Option<String>,
+    /// Index into `data::CLEANUP_ALGORITHMS`.
+    pub cleanup_cursor: usize,
+    pub modify_form: ModifyForm,
+    pub export_input: String,
+    /// Snapshot number typed into the go-to popup.
+    pub goto_input: String,
+    pub label_input: String,
+    /// `(config, number)` the label popup was opened for.
+    pub label_target: Option<(String, u32)>,
+    pub labels: Labels,
+    pub report_input: String,
+    pub create_form: CreateForm,
     pub filtering: bool,
     pub filter_input: String,
+    /// Free-text filtering keeps case (`Ctrl-s` while filtering).
+    pub filter_case_sensitive: bool,
+    /// Free-text filtering matches whole words only (`Ctrl-w` while filtering).
+    pub filter_whole_word: bool,
+    /// Snapshot types left out of the table whatever the filter says.
+    pub hidden_types: HashSet<String>,
     pub show_apply_popup: bool,
+    /// Snapshot number typed into the apply popup to confirm a rollback.
+    pub apply_confirm_input: String,
+    pub show_help_popup: bool,
+    pub show_quit_popup: bool,
+    pub help_scroll: u16,
+    pub help_max_scroll: u16,
+    pub show_history_popup: bool,
+    pub show_about_popup: bool,
+    /// Fetched the first time the About overlay opens.
+    pub snapper_version: Option<Result<String, String>>,
+    version_requested: bool,
+    pub history_scroll: u16,
+    pub history_max_scroll: u16,
     pub show_splash: bool,
-    pub splash_start: Option<std::time::Instant>,
+    /// When the splash takes itself down if no key dismissed it first.
+    pub splash_until: Option<Instant>,
     pub fx: Option<Effect>,
     pub fx_start: Option<std::time::Instant>,
     pub current_sort_key: SortKey,
     pub sort_ascending: bool,
-    pub rx: Option<Receiver<Result<AsyncResult, String>>>,
-    pub selected_indices: HashSet<usize>,
+    pub tasks: TaskQueue,
+    /// Last Up/Down/click in the table; the status fetch waits for a pause.
+    pub last_nav: Instant,
+    pub status_fetch_pending: bool,
+    pub status_task: Option<TaskId>,
+    /// Multi-selected snapshots by config and number, as the all-configs
+    /// view can list the same number more than once.
+    pub selected_numbers: HashSet<(String, u32)>,
+    // Highlighted snapshot when a reload was requested, found again once
+    // the new list arrives
+    reload_highlight: Option<(String, u32)>,
+    /// Display row where visual mode started; `None` outside visual mode.
+    pub select_anchor: Option<usize>,
+    /// Selection from before visual mode, which the visual range adds to.
+    pub visual_base: HashSet<(String, u32)>,
+    /// Fold pre/post pairs into a single table row.
+    pub group_pairs: bool,
+    /// Config and pre number of the pairs currently expanded.
+    pub expanded_pairs: HashSet<(String, u32)>,
+    /// Show "2 hours ago" rather than the timestamp in the table.
+    pub relative_dates: bool,
+    /// Dense view, starting from `config.dense` and saved back on quit.
+    pub dense: bool,
+    /// When the snapshot list was last loaded.
+    pub last_refresh: Option<Instant>,
+    /// When the last reload was started; the auto-refresh interval counts from here.
+    pub refresh_started: Instant,
+    pub auto_refresh_task: Option<TaskId>,
+    /// The mounted snapshot being browsed; unmounted when browsing ends.
+    pub browse: Option<Browse>,
+    pub hit_areas: HitAreas,
+    /// Keys of the remappable main-screen commands.
+    pub keymap: KeyMap,
+    // `active_config` came from `--config` rather than the config file
+    config_from_cli: bool,
 }
 
 impl App {
-    pub fn new() -> App {
+    pub fn new(options: AppOptions) -> App {
+        let mut app = App::with_config(Config::load());
+        // Before anything runs, and before checking for the privilege helper
+        if let Some(destination) = options.ssh {
+            data::set_runner(Box::new(data::Ssh::new(destination.clone())));
+            app.remote = Some(destination);
+        }
+        if options.no_color {
+            app.no_color = true;
+            app.theme = app.theme.monochrome();
+        }
+        if let Some(file) = labels::path(app.remote.as_deref()) {
+            match Labels::load(file) {
+                Ok(loaded) => app.labels = loaded,
+                Err(e) => app.message = format!("⚠️ Labels: {:#}", e),
+            }
+        }
+        app.read_only = !data::can_escalate(app.config.privilege);
+        if let Some(name) = options.config {
+            app.active_config = Some(name);
+            app.config_from_cli = true;
+        }
+        match options.splash_duration {
+            _ if options.no_splash || !app.config.animations => app.dismiss_splash(),
+            Some(duration) if duration.is_zero() => app.dismiss_splash(),
+            Some(duration) => app.splash_until = Some(Instant::now() + duration),
+            None => {}
+        }
+        app
+    }
+
+    pub fn with_config(config: Config) -> App {
+        data::set_lock_retries(config.lock_retries);
+        data::set_sudo_askpass(config.sudo_askpass);
+        history::persist_to(if config.history_file { history::path() } else { None });
+        let mut warnings = config.warnings.clone();
+        let theme = theme::by_name(&config.theme).unwrap_or_else(|| {
+            warnings.push(format!("Unknown theme '{}'", config.theme));
+            theme::DRACULA
+        });
+        let spinner_frames = match SPINNERS.iter().find(|(name, _)| *name == config.spinner_style) {
+            Some((_, frames)) => frames.to_vec(),
+            None => {
+                warnings.push(format!("Unknown spinner_style '{}'", config.spinner_style));
+                SPINNERS[0].1.to_vec()
+            }
+        };
+        let animations = config.animations;
+        // Conflicts were already reported when the config was loaded
+        let keymap = KeyMap::build(&config.keys).0;
+        let message = match warnings.first() {
+            Some(warning) => format!("⚠️ Config: {}", warning),
+            None => String::from("⚡ Initializing..."),
+        };
         App {
+            active_config: config.default_config.clone(),
+            theme,
+            show_theme_popup: false,
+            theme_cursor: 0,
+            current_sort_key: config.default_sort,
+            sort_ascending: config.sort_ascending,
+            dense: config.dense,
+            config,
             snapshots: Vec::new(),
+            list_generation: 0,
+            table_widths: None,
+            space_deltas: HashMap::new(),
+            fs_usage: None,
+            boot_lineage: None,
+            config_fs: HashMap::new(),
+            show_config_info: false,
+            config_info: HashMap::new(),
             table_state: TableState::default(),
-            message: String::from("⚡ Initializing..."),
+            message,
+            toasts: Vec::new(),
             loading: true,
             loading_message: String::from("Loading..."),
-            input_mode: InputMode::Normal,
+            loading_progress: None,
+            loading_start: None,
+            fatal_error: None,
             status_text: String::new(),
+            status_range: None,
+            status_compare: false,
+            status_cursor: 0,
+            status_marked: HashSet::new(),
+            show_undo_popup: false,
+            undo_targets: Vec::new(),
+            show_diff_popup: false,
+            diff_path: String::new(),
+            diff_text: String::new(),
+            diff_scroll: 0,
+            diff_hscroll: 0,
+            diff_max_hscroll: 0,
+            focused_panel: Panel::Table,
+            side_tab: Panel::Details,
+            read_only: false,
+            remote: None,
+            no_color: false,
             details_scroll: 0,
+            details_max_scroll: 0,
             status_scroll: 0,
+            status_max_scroll: 0,
+            status_wrap: true,
+            status_hscroll: 0,
+            status_max_hscroll: 0,
             spinner_state: 0,
-            spinner_frames: vec!["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            spinner_frames,
             show_delete_popup: false,
+            delete_acknowledged: false,
+            delete_scroll: 0,
+            delete_max_scroll: 0,
+            orphan_targets: Vec::new(),
             show_create_popup: false,
-            create_input: String::new(),
+            show_export_popup: false,
+            show_goto_popup: false,
+            show_label_popup: false,
+            show_report_popup: false,
+            report_is_diff: false,
+            show_modify_popup: false,
+            show_cleanup_popup: false,
+            quota_popup: None,
+            pending_pre: None,
+            cleanup_cursor: 0,
+            modify_form: ModifyForm::default(),
+            export_input: String::new(),
+            goto_input: String::new(),
+            label_input: String::new(),
+            label_target: None,
+            // In memory until `new` loads the saved file
+            labels: Labels::default(),
+            report_input: String::new(),
+            create_form: CreateForm::default(),
             filtering: false,
             filter_input: String::new(),
+            hidden_types: HashSet::new(),
+            filter_case_sensitive: false,
+            filter_whole_word: false,
             show_apply_popup: false,
-            show_splash: true,
-            splash_start: Some(std::time::Instant::now()),
+            apply_confirm_input: String::new(),
+            show_help_popup: false,
+            show_quit_popup: false,
+            help_scroll: 0,
+            help_max_scroll: 0,
+            show_history_popup: false,
+            show_about_popup: false,
+            snapper_version: None,
+            version_requested: false,
+            history_scroll: 0,
+            history_max_scroll: 0,
+            show_splash: animations,
+            splash_until: animations.then(|| Instant::now() + SPLASH_DURATION),
             fx: None,
             fx_start: None,
-            current_sort_key: SortKey::Number,
-            sort_ascending: true,
-            rx: None,
-            selected_indices: HashSet::new(),
+            tasks: TaskQueue::default(),
+            last_nav: Instant::now(),
+            status_fetch_pending: false,
+            status_task: None,
+            selected_numbers: HashSet::new(),
+            reload_highlight: None,
+            select_anchor: None,
+            visual_base: HashSet::new(),
+            group_pairs: false,
+            expanded_pairs: HashSet::new(),
+            relative_dates: true,
+            last_refresh: None,
+            refresh_started: Instant::now(),
+            auto_refresh_task: None,
+            browse: None,
+            hit_areas: HitAreas::default(),
+            keymap,
+            config_from_cli: false,
         }
     }
 
-    pub fn refresh_snapshots(&mut self) {
-        self.loading = true;
-        self.message = String::from("🔄 Fetching snapshots...");
-        
-        match data::list_snapshots() {
-            Ok(snapshots) => {
-                self.snapshots = snapshots;
-                self.sort_snapshots();
-                self.loading = false;
-                self.message = String::from("✅ Snapshots loaded.");
-                if !self.snapshots.is_empty() {
-                    self.table_state.select(Some(0));
+    // Replace the snapshot list with freshly loaded data, keeping the user's
+    // chosen sort order.
+    pub fn set_snapshots(&mut self, snapshots: Vec<Snapshot>) {
+        self.exit_visual_mode(); // Row indices are about to change
+        self.snapshots = snapshots;
+        self.list_generation += 1;
+        self.space_deltas = space_deltas(&self.snapshots);
+        if let Err(e) = self.labels.prune(&self.snapshots) {
+            self.message = format!("❌ Could not save labels: {:#}", e);
+        }
+        // A pending pre that was deleted, or got its post elsewhere, is done
+        if let Some((config, pre)) = &self.pending_pre
+            && (!self.snapshots.iter().any(|s| s.config == *config && s.number == *pre && s.snapshot_type == "pre")
+                || self.snapshots.iter().any(|s| s.config == *config && s.pre_number == Some(*pre)))
+        {
+            self.pending_pre = None;
+        }
+        self.sort_snapshots();
+        self.last_refresh = Some(Instant::now());
+    }
+
+    /// Takes in the result of a user-requested reload. The filter applies
+    /// to the new list as it did to the old one, and the snapshot that was
+    /// highlighted when the reload was asked for is highlighted again if it
+    /// is still shown; otherwise the first row is.
+    pub fn load_snapshots(&mut self, snapshots: Vec<Snapshot>) {
+        let wanted = self.reload_highlight.take();
+        self.set_snapshots(snapshots);
+        let row = wanted.and_then(|(config, number)| self.row_of(&config, number)).unwrap_or(0);
+        let shown = !self.get_display_rows().is_empty();
+        self.table_state.select(Some(row).filter(|_| shown));
+    }
+
+    /// Swaps in a reloaded list without moving the user: the highlight
+    /// follows the same snapshot number and the scroll offset is kept.
+    pub fn apply_auto_refresh(&mut self, snapshots: Vec<Snapshot>) {
+        let current = self.get_selected_snapshot().map(|s| (s.config.clone(), s.number));
+        let index = self.table_state.selected();
+        let offset = self.table_state.offset();
+        self.set_snapshots(snapshots);
+
+        let restored = current.and_then(|(config, number)| self.row_of(&config, number));
+        // A deleted snapshot leaves the highlight at the same height
+        let len = self.get_display_rows().len();
+        let selected = restored.or(index.map(|i| i.min(len.saturating_sub(1)))).filter(|_| len > 0);
+        self.table_state.select(selected);
+        *self.table_state.offset_mut() = offset;
+    }
+
+    // Current UI state folded back into the config, ready to be written out
+    pub fn preferences(&self) -> Config {
+        let mut config = self.config.clone();
+        config.default_sort = self.current_sort_key;
+        config.sort_ascending = self.sort_ascending;
+        if !self.config_from_cli {
+            config.default_config = self.active_config.clone();
+        }
+        config.theme = self.theme.name.to_string();
+        config.dense = self.dense;
+        config.warnings.clear();
+        config
+    }
+
+    /// The filter input as parsed with the current case/whole-word options.
+    pub fn filter(&self) -> Filter {
+        Filter {
+            case_sensitive: self.filter_case_sensitive,
+            whole_word: self.filter_whole_word,
+            ..Filter::parse(&self.filter_input)
+        }
+    }
+
+    /// Snapshots the filter lets through and how many it chose from, i.e.
+    /// those of the config being viewed.
+    pub fn filter_counts(&self) -> (usize, usize) {
+        let total = self
+            .snapshots
+            .iter()
+            .filter(|s| self.active_config.as_ref().is_none_or(|config| &s.config == config))
+            .count();
+        (self.get_filtered_snapshots().len(), total)
+    }
+
+    pub fn get_filtered_snapshots(&self) -> Vec<&Snapshot> {
+        let in_config = |s: &&Snapshot| {
+            self.active_config.as_ref().is_none_or(|config| &s.config == config)
+                && !self.hidden_types.contains(&s.snapshot_type)
+        };
+        let filter = self.filter();
+        // A subsequence match has no word boundaries to respect
+        if !self.config.fuzzy_filter || self.filter_whole_word || filter.text.is_empty() {
+            return self
+                .snapshots
+                .iter()
+                .filter(in_config)
+                .filter(|s| filter.matches(s))
+                .collect();
+        }
+
+        // Best match first; the stable sort keeps the chosen order for ties
+        let mut scored: Vec<(i64, &Snapshot)> = self
+            .snapshots
+            .iter()
+            .filter(in_config)
+            .filter_map(|s| filter.score(s, true).map(|score| (score, s)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, s)| s).collect()
+    }
+
+    /// How many snapshots the filtered view holds and their combined used
+    /// space, or `None` when snapper reports no sizes (quota disabled).
+    pub fn filtered_summary(&self) -> (usize, Option<u64>) {
+        let filtered = self.get_filtered_snapshots();
+        let space = filtered
+            .iter()
+            .filter_map(|s| s.used_space)
+            .fold(None, |total: Option<u64>, n| Some(total.unwrap_or(0) + n));
+        (filtered.len(), space)
+    }
+
+    /// `(config, number)` of every `pre` snapshot that has no listed `post`
+    /// yet: the operation that took it is still running, or died before
+    /// finishing.
+    pub fn orphan_pre_numbers(&self) -> HashSet<(&str, u32)> {
+        let listed: HashSet<(&str, u32)> = self.snapshots.iter().map(|s| (s.config.as_str(), s.number)).collect();
+        let closed: HashSet<(&str, u32)> = self
+            .snapshots
+            .iter()
+            .filter(|s| s.snapshot_type == "post")
+            .filter_map(|s| s.pre_number.map(|pre| (s.config.as_str(), pre)))
+            .collect();
+        self.snapshots
+            .iter()
+            .filter(|s| s.snapshot_type == "pre" && !closed.contains(&(s.config.as_str(), s.number)))
+            .filter(|s| s.post_number.is_none_or(|post| !listed.contains(&(s.config.as_str(), post))))
+            .map(|s| (s.config.as_str(), s.number))
+            .collect()
+    }
+
+    /// Numbers about to be deleted that are orphan pre snapshots.
+    pub fn orphan_delete_targets(&self) -> Vec<u32> {
+        let orphans = self.orphan_pre_numbers();
+        self.delete_target_snapshots()
+            .iter()
+            .filter(|s| orphans.contains(&(s.config.as_str(), s.number)))
+            .map(|s| s.number)
+            .collect()
+    }
+
+    /// `(config, number)` of the shown `pre` snapshots without a listed
+    /// `post` and `post` snapshots whose `pre` is gone, in table order.
+    pub fn find_orphan_pairs(&self) -> Vec<(String, u32)> {
+        let listed: HashSet<(&str, u32)> = self.snapshots.iter().map(|s| (s.config.as_str(), s.number)).collect();
+        let orphan_pres = self.orphan_pre_numbers();
+        self.get_filtered_snapshots()
+            .iter()
+            .filter(|s| match s.snapshot_type.as_str() {
+                "pre" => orphan_pres.contains(&(s.config.as_str(), s.number)),
+                "post" => s.pre_number.is_none_or(|pre| !listed.contains(&(s.config.as_str(), pre))),
+                _ => false,
+            })
+            .map(|s| (s.config.clone(), s.number))
+            .collect()
+    }
+
+    /// Asks to delete every orphan from `find_orphan_pairs`. The selection
+    /// is left alone: it goes by number only, and another config listed
+    /// alongside may have a snapshot of the same number that isn't one.
+    pub fn open_orphan_cleanup(&mut self) {
+        if self.refuse_read_only("deleting") {
+            return;
+        }
+        let orphans = self.find_orphan_pairs();
+        if orphans.is_empty() {
+            self.message = String::from("✨ No orphan pre/post snapshots in view.");
+            return;
+        }
+        self.open_delete_popup();
+        self.orphan_targets = orphans;
+    }
+
+    /// The snapshots the delete popup removes, in table order: the orphan
+    /// cleanup's picks, or those `get_targets_for_delete` names.
+    pub fn delete_target_snapshots(&self) -> Vec<&Snapshot> {
+        let filtered = self.get_filtered_snapshots().into_iter();
+        if !self.orphan_targets.is_empty() {
+            return filtered
+                .filter(|s| self.orphan_targets.iter().any(|(config, number)| *config == s.config && *number == s.number))
+                .collect();
+        }
+        let targets = self.get_targets_for_delete();
+        filtered.filter(|s| targets.iter().any(|(config, number)| *config == s.config && *number == s.number)).collect()
+    }
+
+    /// Snapshots about to be deleted that are mounted now or booted next,
+    /// whose removal can break booting.
+    pub fn boot_critical_delete_targets(&self) -> Vec<&Snapshot> {
+        self.delete_target_snapshots().into_iter().filter(|s| s.active || s.default).collect()
+    }
+
+    /// Enter in the delete popup waits for `y` while the targets include
+    /// the active or default snapshot.
+    pub fn delete_needs_acknowledgement(&self) -> bool {
+        !self.delete_acknowledged && !self.boot_critical_delete_targets().is_empty()
+    }
+
+    /// The rows of the snapshot table: the filtered view, with pre/post
+    /// pairs folded together when `group_pairs` is on.
+    pub fn get_display_rows(&self) -> Vec<DisplayRow<'_>> {
+        let filtered = self.get_filtered_snapshots();
+        if !self.group_pairs {
+            return filtered.into_iter().map(DisplayRow::Single).collect();
+        }
+
+        // Only pairs whose both halves are visible are folded
+        let posts: HashMap<(&str, u32), &Snapshot> = filtered
+            .iter()
+            .filter(|s| s.snapshot_type == "post")
+            .filter_map(|s| s.pre_number.map(|pre| ((s.config.as_str(), pre), *s)))
+            .collect();
+        let paired_posts: HashSet<(&str, u32)> = filtered
+            .iter()
+            .filter(|s| s.snapshot_type == "pre" && posts.contains_key(&(s.config.as_str(), s.number)))
+            .map(|s| (s.config.as_str(), posts[&(s.config.as_str(), s.number)].number))
+            .collect();
+
+        let mut rows = Vec::with_capacity(filtered.len());
+        for snap in filtered {
+            let key = (snap.config.as_str(), snap.number);
+            if snap.snapshot_type == "post" && paired_posts.contains(&key) {
+                continue;
+            }
+            match posts.get(&key) {
+                Some(&post) if snap.snapshot_type == "pre" => {
+                    let expanded = self.expanded_pairs.contains(&(snap.config.clone(), snap.number));
+                    rows.push(DisplayRow::Pair { pre: snap, post, expanded });
+                    if expanded {
+                        rows.push(DisplayRow::Single(snap));
+                        rows.push(DisplayRow::Single(post));
+                    }
                 }
+                _ => rows.push(DisplayRow::Single(snap)),
             }
-            Err(e) => {
-                self.loading = false;
-                self.message = format!("❌ Error: {}", e);
+        }
+        rows
+    }
+
+    // Display row showing snapshot `number` of `config`; a folded pair
+    // holds both of its snapshots
+    fn row_of(&self, config: &str, number: u32) -> Option<usize> {
+        self.get_display_rows()
+            .iter()
+            .position(|row| row.snapshots().iter().any(|s| s.config == config && s.number == number))
+    }
+
+    pub fn get_selected_row(&self) -> Option<DisplayRow<'_>> {
+        self.table_state
+            .selected()
+            .and_then(|i| self.get_display_rows().get(i).copied())
+    }
+
+    /// The command that opens the highlighted snapshot's directory in an
+    /// outside program, or `None` with the reason in the status line.
+    pub fn external_browse_command(&mut self) -> Option<Vec<String>> {
+        let Some(snap) = self.get_selected_snapshot() else {
+            self.message = String::from("❌ Error: No snapshot selected to open.");
+            return None;
+        };
+        if snap.number == 0 {
+            self.message = String::from("ℹ️ Snapshot 0 is the live system; open it directly.");
+            return None;
+        }
+        if let Some(destination) = &self.remote {
+            self.message = format!("ℹ️ Snapshot {} is on {}; use [B] to browse it here.", snap.number, destination);
+            return None;
+        }
+        let dir = data::snapshot_path(&snap.subvolume, snap.number);
+        Some(external_browser(|name| std::env::var(name).ok(), &dir))
+    }
+
+    /// Mounts the highlighted snapshot and shows its root in the right panel.
+    pub fn open_browse(&mut self) {
+        let Some(snap) = self.get_selected_snapshot().cloned() else {
+            self.message = String::from("❌ Error: No snapshot selected to browse.");
+            return;
+        };
+        if snap.number == 0 {
+            self.message = String::from("ℹ️ Snapshot 0 is the live system; browse it directly.");
+            return;
+        }
+        self.loading = true;
+        self.loading_message = format!("Mounting snapshot {}...", snap.number);
+        let privilege = self.config.privilege;
+        self.tasks.spawn(move |cancel| {
+            let root = data::mount_snapshot(&snap, privilege, cancel)?;
+            let entries = data::list_directory(&root, privilege, cancel)?;
+            Ok(AsyncResult::Mounted { config: snap.config, number: snap.number, root, entries })
+        });
+    }
+
+    pub fn set_browse_listing(&mut self, dir: PathBuf, entries: Vec<DirEntry>) {
+        if let Some(browse) = self.browse.as_mut() {
+            browse.dir = dir;
+            browse.entries = entries;
+            browse.cursor = 0;
+            browse.file = None;
+        }
+    }
+
+    pub fn browse_move(&mut self, up: bool) {
+        let Some(browse) = self.browse.as_mut() else {
+            return;
+        };
+        if browse.file.is_some() {
+            browse.file_scroll = if up { browse.file_scroll.saturating_sub(1) } else { browse.file_scroll + 1 };
+        } else if up {
+            browse.cursor = browse.cursor.saturating_sub(1);
+        } else {
+            browse.cursor = (browse.cursor + 1).min(browse.entries.len().saturating_sub(1));
+        }
+    }
+
+    /// Descends into the directory under the cursor or previews the file.
+    pub fn browse_open(&mut self) {
+        let Some(browse) = self.browse.as_ref().filter(|b| b.file.is_none()) else {
+            return;
+        };
+        let Some(entry) = browse.entries.get(browse.cursor) else {
+            return;
+        };
+        let path = browse.dir.join(&entry.name);
+        let privilege = self.config.privilege;
+        if entry.is_dir {
+            self.spawn_listing(path);
+        } else {
+            self.loading = true;
+            self.loading_message = format!("Reading {}...", entry.name);
+            self.tasks.spawn(move |cancel| {
+                data::read_file_head(&path, privilege, cancel)
+                    .map(|text| AsyncResult::FileView { path, text })
+                    .map_err(TaskError::from)
+            });
+        }
+    }
+
+    /// Leaves the file preview, or goes up one directory.
+    pub fn browse_back(&mut self) {
+        let Some(browse) = self.browse.as_mut() else {
+            return;
+        };
+        if browse.file.take().is_some() {
+            return;
+        }
+        if browse.dir != browse.root
+            && let Some(parent) = browse.dir.parent().map(PathBuf::from)
+        {
+            self.spawn_listing(parent);
+        }
+    }
+
+    fn spawn_listing(&mut self, dir: PathBuf) {
+        let privilege = self.config.privilege;
+        self.loading = true;
+        self.loading_message = format!("Listing {}...", dir.display());
+        self.tasks.spawn(move |cancel| {
+            data::list_directory(&dir, privilege, cancel)
+                .map(|entries| AsyncResult::Listing { dir, entries })
+                .map_err(TaskError::from)
+        });
+    }
+
+    /// The modal keys go to, in the order `run_app` checks them.
+    pub fn open_modal(&self) -> Option<Modal> {
+        let open = [
+            (self.show_quit_popup, Modal::Quit),
+            (self.show_help_popup, Modal::Help),
+            (self.show_about_popup, Modal::About),
+            (self.show_history_popup, Modal::History),
+            (self.show_theme_popup, Modal::Theme),
+            (self.quota_popup.is_some(), Modal::Quota),
+            (self.show_cleanup_popup, Modal::Cleanup),
+            (self.show_report_popup, Modal::Report),
+            (self.show_diff_popup, Modal::Diff),
+            (self.show_undo_popup, Modal::Undo),
+            (self.show_delete_popup, Modal::Delete),
+            (self.show_apply_popup, Modal::Apply),
+            (self.show_modify_popup, Modal::Modify),
+            (self.show_goto_popup, Modal::Goto),
+            (self.show_label_popup, Modal::Label),
+            (self.show_export_popup, Modal::Export),
+            (self.show_create_popup, Modal::Create),
+            (self.browse.is_some(), Modal::Browse),
+            (self.filtering, Modal::Filter),
+        ];
+        open.into_iter().find_map(|(shown, modal)| shown.then_some(modal))
+    }
+
+    /// What Esc does: closes the topmost modal, dropping what was typed
+    /// into it, and goes back to the view below. `false` when none is open.
+    pub fn close_modal(&mut self) -> bool {
+        let Some(modal) = self.open_modal() else {
+            return false;
+        };
+        match modal {
+            Modal::Quit => self.show_quit_popup = false,
+            Modal::Help => self.show_help_popup = false,
+            Modal::About => self.show_about_popup = false,
+            Modal::History => self.show_history_popup = false,
+            Modal::Theme => self.show_theme_popup = false,
+            Modal::Quota => self.quota_popup = None,
+            Modal::Cleanup => self.show_cleanup_popup = false,
+            Modal::Report => self.show_report_popup = false,
+            Modal::Diff => self.show_diff_popup = false,
+            Modal::Undo => self.show_undo_popup = false,
+            Modal::Delete => self.show_delete_popup = false,
+            Modal::Apply => self.show_apply_popup = false,
+            Modal::Modify => self.show_modify_popup = false,
+            Modal::Goto => self.show_goto_popup = false,
+            Modal::Label => self.show_label_popup = false,
+            Modal::Export => self.show_export_popup = false,
+            Modal::Create => self.show_create_popup = false,
+            Modal::Browse => self.close_browse(),
+            Modal::Filter => {
+                self.filtering = false;
+                self.filter_input.clear();
+                self.table_state.select(Some(0));
             }
         }
+        true
     }
 
-    pub fn get_filtered_snapshots(&self) -> Vec<&Snapshot> {
-        if self.filter_input.is_empty() {
-            self.snapshots.iter().collect()
+    pub fn close_browse(&mut self) {
+        let Some(browse) = self.browse.take() else {
+            return;
+        };
+        let privilege = self.config.privilege;
+        self.tasks.spawn_background(move |cancel| {
+            data::umount_snapshot(&browse.config, browse.number, privilege, cancel)
+                .map(|_| AsyncResult::Unmounted(browse.number))
+                .map_err(TaskError::from)
+        });
+    }
+
+    /// Unmounts a still-browsed snapshot on the way out, waiting for it.
+    pub fn unmount_on_exit(&mut self) {
+        if let Some(browse) = self.browse.take() {
+            let _ = data::umount_snapshot(&browse.config, browse.number, self.config.privilege, &data::CancelToken::default());
+        }
+    }
+
+    /// Quitting now would abandon a running operation or a selection.
+    pub fn quit_needs_confirm(&self) -> bool {
+        self.loading || self.get_selected_count() > 0
+    }
+
+    pub fn toggle_relative_dates(&mut self) {
+        self.relative_dates = !self.relative_dates;
+        self.message = String::from(if self.relative_dates {
+            "🕒 Showing relative dates."
         } else {
-            self.snapshots
-                .iter()
-                .filter(|s| {
-                    s.description.to_lowercase().contains(&self.filter_input.to_lowercase())
-                        || s.snapshot_type.to_lowercase().contains(&self.filter_input.to_lowercase())
-                        || s.user.to_lowercase().contains(&self.filter_input.to_lowercase())
-                        || s.number.to_string().contains(&self.filter_input)
-                })
-                .collect()
+            "🕒 Showing absolute dates."
+        });
+    }
+
+    pub fn toggle_dense(&mut self) {
+        self.dense = !self.dense;
+        self.message = String::from(if self.dense { "📐 Dense view." } else { "📐 Roomy view." });
+    }
+
+    /// A snapshot date as the table's Date column shows it.
+    pub fn display_date(&self, date: &str) -> String {
+        if self.relative_dates {
+            format_relative(date)
+        } else {
+            // ISO dates are shown the same way as snapper's classic ones
+            data::parse_date(date).map_or_else(|| date.to_string(), data::format_local_time)
+        }
+    }
+
+    pub fn toggle_grouping(&mut self) {
+        let current = self.get_selected_snapshot().map(|s| (s.config.clone(), s.number));
+        self.exit_visual_mode();
+        self.group_pairs = !self.group_pairs;
+        self.message = String::from(if self.group_pairs {
+            "⛓️ Grouping pre/post pairs."
+        } else {
+            "⛓️ Showing pre/post snapshots separately."
+        });
+
+        // Keep the highlight on the same snapshot, or the pair containing it
+        let index = current.and_then(|(config, number)| {
+            self.get_display_rows().iter().position(|row| {
+                row.snapshots().iter().any(|s| s.config == config && s.number == number)
+            })
+        });
+        self.table_state.select(index.or(Some(0)));
+    }
+
+    /// Expands or collapses the pair under the highlight. Collapsing from
+    /// one of its member rows moves the highlight back to the pair row.
+    pub fn set_pair_expanded(&mut self, expand: bool) {
+        let Some(index) = self.table_state.selected() else {
+            return;
+        };
+        let rows = self.get_display_rows();
+        let pair = match rows.get(index) {
+            Some(DisplayRow::Pair { pre, .. }) => Some(((pre.config.clone(), pre.number), index)),
+            Some(DisplayRow::Single(_)) if !expand => (1..=2)
+                .filter_map(|back| index.checked_sub(back))
+                .find_map(|i| match rows[i] {
+                    DisplayRow::Pair { pre, expanded: true, .. } => Some(((pre.config.clone(), pre.number), i)),
+                    _ => None,
+                }),
+            _ => None,
+        };
+        if let Some((pre, pair_index)) = pair {
+            self.exit_visual_mode();
+            if expand {
+                self.expanded_pairs.insert(pre);
+            } else {
+                self.expanded_pairs.remove(&pre);
+                self.table_state.select(Some(pair_index));
+            }
         }
     }
 
     pub fn next(&mut self) {
-        let filtered_len = self.get_filtered_snapshots().len();
+        let filtered_len = self.get_display_rows().len();
         if filtered_len > 0 {
             let i = match self.table_state.selected() {
                 Some(i) => {
                     if i >= filtered_len - 1 {
-                        0
+                        if self.config.wrap_navigation { 0 } else { filtered_len - 1 }
                     } else {
                         i + 1
                     }
@@ -136,16 +1268,17 @@ impl App {
                 None => 0,
             };
             self.table_state.select(Some(i));
+            self.update_visual_selection();
         }
     }
 
     pub fn previous(&mut self) {
-        let filtered_len = self.get_filtered_snapshots().len();
+        let filtered_len = self.get_display_rows().len();
         if filtered_len > 0 {
             let i = match self.table_state.selected() {
                 Some(i) => {
                     if i == 0 {
-                        filtered_len - 1
+                        if self.config.wrap_navigation { filtered_len - 1 } else { 0 }
                     } else {
                         i - 1
                     }
@@ -153,184 +1286,2836 @@ impl App {
                 None => 0,
             };
             self.table_state.select(Some(i));
+            self.update_visual_selection();
         }
     }
 
+    // The table renders the display rows, so the highlighted row index is
+    // always resolved against them rather than against `self.snapshots`.
     pub fn get_selected_snapshot(&self) -> Option<&Snapshot> {
-        self.table_state.selected().and_then(|i| self.snapshots.get(i))
+        self.get_selected_row().map(|row| row.primary())
+    }
+
+    // Selected snapshots that are visible in the filtered view, in table
+    // order. Hidden rows never take part in bulk operations.
+    pub fn get_visible_selection(&self) -> Vec<(String, u32)> {
+        self.get_filtered_snapshots()
+            .iter()
+            .filter(|snapshot| self.is_selected(snapshot))
+            .map(|snapshot| (snapshot.config.clone(), snapshot.number))
+            .collect()
+    }
+
+    pub fn is_selected(&self, snapshot: &Snapshot) -> bool {
+        self.selected_numbers.contains(&(snapshot.config.clone(), snapshot.number))
     }
 
-    pub fn get_targets_for_delete(&self) -> Vec<u32> {
-        if !self.selected_indices.is_empty() {
+    /// `(config, number)` of each snapshot a delete removes: the visible
+    /// selection, else the highlighted row.
+    pub fn get_targets_for_delete(&self) -> Vec<(String, u32)> {
+        let selection = self.get_visible_selection();
+        if !selection.is_empty() {
             // Delete all selected snapshots
-            self.selected_indices.iter()
-                .filter_map(|&idx| self.snapshots.get(idx))
-                .map(|snapshot| snapshot.number)
-                .collect()
-        } else if let Some(idx) = self.table_state.selected() {
-            // Delete single currently highlighted snapshot
-            if let Some(snapshot) = self.snapshots.get(idx) {
-                vec![snapshot.number]
-            } else {
-                vec![]
-            }
+            selection
+        } else if let Some(row) = self.get_selected_row() {
+            // Delete the highlighted snapshot, or both halves of a pair,
+            // in its own config
+            row.snapshots().iter().map(|s| (s.config.clone(), s.number)).collect()
         } else {
             vec![]
         }
     }
 
-    pub fn handle_delete_result(&mut self, success_count: usize, error_count: usize) {
-        // Update message
-        if success_count > 0 {
-            self.message = if success_count == 1 {
-                format!("🗑️ Deleted 1 snapshot")
+    pub fn handle_delete_result(&mut self, succeeded: &[u32], failed: &[(u32, String)]) {
+        let mut text = match succeeded.len() {
+            0 => String::new(),
+            1 => String::from("🗑️ Deleted 1 snapshot"),
+            n => format!("🗑️ Deleted {} snapshots", n),
+        };
+        if !failed.is_empty() {
+            let numbers: Vec<String> = failed.iter().map(|(number, _)| format!("#{}", number)).collect();
+            if text.is_empty() {
+                text = format!("❌ Failed to delete {}", numbers.join(", "));
             } else {
-                format!("🗑️ Deleted {} snapshots", success_count)
-            };
-            if error_count > 0 {
-                self.message.push_str(&format!(" ({} failed ❌)", error_count));
+                text.push_str(&format!(" ({} failed ❌)\n  Failed: {}", failed.len(), numbers.join(", ")));
             }
-        } else if error_count > 0 {
-            self.message = format!("❌ Failed to delete {} snapshot(s)", error_count);
+
+            // The reasons can be long, so they go to the scrollable status
+            // panel rather than the toast
+            if let Some(id) = self.status_task.take() {
+                self.tasks.cancel(id);
+            }
+            self.status_text = failed.iter().map(|(number, reason)| format!("#{}  {}", number, reason)).collect::<Vec<_>>().join("\n");
+            self.status_range = None;
+            self.status_compare = false;
+            self.status_scroll = 0;
+            self.status_cursor = 0;
+            self.status_marked.clear();
+            self.message = format!("❌ {} snapshot(s) could not be deleted; retry these:", failed.len());
+        }
+        if !text.is_empty() {
+            let severity = if failed.is_empty() { Severity::Success } else { Severity::Error };
+            self.toast(severity, text);
         }
 
-        // Clear selections and refresh
+        // Clear selections; main.rs triggers the refresh
         self.clear_selections();
-        // Note: Refreshing snapshots should be done by the caller (main.rs) via thread
-        // or we can trigger it here if we move the thread logic? 
-        // For now, main.rs handles the refresh trigger.
     }
 
     pub fn get_target_for_apply(&self) -> Option<u32> {
         self.get_selected_snapshot().map(|s| s.number)
     }
-    
-    pub fn get_status_selected_snapshot(&mut self) {
-         if let Some(snap) = self.get_selected_snapshot().cloned() {
-            self.message = format!("⏳ Fetching status for {}...", snap.number);
-            match data::get_snapshot_status(&snap) {
-                Ok(status) => {
-                    self.status_text = status;
-                    self.message = format!("✅ Status loaded for snapshot {}.", snap.number);
-                    self.status_scroll = 0; // Reset scroll
-                }
-                Err(e) => {
-                    self.message = format!("❌ Error getting status: {}", e);
-                    self.status_text.clear();
-                }
-            }
-        }
+
+    pub fn open_goto_popup(&mut self) {
+        self.goto_input.clear();
+        self.show_goto_popup = true;
     }
 
-    pub fn on_tick(&mut self) {
-        if self.loading {
-            self.spinner_state = (self.spinner_state + 1) % self.spinner_frames.len();
+    /// Highlights the row holding the snapshot numbered `goto_input` in the
+    /// filtered view; the table scrolls it into view when drawn.
+    pub fn goto_snapshot(&mut self) {
+        self.show_goto_popup = false;
+        let Ok(number) = self.goto_input.parse::<u32>() else {
+            return;
+        };
+        let Some(config) = self.get_filtered_snapshots().iter().find(|s| s.number == number).map(|s| s.config.clone()) else {
+            self.message = format!("❌ Snapshot {} is not in the list.", number);
+            return;
+        };
+        let row = self.row_of(&config, number);
+        self.exit_visual_mode();
+        self.table_state.select(row);
+        self.schedule_status_fetch();
+    }
+
+    /// Asks for a label for the highlighted snapshot, the post of a pair,
+    /// starting from the one it already has.
+    pub fn open_label_popup(&mut self) {
+        let Some(snapshot) = self.get_selected_snapshot() else {
+            self.message = String::from("ℹ️ No snapshot highlighted to label.");
+            return;
+        };
+        let target = (snapshot.config.clone(), snapshot.number);
+        self.label_input = self.labels.get(&target.0, target.1).unwrap_or_default().to_string();
+        self.label_target = Some(target);
+        self.show_label_popup = true;
+    }
+
+    /// Saves the label typed into the label popup; a blank one removes it.
+    pub fn save_label(&mut self) {
+        self.show_label_popup = false;
+        let Some((config, number)) = self.label_target.take() else {
+            return;
+        };
+        let removed = self.label_input.trim().is_empty();
+        self.list_generation += 1;
+        self.message = match self.labels.set(&config, number, &self.label_input) {
+            Err(e) => format!("❌ Could not save labels: {:#}", e),
+            Ok(()) if removed => format!("🔖 Removed the label of snapshot {}.", number),
+            Ok(()) => format!("🔖 Labelled snapshot {}.", number),
+        };
+    }
+
+    /// The label shown for `row`; a pair falls back to its pre's.
+    pub fn row_label(&self, row: DisplayRow) -> Option<&str> {
+        row.snapshots().iter().rev().find_map(|s| self.labels.get(&s.config, s.number))
+    }
+
+    pub fn open_export_popup(&mut self) {
+        self.export_input = String::from("snapshots.csv");
+        self.show_export_popup = true;
+    }
+
+    /// Writes the rows currently on screen, in on-screen order, to the path
+    /// typed into the export popup.
+    pub fn export_filtered(&mut self) {
+        let path = expand_home(&self.export_input);
+        let snaps: Vec<Snapshot> = self.get_filtered_snapshots().into_iter().cloned().collect();
+        let format = ExportFormat::from_path(&path);
+        self.message = match data::export_snapshots(&snaps, &path, format) {
+            Ok(()) => format!("✅ Exported {} snapshots to {}", snaps.len(), path.display()),
+            Err(e) => format!("❌ Export failed: {:#}", e),
+        };
+    }
+
+    /// Asks where to save the status listing, or the open diff when
+    /// `diff` is set.
+    pub fn open_report_popup(&mut self, diff: bool) {
+        let Some(range) = &self.status_range else {
+            self.message = String::from("ℹ️ No status to save; press S to load one first.");
+            return;
+        };
+        let kind = if diff { "diff" } else { "status" };
+        self.report_input = format!("snapper-{}-{}-{}.txt", kind, range.pre, range.post);
+        self.report_is_diff = diff;
+        self.show_report_popup = true;
+    }
+
+    /// Writes the report to the path typed into the report popup; a failure
+    /// is shown in the status panel.
+    pub fn save_report(&mut self) {
+        self.show_report_popup = false;
+        let Some(range) = &self.status_range else {
+            return;
+        };
+        let path = expand_home(&self.report_input);
+        let time = data::format_local_time(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0));
+        let (file, body) = match self.report_is_diff {
+            true => (Some(self.diff_path.as_str()), &self.diff_text),
+            false => (None, &self.status_text),
+        };
+        let mut contents = data::report_header(range, file, &time) + body;
+        if !contents.ends_with('\n') {
+            contents.push('\n');
         }
+        self.message = match data::save_report(&path, &contents) {
+            Ok(()) => format!("✅ Report saved to {}", path.display()),
+            Err(e) => format!("❌ Report not saved: {:#}", e),
+        };
     }
 
-    pub fn scroll_details(&mut self, up: bool) {
-        if up {
-            if self.details_scroll > 0 {
-                self.details_scroll -= 1;
-            }
+    /// Copies the highlighted snapshot's details, or the whole filtered
+    /// table as TSV while snapshots are multi-selected.
+    pub fn copy_to_clipboard(&mut self) {
+        let (text, what) = if self.get_selected_count() > 0 {
+            let snaps = self.get_filtered_snapshots();
+            (data::snapshots_to_tsv(&snaps), format!("{} snapshots", snaps.len()))
+        } else if let Some(snap) = self.get_selected_snapshot() {
+            (data::snapshot_summary(snap), format!("snapshot {}", snap.number))
         } else {
-            self.details_scroll += 1;
+            self.message = String::from("❌ Error: No snapshot selected to copy.");
+            return;
+        };
+        self.message = match clipboard::copy(&text) {
+            Ok(Copied::Clipboard(tool)) => format!("📋 Copied {} to the clipboard ({}).", what, tool),
+            Ok(Copied::File(path)) => format!("📋 No clipboard available; {} saved to {}", what, path.display()),
+            Err(e) => format!("❌ Copy failed: {:#}", e),
+        };
+    }
+
+    // Refuses a command that changes snapshots in read-only mode; true
+    // when it was refused
+    fn refuse_read_only(&mut self, what: &str) -> bool {
+        if self.read_only {
+            self.message = format!("🔒 Read-only: {} needs root, and {} isn't available.", what, self.config.privilege.name());
         }
+        self.read_only
     }
 
-    pub fn scroll_status(&mut self, up: bool) {
-        if up {
-            if self.status_scroll > 0 {
-                self.status_scroll -= 1;
-            }
-        } else {
-            self.status_scroll += 1;
+    pub fn open_delete_popup(&mut self) {
+        if !self.refuse_read_only("deleting") {
+            self.delete_acknowledged = false;
+            self.delete_scroll = 0;
+            self.orphan_targets.clear();
+            self.show_delete_popup = true;
         }
     }
 
-    pub fn set_sort_key(&mut self, key: SortKey) {
-        // Toggle ascending/descending if same key
-        if matches!((&self.current_sort_key, &key),
-            (SortKey::Number, SortKey::Number) |
-            (SortKey::Type, SortKey::Type) |
-            (SortKey::Date, SortKey::Date) |
-            (SortKey::User, SortKey::User) |
-            (SortKey::UsedSpace, SortKey::UsedSpace))
+    pub fn open_create_popup(&mut self) {
+        if self.refuse_read_only("creating a snapshot") {
+            return;
+        }
+        let mut values: [String; 4] = Default::default();
+        values[1] = String::from("single");
+        self.create_form = CreateForm { values, field: 0 };
+        self.show_create_popup = true;
+    }
+
+    pub fn create_move_field(&mut self, up: bool) {
+        let count = CREATE_FIELDS.len();
+        let field = &mut self.create_form.field;
+        *field = if up { (*field + count - 1) % count } else { (*field + 1) % count };
+    }
+
+    pub fn create_input(&mut self) -> &mut String {
+        &mut self.create_form.values[self.create_form.field]
+    }
+
+    /// The snapshot the create form describes, checked before anything runs.
+    /// A `post` snapshot closes the highlighted `pre` one.
+    pub fn new_snapshot(&self) -> anyhow::Result<data::NewSnapshot> {
+        let [description, snapshot_type, cleanup, userdata] = &self.create_form.values;
+        if description.trim().is_empty() {
+            anyhow::bail!("The new snapshot needs a description");
+        }
+        let snapshot_type = snapshot_type.trim().to_lowercase();
+        // A post goes into its pre's config
+        let (pre_number, config) = match snapshot_type.as_str() {
+            "single" | "pre" => (None, None),
+            // The highlighted pre, else the one this session is waiting on
+            "post" => match (self.get_selected_snapshot(), &self.pending_pre) {
+                (Some(snap), _) if snap.snapshot_type == "pre" => (Some(snap.number), Some(snap.config.clone())),
+                (_, Some((config, pre))) => (Some(*pre), Some(config.clone())),
+                _ => anyhow::bail!("Highlight the pre snapshot that the post snapshot belongs to"),
+            },
+            other => anyhow::bail!("Type '{}' is not single, pre or post", other),
+        };
+        let cleanup = Some(cleanup.trim()).filter(|c| !c.is_empty());
+        if let Some(cleanup) = cleanup
+            && !data::CLEANUP_ALGORITHMS.contains(&cleanup)
         {
-            self.sort_ascending = !self.sort_ascending;
-        } else {
-            self.current_sort_key = key;
-            self.sort_ascending = true;
+            anyhow::bail!("Cleanup '{}' is not one of {}", cleanup, data::CLEANUP_ALGORITHMS.join(", "));
         }
-        self.sort_snapshots();
+        let userdata = data::parse_userdata(userdata)?;
+        Ok(data::NewSnapshot {
+            description: description.clone(),
+            snapshot_type,
+            pre_number,
+            cleanup: cleanup.map(str::to_string),
+            userdata: self.tagged(userdata),
+            config,
+        })
     }
 
-    pub fn sort_snapshots(&mut self) {
-        match self.current_sort_key {
-            SortKey::Number => {
-                self.snapshots.sort_by_key(|s| s.number);
+    // Adds the created-here tag when enabled; a value typed into the form
+    // wins over it
+    fn tagged(&self, mut userdata: HashMap<String, String>) -> Option<HashMap<String, String>> {
+        if self.config.tag_created {
+            userdata.entry(self.config.created_tag_key.clone()).or_insert_with(|| CREATED_TAG_VALUE.to_string());
+        }
+        Some(userdata).filter(|u| !u.is_empty())
+    }
+
+    /// Creates a single snapshot described by `default_description_template`
+    /// straight away, without the create popup.
+    pub fn quick_create(&mut self) {
+        if self.refuse_read_only("creating a snapshot") {
+            return;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+        let snapshot = data::NewSnapshot {
+            description: expand_description(&self.config.default_description_template, now),
+            snapshot_type: String::from("single"),
+            userdata: self.tagged(HashMap::new()),
+            ..Default::default()
+        };
+        self.start_create(snapshot);
+    }
+
+    /// Runs `snapper create`; the list is reloaded when it is done.
+    pub fn start_create(&mut self, snapshot: data::NewSnapshot) {
+        self.show_create_popup = false;
+        self.loading = true;
+        self.loading_message = String::from("Creating snapshot...");
+        let privilege = self.config.privilege;
+        self.tasks.spawn(move |cancel| {
+            let number = data::create_snapshot(&snapshot, privilege, cancel)?;
+            Ok(AsyncResult::Create(snapshot, number))
+        });
+    }
+
+    /// Reports a finished `snapper create`. A new `pre` snapshot is kept as
+    /// `pending_pre` until its post is taken.
+    pub fn handle_create_result(&mut self, snapshot: &data::NewSnapshot, number: Option<u32>) {
+        let name = match number {
+            Some(number) => format!("#{} {}", number, snapshot.description),
+            None => snapshot.description.clone(),
+        };
+        let config = snapshot.config.clone().unwrap_or_else(|| String::from("root"));
+        if snapshot.snapshot_type == "pre"
+            && let Some(number) = number
+        {
+            self.pending_pre = Some((config, number));
+            self.toast(Severity::Success, format!("✅ Pre snapshot created: {}. Press [>] for its post when you're done.", name));
+            return;
+        }
+        if snapshot.pre_number.is_some_and(|pre| self.pending_pre == Some((config, pre))) {
+            self.pending_pre = None;
+        }
+        self.toast(Severity::Success, format!("✅ Snapshot created: {}", name));
+    }
+
+    /// Takes the `post` snapshot of `pending_pre`.
+    pub fn create_matching_post(&mut self) {
+        if self.refuse_read_only("creating a snapshot") {
+            return;
+        }
+        match self.matching_post() {
+            Some(snapshot) => self.start_create(snapshot),
+            None => self.message = String::from("ℹ️ No pre snapshot is waiting for its post; create one with type pre first."),
+        }
+    }
+
+    /// The `post` that closes `pending_pre` in its config, with the pre's
+    /// description and cleanup algorithm.
+    pub fn matching_post(&self) -> Option<data::NewSnapshot> {
+        let (config, pre_number) = self.pending_pre.clone()?;
+        let pre = self.snapshots.iter().find(|s| s.config == config && s.number == pre_number && s.snapshot_type == "pre");
+        Some(data::NewSnapshot {
+            description: pre.map_or_else(|| format!("post of #{}", pre_number), |pre| pre.description.clone()),
+            snapshot_type: String::from("post"),
+            pre_number: Some(pre_number),
+            cleanup: pre.and_then(|pre| pre.cleanup.clone()).filter(|cleanup| !cleanup.is_empty()),
+            userdata: self.tagged(HashMap::new()),
+            config: Some(config),
+        })
+    }
+
+    /// Whether `snap` carries the tag the create popup adds.
+    pub fn created_here(&self, snap: &Snapshot) -> bool {
+        snap.userdata
+            .as_ref()
+            .and_then(|data| data.get(&self.config.created_tag_key))
+            .is_some_and(|value| value == CREATED_TAG_VALUE)
+    }
+
+    pub fn open_modify_popup(&mut self) {
+        if self.refuse_read_only("modifying a snapshot") {
+            return;
+        }
+        let Some(snap) = self.get_selected_snapshot() else {
+            self.message = String::from("❌ Error: No snapshot selected to modify.");
+            return;
+        };
+        let values = [
+            snap.description.clone(),
+            snap.cleanup.clone().unwrap_or_default(),
+            data::format_userdata(snap.userdata.as_ref()),
+        ];
+        self.modify_form = ModifyForm {
+            config: snap.config.clone(),
+            number: snap.number,
+            original: values.clone(),
+            values,
+            field: 0,
+        };
+        self.show_modify_popup = true;
+    }
+
+    pub fn modify_move_field(&mut self, up: bool) {
+        let count = MODIFY_FIELDS.len();
+        let field = &mut self.modify_form.field;
+        *field = if up { (*field + count - 1) % count } else { (*field + 1) % count };
+    }
+
+    pub fn modify_input(&mut self) -> &mut String {
+        &mut self.modify_form.values[self.modify_form.field]
+    }
+
+    /// The edited fields of the modify form, or `None` if nothing changed.
+    pub fn modify_changes(&self) -> anyhow::Result<Option<ModifyChanges>> {
+        let form = &self.modify_form;
+        let changed = |i: usize| (form.values[i] != form.original[i]).then(|| form.values[i].clone());
+        let userdata = changed(2).map(|text| data::parse_userdata(&text)).transpose()?;
+        let changes = ModifyChanges {
+            config: form.config.clone(),
+            number: form.number,
+            description: changed(0),
+            cleanup: changed(1),
+            userdata,
+        };
+        if changes.description.is_none() && changes.cleanup.is_none() && changes.userdata.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(changes))
+    }
+
+    pub fn open_apply_popup(&mut self) {
+        if self.refuse_read_only("a rollback") {
+            return;
+        }
+        if self.get_selected_count() > 0 {
+            self.message = "❌ Error: Cannot apply with multi-selection active. Clear selections first (select with space to deselect).".to_string();
+            return;
+        }
+        if let Some(reason) = self.rollback_refusal() {
+            self.message = reason;
+            return;
+        }
+        self.apply_confirm_input.clear();
+        self.show_apply_popup = true;
+    }
+
+    fn selected_fs(&self) -> Option<&ConfigFs> {
+        self.get_selected_snapshot().and_then(|s| self.config_fs.get(&s.config)?.as_ref())
+    }
+
+    /// Whether the selected snapshot's config can be rolled back to.
+    pub fn apply_supported(&self) -> bool {
+        self.rollback_refusal().is_none()
+    }
+
+    /// Why the selected snapshot can't be rolled back to, if it can't.
+    /// snapper only rolls back the config of the system root, and only on
+    /// btrfs; a filesystem not known yet is assumed to be btrfs.
+    pub fn rollback_refusal(&self) -> Option<String> {
+        let snap = self.get_selected_snapshot()?;
+        if !snap.subvolume.is_empty() && snap.subvolume != "/" {
+            return Some(format!(
+                "❌ Rollback only applies to the system root, and {} is {}; use undo (u) to revert its files instead.",
+                snap.config, snap.subvolume
+            ));
+        }
+        match self.selected_fs() {
+            Some(fs) if !fs.supports_rollback() => {
+                Some(format!("❌ Rollback isn't supported on {}; use undo (u) to revert files instead.", fs.fstype))
             }
-            SortKey::Type => {
-                self.snapshots.sort_by(|a, b| a.snapshot_type.cmp(&b.snapshot_type));
+            _ => None,
+        }
+    }
+
+    /// Whether Enter in the apply popup may start the rollback. With
+    /// `confirm_apply_by_number` set, the target number must be typed first.
+    pub fn apply_confirmed(&self) -> bool {
+        match self.get_target_for_apply() {
+            Some(number) => {
+                !self.config.confirm_apply_by_number
+                    || self.apply_confirm_input == number.to_string()
             }
-            SortKey::Date => {
-                self.snapshots.sort_by(|a, b| a.date.cmp(&b.date));
+            None => false,
+        }
+    }
+    
+    /// Marks the highlighted snapshot's status as wanted; `on_tick` fetches
+    /// it once navigation has paused for `STATUS_DEBOUNCE`.
+    pub fn schedule_status_fetch(&mut self) {
+        self.last_nav = Instant::now();
+        self.status_fetch_pending = true;
+    }
+
+    fn fetch_selected_status(&mut self) {
+        if let Some((number, range)) = self.selected_status_range() {
+            self.message = format!("⏳ Fetching status for {}...", number);
+            self.start_status_fetch(range, false, false);
+        }
+    }
+
+    /// Fetches the highlighted snapshot's status behind the loading overlay.
+    pub fn request_status(&mut self) {
+        // On a narrow terminal, bring the status tab up to show it in,
+        // unless details has the focus and would be hidden with it
+        if self.focused_panel != Panel::Details {
+            self.side_tab = Panel::Status;
+        }
+        if let Some((number, range)) = self.selected_status_range() {
+            self.loading = true;
+            self.loading_message = format!("Fetching status for {}...", number);
+            self.start_status_fetch(range, false, true);
+        }
+    }
+
+    // The highlighted snapshot's number and status range. Without a range
+    // the panel is cleared and says so, rather than showing a stale listing.
+    fn selected_status_range(&mut self) -> Option<(u32, StatusRange)> {
+        let snap = self.get_selected_snapshot()?;
+        let number = snap.number;
+        if let Some(range) = data::status_range(snap) {
+            return Some((number, range));
+        }
+
+        if let Some(id) = self.status_task.take() {
+            self.tasks.cancel(id);
+        }
+        self.status_text.clear();
+        self.status_range = None;
+        self.status_compare = false;
+        self.status_scroll = 0;
+        self.status_cursor = 0;
+        self.status_marked.clear();
+        self.message = format!("ℹ️ No comparison range available for snapshot {}.", number);
+        None
+    }
+
+    /// The two multi-selected snapshots, lower number first, when exactly two
+    /// of the same config are selected in the filtered view.
+    pub fn comparison_range(&self) -> Result<StatusRange, String> {
+        let selected: Vec<&Snapshot> = self
+            .get_filtered_snapshots()
+            .into_iter()
+            .filter(|s| self.is_selected(s))
+            .collect();
+        let [a, b] = selected[..] else {
+            return Err(format!("Select exactly two snapshots to compare ({} selected)", selected.len()));
+        };
+        if a.config != b.config {
+            return Err(format!("Cannot compare snapshots of {} and {}", a.config, b.config));
+        }
+        Ok(StatusRange {
+            config: a.config.clone(),
+            pre: a.number.min(b.number),
+            post: a.number.max(b.number),
+        })
+    }
+
+    /// Lists the changes between the two multi-selected snapshots.
+    pub fn request_compare(&mut self) {
+        match self.comparison_range() {
+            Ok(range) => {
+                self.loading = true;
+                self.loading_message = format!("Comparing {} and {}...", range.pre, range.post);
+                self.start_status_fetch(range, true, true);
             }
-            SortKey::User => {
-                self.snapshots.sort_by(|a, b| a.user.cmp(&b.user));
+            Err(e) => self.message = format!("❌ Error: {}", e),
+        }
+    }
+
+    /// The status key and button: two selected snapshots are compared with
+    /// each other, else the highlighted one's changes are listed.
+    pub fn status_or_compare(&mut self) {
+        if self.get_selected_count() > 0 {
+            self.request_compare();
+        } else {
+            self.request_status();
+        }
+    }
+
+    // Streams the status into the panel as snapper prints it
+    fn start_status_fetch(&mut self, range: StatusRange, compare: bool, blocking: bool) {
+        // Only the latest highlighted row's status is of interest
+        if let Some(id) = self.status_task.take() {
+            self.tasks.cancel(id);
+        }
+        self.status_compare = compare;
+        self.status_text.clear();
+        self.status_range = Some(range.clone());
+        self.status_scroll = 0;
+        self.status_cursor = 0;
+        self.status_marked.clear();
+
+        let privilege = self.config.privilege;
+        let id = self.tasks.spawn_streaming(blocking, move |cancel, progress| {
+            let on_lines = |lines| progress.send(AsyncResult::StatusLines { range: range.clone(), lines });
+            data::get_status_range(&range.config, range.pre, range.post, privilege, cancel, on_lines)
+                .map(|text| AsyncResult::Status { range, text })
+                .map_err(TaskError::from)
+        });
+        self.status_task = Some(id);
+    }
+
+    /// A status fetch is still appending to `status_text`.
+    pub fn status_streaming(&self) -> bool {
+        self.status_task.is_some_and(|id| self.tasks.is_pending(id))
+    }
+
+    /// Stops a status fetch, keeping whatever lines already arrived.
+    pub fn cancel_status(&mut self) {
+        if let Some(id) = self.status_task.take() {
+            self.tasks.cancel(id);
+        }
+        self.message = String::from("⛔ Status fetch cancelled.");
+    }
+
+    pub fn append_status(&mut self, range: StatusRange, lines: Vec<String>) {
+        if self.status_range.as_ref() != Some(&range) {
+            return;
+        }
+        for line in lines {
+            self.status_text.push_str(&line);
+            self.status_text.push('\n');
+        }
+        if self.loading {
+            self.loading_message = format!(
+                "Fetching status for {}... ({} lines)",
+                range.post,
+                self.status_text.lines().count()
+            );
+        }
+    }
+
+    pub fn set_status(&mut self, range: StatusRange, text: String) {
+        // When the streamed lines are already on screen, keep the cursor
+        // and marks the user set while they arrived
+        let streamed = self.status_range.as_ref() == Some(&range)
+            && !self.status_text.is_empty()
+            && text.starts_with(&self.status_text);
+        self.status_text = text;
+        self.status_range = Some(range);
+        if streamed {
+            let count = self.status_text.lines().count();
+            self.status_cursor = self.status_cursor.min(count.saturating_sub(1));
+        } else {
+            self.status_scroll = 0; // Reset scroll
+            self.status_cursor = 0;
+            self.status_marked.clear();
+        }
+    }
+
+    pub fn status_next_file(&mut self) {
+        let count = self.status_text.lines().count();
+        if count > 0 {
+            self.status_cursor = (self.status_cursor + 1).min(count - 1);
+        }
+    }
+
+    pub fn status_previous_file(&mut self) {
+        self.status_cursor = self.status_cursor.saturating_sub(1);
+    }
+
+    // Path under the status cursor, if that line is a file entry
+    pub fn get_status_cursor_path(&self) -> Option<String> {
+        self.status_text
+            .lines()
+            .nth(self.status_cursor)
+            .and_then(data::parse_status_line)
+            .map(|(_, path)| path.to_string())
+    }
+
+    pub fn toggle_status_mark(&mut self) {
+        if self.get_status_cursor_path().is_some() && !self.status_marked.remove(&self.status_cursor) {
+            self.status_marked.insert(self.status_cursor);
+        }
+    }
+
+    // Marked file lines in listing order, or the file under the cursor
+    pub fn get_undo_targets(&self) -> Vec<String> {
+        let marked: Vec<String> = self
+            .status_text
+            .lines()
+            .enumerate()
+            .filter(|(idx, _)| self.status_marked.contains(idx))
+            .filter_map(|(_, line)| data::parse_status_line(line))
+            .map(|(_, path)| path.to_string())
+            .collect();
+        if marked.is_empty() {
+            self.get_status_cursor_path().into_iter().collect()
+        } else {
+            marked
+        }
+    }
+
+    pub fn open_undo_popup(&mut self, targets: Vec<String>) {
+        if self.refuse_read_only("undoing changes") {
+            return;
+        }
+        if self.status_range.is_none() || targets.is_empty() {
+            self.message = String::from("❌ Error: No file selected to revert.");
+            return;
+        }
+        self.undo_targets = targets;
+        self.show_undo_popup = true;
+    }
+
+    pub fn handle_undo_result(&mut self, success_count: usize, error_count: usize) {
+        if success_count > 0 {
+            let mut text = format!("↩️ Reverted {} file(s)", success_count);
+            if error_count > 0 {
+                text.push_str(&format!(" ({} failed ❌)", error_count));
             }
-            SortKey::UsedSpace => {
-                self.snapshots.sort_by_key(|s| s.used_space.unwrap_or(0));
+            let severity = if error_count > 0 { Severity::Warning } else { Severity::Success };
+            self.toast(severity, text);
+        } else if error_count > 0 {
+            self.toast(Severity::Error, format!("❌ Failed to revert {} file(s)", error_count));
+        }
+        self.undo_targets.clear();
+        self.status_marked.clear();
+    }
+
+    pub fn open_diff(&mut self, path: String, text: String) {
+        self.diff_path = path;
+        self.diff_text = text;
+        self.diff_scroll = 0;
+        self.diff_hscroll = 0;
+        self.show_diff_popup = true;
+    }
+
+    pub fn scroll_diff(&mut self, up: bool, amount: u16) {
+        if up {
+            self.diff_scroll = self.diff_scroll.saturating_sub(amount);
+        } else {
+            let max = self.diff_text.lines().count().saturating_sub(1) as u16;
+            self.diff_scroll = self.diff_scroll.saturating_add(amount).min(max);
+        }
+    }
+
+    pub fn request_refresh(&mut self) {
+        // Callers may have emptied the list already; then the highlight
+        // remembered by an earlier request still stands
+        if let Some(snap) = self.get_selected_snapshot() {
+            self.reload_highlight = Some((snap.config.clone(), snap.number));
+        }
+        self.loading = true;
+        self.loading_message = String::from("Refreshing...");
+        self.config_info.clear();
+        self.refresh_started = Instant::now();
+        self.tasks.spawn(|cancel| {
+            data::list_snapshots(cancel)
+                .map(AsyncResult::Snapshots)
+                .map_err(TaskError::from)
+        });
+    }
+
+    // Subvolume of the config being viewed, falling back to `root`.
+    fn usage_subvolume(&self) -> Option<String> {
+        let config = self.active_config.as_deref().unwrap_or("root");
+        self.snapshots
+            .iter()
+            .find(|s| s.config == config)
+            .or_else(|| self.snapshots.first())
+            .map(|s| s.subvolume.clone())
+            .filter(|subvolume| !subvolume.is_empty())
+    }
+
+    /// The subvolume whose usage a bulk delete re-reads after each snapshot
+    /// it removes, when `live_usage_on_delete` is set and the bar is shown.
+    pub fn delete_usage_subvolume(&self) -> Option<String> {
+        if !self.config.live_usage_on_delete || self.fs_usage.is_none() {
+            return None;
+        }
+        self.usage_subvolume().filter(|_| self.usage_on_btrfs())
+    }
+
+    pub fn request_usage(&mut self) {
+        let Some(subvolume) = self.usage_subvolume().filter(|_| self.usage_on_btrfs()) else {
+            self.fs_usage = None;
+            return;
+        };
+        let privilege = self.config.privilege;
+        self.tasks.spawn_background(move |cancel| {
+            // Not being on btrfs just hides the usage bar
+            Ok(AsyncResult::Usage(data::get_btrfs_usage(&subvolume, privilege, cancel).ok()))
+        });
+    }
+
+    // Whether the config `request_usage` reports on isn't known to be on
+    // another filesystem
+    fn usage_on_btrfs(&self) -> bool {
+        let config = self.active_config.as_deref().unwrap_or("root");
+        self.config_fs.get(config).and_then(Option::as_ref).is_none_or(ConfigFs::is_btrfs)
+    }
+
+    /// Looks up the filesystem of every config that has snapshots, once each.
+    pub fn request_config_fs(&mut self) {
+        let names: Vec<String> = self.snapshots.iter().map(|s| s.config.clone()).collect();
+        for name in names {
+            if self.config_fs.contains_key(&name) {
+                continue;
             }
+            self.config_fs.insert(name.clone(), None);
+            let privilege = self.config.privilege;
+            self.tasks.spawn_background(move |cancel| {
+                let fs = data::get_config_fs(&name, privilege, cancel).ok();
+                Ok(AsyncResult::ConfigFs { name, fs })
+            });
         }
-        if !self.sort_ascending {
-            self.snapshots.reverse();
+    }
+
+    pub fn request_boot_lineage(&mut self) {
+        let root_fs = self.config_fs.get("root").and_then(Option::as_ref);
+        // Without a root config snapper doesn't manage `/`, so there is no
+        // rollback to follow
+        if !self.snapshots.iter().any(|s| s.config == "root") || root_fs.is_some_and(|fs| !fs.is_btrfs()) {
+            self.boot_lineage = None;
+            return;
         }
+        let privilege = self.config.privilege;
+        self.tasks.spawn_background(move |cancel| {
+            let Some((_, root)) = data::root_mount(cancel).filter(|(fstype, _)| fstype == "btrfs") else {
+                return Ok(AsyncResult::Lineage(None));
+            };
+            // Showing only half would read as a pending rollback
+            let Ok(default) = data::get_default_subvolume(privilege, cancel) else {
+                return Ok(AsyncResult::Lineage(None));
+            };
+            Ok(AsyncResult::Lineage(Some(BootLineage {
+                booted: data::snapshot_number_of(&root),
+                default: data::snapshot_number_of(&default),
+            })))
+        });
     }
 
-    pub fn get_sort_indicator(&self, key: SortKey) -> &'static str {
-        let is_active = matches!((&self.current_sort_key, &key),
-            (SortKey::Number, SortKey::Number) |
-            (SortKey::Type, SortKey::Type) |
-            (SortKey::Date, SortKey::Date) |
-            (SortKey::User, SortKey::User) |
-            (SortKey::UsedSpace, SortKey::UsedSpace));
-        
-        if is_active {
-            if self.sort_ascending { " ↑" } else { " ↓" }
-        } else {
-            ""
+    // Config shown in the config-info panel: the selected snapshot's, else the active one.
+    pub fn info_config_name(&self) -> String {
+        self.get_selected_snapshot()
+            .map(|s| s.config.clone())
+            .or_else(|| self.active_config.clone())
+            .unwrap_or_else(|| String::from("root"))
+    }
+
+    pub fn toggle_config_info(&mut self) {
+        self.show_config_info = !self.show_config_info;
+        self.request_config_info();
+    }
+
+    // Reloads in the background once `auto_refresh_secs` has passed, unless
+    // something else is running or visual mode depends on row positions
+    fn request_auto_refresh(&mut self) {
+        let Some(secs) = self.config.auto_refresh_secs else {
+            return;
+        };
+        let in_flight = self.auto_refresh_task.is_some_and(|id| self.tasks.is_pending(id));
+        if in_flight
+            || self.tasks.is_busy()
+            || self.select_anchor.is_some()
+            || self.refresh_started.elapsed() < Duration::from_secs(secs)
+        {
+            return;
         }
+        self.refresh_started = Instant::now();
+        let id = self.tasks.spawn_background(|cancel| {
+            data::list_snapshots(cancel)
+                .map(AsyncResult::AutoRefresh)
+                .map_err(TaskError::from)
+        });
+        self.auto_refresh_task = Some(id);
     }
-    
-    pub fn toggle_selection(&mut self) {
-        if let Some(idx) = self.table_state.selected() {
-            if self.selected_indices.contains(&idx) {
-                self.selected_indices.remove(&idx);
-            } else {
-                self.selected_indices.insert(idx);
+
+    // Fetched once per config; the cache is dropped on a full refresh.
+    fn request_config_info(&mut self) {
+        if !self.show_config_info {
+            return;
+        }
+        let name = self.info_config_name();
+        if self.config_info.contains_key(&name) {
+            return;
+        }
+        self.config_info.insert(name.clone(), None);
+        let privilege = self.config.privilege;
+        self.tasks.spawn_background(move |cancel| {
+            let values = data::get_config(&name, privilege, cancel).map_err(|e| format!("{:#}", e));
+            Ok(AsyncResult::ConfigInfo { name, values })
+        });
+    }
+
+    pub fn cancel_operation(&mut self) {
+        self.tasks.cancel_all(); // Results of aborted tasks are dropped
+        self.config_info.retain(|_, info| info.is_some());
+        self.loading = false;
+        self.message = String::from("⛔ Operation cancelled.");
+    }
+
+    /// Advances timers; returns whether anything on screen changed.
+    pub fn on_tick(&mut self) -> bool {
+        let toasts = self.toasts.len();
+        self.toasts.retain(|(_, raised, _)| raised.elapsed() < TOAST_DURATION);
+        let mut changed = self.toasts.len() != toasts;
+        if self.splash_until.is_some_and(|until| Instant::now() >= until) {
+            self.dismiss_splash();
+            changed = true;
+        }
+        // Started or ended since the last tick
+        match (self.loading, self.loading_start) {
+            (true, None) => self.loading_start = Some(Instant::now()),
+            (false, Some(_)) => self.loading_start = None,
+            _ => {}
+        }
+        if self.loading && self.config.animations {
+            self.spinner_state = (self.spinner_state + 1) % self.spinner_frames.len();
+            changed = true;
+        }
+        if self.status_fetch_pending && self.last_nav.elapsed() >= STATUS_DEBOUNCE {
+            self.status_fetch_pending = false;
+            self.fetch_selected_status();
+            changed = true;
+        }
+        self.request_config_info();
+        self.request_auto_refresh();
+        changed
+    }
+
+    /// Something is moving on screen, so every frame must be drawn.
+    pub fn is_animating(&self) -> bool {
+        self.loading || self.fx.as_ref().is_some_and(Shader::running)
+    }
+
+    pub fn toast(&mut self, severity: Severity, text: impl Into<String>) {
+        self.toasts.push((text.into(), Instant::now(), severity));
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    pub fn dismiss_splash(&mut self) {
+        self.show_splash = false;
+        self.splash_until = None;
+    }
+
+    /// Tab order: table, details, status.
+    pub fn cycle_focus(&mut self, back: bool) {
+        const ORDER: [Panel; 3] = [Panel::Table, Panel::Details, Panel::Status];
+        let idx = ORDER.iter().position(|&p| p == self.focused_panel).unwrap_or(0);
+        let step = if back { ORDER.len() - 1 } else { 1 };
+        self.focused_panel = ORDER[(idx + step) % ORDER.len()];
+        if self.focused_panel != Panel::Table {
+            self.side_tab = self.focused_panel;
+        }
+    }
+
+    /// Up/Down (`page` false) or PageUp/PageDown (`page` true) on the
+    /// focused panel: rows of the table, lines of the others.
+    pub fn move_in_focused(&mut self, up: bool, page: bool) {
+        let amount = if page { PAGE_STEP } else { 1 };
+        match self.focused_panel {
+            Panel::Table if page => {
+                let height = match self.hit_areas.table_rows() {
+                    0 => PAGE_STEP as usize,
+                    rows => rows,
+                };
+                if up { self.page_up(height) } else { self.page_down(height) }
             }
+            Panel::Table => {
+                if up { self.previous() } else { self.next() }
+                self.schedule_status_fetch(); // Auto-show status once navigation pauses
+            }
+            Panel::Details => self.scroll_details(up, amount),
+            Panel::Status => self.scroll_status(up, amount),
         }
     }
-    
-    pub fn clear_selections(&mut self) {
-        self.selected_indices.clear();
+
+    /// Moves the highlight `height` rows down, stopping at the last row.
+    pub fn page_down(&mut self, height: usize) {
+        let i = self.table_state.selected().unwrap_or(0);
+        self.select_row(i.saturating_add(height));
     }
-    
-    pub fn get_selected_count(&self) -> usize {
-        self.selected_indices.len()
+
+    /// Moves the highlight `height` rows up, stopping at the first row.
+    pub fn page_up(&mut self, height: usize) {
+        let i = self.table_state.selected().unwrap_or(0);
+        self.select_row(i.saturating_sub(height));
     }
-}
 
-// Helper function for human-readable sizes
-pub fn format_size(bytes: u64) -> String {
-    if bytes < 1024 {
-        format!("{}B", bytes)
-    } else if bytes < 1024 * 1024 {
-        format!("{:.1}K", bytes as f64 / 1024.0)
-    } else if bytes < 1024 * 1024 * 1024 {
-        format!("{:.1}M", bytes as f64 / (1024.0 * 1024.0))
-    } else {
-        format!("{:.1}G", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+    pub fn select_first(&mut self) {
+        self.select_row(0);
+    }
+
+    pub fn select_last(&mut self) {
+        self.select_row(usize::MAX);
+    }
+
+    // Highlights row `i`, clamped to the filtered rows, as if moved there
+    // with the arrows
+    fn select_row(&mut self, i: usize) {
+        let last = self.get_display_rows().len().saturating_sub(1);
+        self.table_state.select(Some(i.min(last)));
+        self.update_visual_selection();
+        self.schedule_status_fetch();
+    }
+
+    // Max offsets are refreshed from the rendered panels on every draw
+    pub fn scroll_details(&mut self, up: bool, amount: u16) {
+        if up {
+            self.details_scroll = self.details_scroll.saturating_sub(amount);
+        } else {
+            self.details_scroll = self.details_scroll.saturating_add(amount).min(self.details_max_scroll);
+        }
+    }
+
+    pub fn toggle_status_wrap(&mut self) {
+        self.status_wrap = !self.status_wrap;
+        self.status_hscroll = 0;
+    }
+
+    // Like the vertical offsets, the maximums come from the last draw
+    pub fn scroll_status_sideways(&mut self, left: bool) {
+        self.status_hscroll = sideways(self.status_hscroll, self.status_max_hscroll, left);
+    }
+
+    pub fn scroll_diff_sideways(&mut self, left: bool) {
+        self.diff_hscroll = sideways(self.diff_hscroll, self.diff_max_hscroll, left);
+    }
+
+    pub fn scroll_status(&mut self, up: bool, amount: u16) {
+        if up {
+            self.status_scroll = self.status_scroll.saturating_sub(amount);
+        } else {
+            self.status_scroll = self.status_scroll.saturating_add(amount).min(self.status_max_scroll);
+        }
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help_popup = !self.show_help_popup;
+        self.help_scroll = 0;
+    }
+
+    pub fn open_about(&mut self) {
+        self.show_about_popup = true;
+        if !self.version_requested {
+            self.version_requested = true;
+            self.tasks.spawn_background(|cancel| {
+                Ok(AsyncResult::SnapperVersion(data::get_snapper_version(cancel).map_err(|e| format!("{:#}", e))))
+            });
+        }
+    }
+
+    pub fn toggle_history(&mut self) {
+        self.show_history_popup = !self.show_history_popup;
+        self.history_scroll = 0;
+    }
+
+    pub fn scroll_history(&mut self, up: bool) {
+        if up {
+            self.history_scroll = self.history_scroll.saturating_sub(1);
+        } else {
+            self.history_scroll = (self.history_scroll + 1).min(self.history_max_scroll);
+        }
+    }
+
+    pub fn scroll_delete(&mut self, up: bool) {
+        if up {
+            self.delete_scroll = self.delete_scroll.saturating_sub(1);
+        } else {
+            self.delete_scroll = (self.delete_scroll + 1).min(self.delete_max_scroll);
+        }
+    }
+
+    pub fn scroll_help(&mut self, up: bool) {
+        if up {
+            self.help_scroll = self.help_scroll.saturating_sub(1);
+        } else {
+            self.help_scroll = (self.help_scroll + 1).min(self.help_max_scroll);
+        }
+    }
+
+    pub fn open_theme_popup(&mut self) {
+        self.theme_cursor = THEMES.iter().position(|t| t.name == self.theme.name).unwrap_or(0);
+        self.show_theme_popup = true;
+    }
+
+    pub fn move_theme_cursor(&mut self, up: bool) {
+        self.theme_cursor = if up {
+            self.theme_cursor.checked_sub(1).unwrap_or(THEMES.len() - 1)
+        } else {
+            (self.theme_cursor + 1) % THEMES.len()
+        };
+    }
+
+    pub fn apply_theme_cursor(&mut self) {
+        if let Some(theme) = THEMES.get(self.theme_cursor) {
+            self.theme = if self.no_color { theme.monochrome() } else { *theme };
+            self.message = format!("🎨 Theme set to {}.", theme.name);
+        }
+        self.show_theme_popup = false;
+    }
+
+    /// Whether `config` has snapshots but snapper reports used space for
+    /// none of them, as happens before quota groups are set up. Snapshot 0,
+    /// the live system, never has a size.
+    pub fn used_space_missing(&self, config: &str) -> bool {
+        let mut snapshots = self.snapshots.iter().filter(|s| s.config == config && s.number != 0).peekable();
+        snapshots.peek().is_some() && snapshots.all(|s| s.used_space.is_none())
+    }
+
+    /// Asks to run `snapper setup-quota` for the highlighted snapshot's
+    /// config, when it lacks used space and is known to be on btrfs.
+    pub fn open_quota_popup(&mut self) {
+        if self.refuse_read_only("setting up quota") {
+            return;
+        }
+        let config = self.info_config_name();
+        if !self.used_space_missing(&config) {
+            self.message = format!("ℹ️ {} already reports used space.", config);
+            return;
+        }
+        match self.config_fs.get(&config).and_then(Option::as_ref) {
+            Some(fs) if fs.is_btrfs() => self.quota_popup = Some(config),
+            Some(fs) => self.message = format!("ℹ️ Used space needs btrfs quota groups; {} is on {}.", config, fs.fstype),
+            None => self.message = format!("ℹ️ The filesystem of {} isn't known yet; try again shortly.", config),
+        }
+    }
+
+    pub fn start_setup_quota(&mut self) {
+        let Some(config) = self.quota_popup.take() else {
+            return;
+        };
+        let privilege = self.config.privilege;
+        self.loading = true;
+        self.loading_message = format!("Setting up quota for {}...", config);
+        self.tasks.spawn(move |cancel| {
+            data::setup_quota(&config, privilege, cancel)?;
+            Ok(AsyncResult::QuotaSetUp(config))
+        });
+    }
+
+    pub fn open_cleanup_popup(&mut self) {
+        if self.refuse_read_only("a cleanup") {
+            return;
+        }
+        self.cleanup_cursor = 0;
+        self.show_cleanup_popup = true;
+    }
+
+    pub fn move_cleanup_cursor(&mut self, up: bool) {
+        let len = data::CLEANUP_ALGORITHMS.len();
+        self.cleanup_cursor = if up {
+            self.cleanup_cursor.checked_sub(1).unwrap_or(len - 1)
+        } else {
+            (self.cleanup_cursor + 1) % len
+        };
+    }
+
+    /// Runs the algorithm under the cursor on the config of the highlighted
+    /// snapshot. The config's snapshots are counted either side of the
+    /// cleanup to report how many it removed.
+    pub fn start_cleanup(&mut self) {
+        self.show_cleanup_popup = false;
+        let config = self.info_config_name();
+        let algorithm = data::CLEANUP_ALGORITHMS[self.cleanup_cursor];
+        let privilege = self.config.privilege;
+        self.loading = true;
+        self.loading_message = format!("Running {} cleanup on {}...", algorithm, config);
+        self.tasks.spawn(move |cancel| {
+            let count = |cancel| -> anyhow::Result<usize> {
+                Ok(data::list_snapshots(cancel)?.iter().filter(|s| s.config == config).count())
+            };
+            let before = count(cancel)?;
+            data::run_cleanup(&config, algorithm, privilege, cancel)?;
+            let after = count(cancel)?;
+            Ok(AsyncResult::Cleanup { config, algorithm, removed: before.saturating_sub(after) })
+        });
+    }
+
+    pub fn set_sort_key(&mut self, key: SortKey) {
+        // Toggle ascending/descending if same key
+        if matches!((&self.current_sort_key, &key),
+            (SortKey::Number, SortKey::Number) |
+            (SortKey::Type, SortKey::Type) |
+            (SortKey::Date, SortKey::Date) |
+            (SortKey::User, SortKey::User) |
+            (SortKey::UsedSpace, SortKey::UsedSpace))
+        {
+            self.sort_ascending = !self.sort_ascending;
+        } else {
+            self.current_sort_key = key;
+            self.sort_ascending = true;
+        }
+        self.sort_snapshots();
+    }
+
+    pub fn sort_snapshots(&mut self) {
+        match self.current_sort_key {
+            SortKey::Number => {
+                self.snapshots.sort_by_key(|s| s.number);
+            }
+            SortKey::Type => {
+                self.snapshots.sort_by(|a, b| a.snapshot_type.cmp(&b.snapshot_type));
+            }
+            SortKey::Date => {
+                // The raw text only orders dates of one format, so it just
+                // breaks ties and orders the dates that didn't parse. Those
+                // stay last whichever way the sort runs
+                let ascending = self.sort_ascending;
+                let directed = |order: std::cmp::Ordering| if ascending { order } else { order.reverse() };
+                self.snapshots.sort_by(|a, b| match (a.timestamp, b.timestamp) {
+                    (Some(x), Some(y)) => directed(x.cmp(&y).then_with(|| a.date.cmp(&b.date))),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => directed(a.date.cmp(&b.date)),
+                });
+                return;
+            }
+            SortKey::User => {
+                self.snapshots.sort_by(|a, b| a.user.cmp(&b.user));
+            }
+            SortKey::UsedSpace => {
+                self.snapshots.sort_by_key(|s| s.used_space.unwrap_or(0));
+            }
+        }
+        if !self.sort_ascending {
+            self.snapshots.reverse();
+        }
+    }
+
+    pub fn get_sort_indicator(&self, key: SortKey) -> &'static str {
+        let is_active = matches!((&self.current_sort_key, &key),
+            (SortKey::Number, SortKey::Number) |
+            (SortKey::Type, SortKey::Type) |
+            (SortKey::Date, SortKey::Date) |
+            (SortKey::User, SortKey::User) |
+            (SortKey::UsedSpace, SortKey::UsedSpace));
+        
+        if is_active {
+            if self.sort_ascending { " ↑" } else { " ↓" }
+        } else {
+            ""
+        }
+    }
+    
+    pub fn toggle_selection(&mut self) {
+        let keys: Vec<(String, u32)> = match self.get_selected_row() {
+            Some(row) => row.snapshots().iter().map(|s| (s.config.clone(), s.number)).collect(),
+            None => return,
+        };
+        // A pair is selected as a unit: select both unless both already are
+        if keys.iter().all(|key| self.selected_numbers.contains(key)) {
+            for key in keys {
+                self.selected_numbers.remove(&key);
+            }
+        } else {
+            self.selected_numbers.extend(keys);
+        }
+    }
+    
+    pub fn toggle_filter_case_sensitive(&mut self) {
+        self.filter_case_sensitive = !self.filter_case_sensitive;
+        self.table_state.select(Some(0));
+    }
+
+    pub fn toggle_filter_whole_word(&mut self) {
+        self.filter_whole_word = !self.filter_whole_word;
+        self.table_state.select(Some(0));
+    }
+
+    /// Hides the `index`th of `HIDEABLE_TYPES`, or shows it again.
+    pub fn toggle_hidden_type(&mut self, index: usize) {
+        let Some(&snapshot_type) = HIDEABLE_TYPES.get(index) else {
+            return;
+        };
+        if !self.hidden_types.remove(snapshot_type) {
+            self.hidden_types.insert(snapshot_type.to_string());
+        }
+        self.exit_visual_mode(); // The range is tied to the current rows
+        self.table_state.select(Some(0));
+    }
+
+    pub fn select_all_filtered(&mut self) {
+        let keys: Vec<(String, u32)> = self.get_filtered_snapshots().iter().map(|s| (s.config.clone(), s.number)).collect();
+        self.selected_numbers.extend(keys);
+    }
+
+    pub fn invert_selection(&mut self) {
+        let keys: Vec<(String, u32)> = self.get_filtered_snapshots().iter().map(|s| (s.config.clone(), s.number)).collect();
+        for key in keys {
+            if !self.selected_numbers.remove(&key) {
+                self.selected_numbers.insert(key);
+            }
+        }
+    }
+    
+    pub fn toggle_visual_mode(&mut self) {
+        if self.select_anchor.is_some() {
+            self.exit_visual_mode();
+        } else if let Some(cursor) = self.table_state.selected() {
+            self.visual_base = self.selected_numbers.clone();
+            self.select_anchor = Some(cursor);
+            self.update_visual_selection();
+        }
+    }
+
+    /// Leaves visual mode, keeping whatever it selected.
+    pub fn exit_visual_mode(&mut self) {
+        self.select_anchor = None;
+        self.visual_base.clear();
+    }
+
+    // Selection = pre-visual selection plus every row between anchor and cursor
+    fn update_visual_selection(&mut self) {
+        let (Some(anchor), Some(cursor)) = (self.select_anchor, self.table_state.selected()) else {
+            return;
+        };
+        let (from, to) = (anchor.min(cursor), anchor.max(cursor));
+        let range: Vec<(String, u32)> = self
+            .get_display_rows()
+            .iter()
+            .skip(from)
+            .take(to - from + 1)
+            .flat_map(|row| row.snapshots())
+            .map(|s| (s.config.clone(), s.number))
+            .collect();
+        self.selected_numbers = self.visual_base.iter().cloned().chain(range).collect();
+    }
+
+    pub fn clear_selections(&mut self) {
+        self.selected_numbers.clear();
+    }
+    
+    pub fn get_selected_count(&self) -> usize {
+        self.get_visible_selection().len()
+    }
+}
+
+// Largest scroll offset that still keeps the viewport filled with content
+pub fn max_scroll(content_lines: usize, viewport_height: u16) -> u16 {
+    content_lines
+        .saturating_sub(viewport_height as usize)
+        .min(u16::MAX as usize) as u16
+}
+
+/// What to tell the user after rolling back to `number`. Only the system
+/// root is rolled back (see `rollback_refusal`), which takes effect on the
+/// next boot.
+pub fn rollback_message(number: u32) -> String {
+    format!("✅ Snapshot {} applied. Reboot to take effect.", number)
+}
+
+fn sideways(offset: u16, max: u16, left: bool) -> u16 {
+    if left { offset.saturating_sub(HSCROLL_STEP) } else { offset.saturating_add(HSCROLL_STEP).min(max) }
+}
+
+// `template` with `{time}` replaced by the local `YYYY-MM-DD HH:MM:SS` at
+// `secs` and `{date}` by its date part
+fn expand_description(template: &str, secs: i64) -> String {
+    let time = data::format_local_time(secs);
+    let date = time.split(' ').next().unwrap_or_default();
+    template.replace("{time}", &time).replace("{date}", date)
+}
+
+// Growth of each snapshot over the one before it in its config, in
+// chronological order whatever the table is sorted by
+fn space_deltas(snapshots: &[Snapshot]) -> HashMap<(String, u32), i64> {
+    let mut ordered: Vec<&Snapshot> = snapshots.iter().collect();
+    ordered.sort_by(|a, b| {
+        (&a.config, a.timestamp.is_none(), a.timestamp, a.number).cmp(&(&b.config, b.timestamp.is_none(), b.timestamp, b.number))
+    });
+    ordered
+        .windows(2)
+        .filter(|pair| pair[0].config == pair[1].config)
+        .filter_map(|pair| {
+            let (before, after) = (pair[0].used_space?, pair[1].used_space?);
+            Some(((pair[1].config.clone(), pair[1].number), after as i64 - before as i64))
+        })
+        .collect()
+}
+
+// A typed path, with a leading `~/` meaning the home directory
+fn expand_home(input: &str) -> PathBuf {
+    match input.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(rest))
+            .unwrap_or_else(|| PathBuf::from(input)),
+        None => PathBuf::from(input),
+    }
+}
+
+/// Renders a snapper date as e.g. "2 hours ago"; unparseable dates are
+/// returned unchanged.
+pub fn format_relative(date: &str) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    match data::parse_date(date) {
+        Some(then) => describe_elapsed(now - then),
+        None => date.to_string(),
+    }
+}
+
+fn describe_elapsed(secs: i64) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+
+    let elapsed = secs.unsigned_abs();
+    let (count, unit) = match elapsed {
+        0..MINUTE => return String::from("just now"),
+        MINUTE..HOUR => (elapsed / MINUTE, "minute"),
+        HOUR..DAY => (elapsed / HOUR, "hour"),
+        DAY..MONTH => (elapsed / DAY, "day"),
+        MONTH..YEAR => (elapsed / MONTH, "month"),
+        _ => (elapsed / YEAR, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    // Clock skew can put a snapshot slightly in the future
+    if secs < 0 {
+        format!("in {} {}{}", count, unit, plural)
+    } else {
+        format!("{} {}{} ago", count, unit, plural)
+    }
+}
+
+// Helper function for human-readable sizes
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{}B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1}K", bytes as f64 / 1024.0)
+    } else if bytes < 1024 * 1024 * 1024 {
+        format!("{:.1}M", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1}G", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+    }
+}
+
+/// `format_size` followed by the exact count, e.g. `12.1M (12678901 bytes)`,
+/// for places with room to spare.
+pub fn format_size_with_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format_size(bytes);
+    }
+    format!("{} ({} bytes)", format_size(bytes), bytes)
+}
+
+/// The command that opens `dir`: `$FILE_MANAGER`, else `$VISUAL` or
+/// `$EDITOR`, else `xdg-open` in a graphical session and `vi` outside one.
+/// Variables may carry arguments, as in `EDITOR="code --wait"`.
+pub fn external_browser(var: impl Fn(&str) -> Option<String>, dir: &Path) -> Vec<String> {
+    let set = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+    let program = set("FILE_MANAGER").or_else(|| set("VISUAL")).or_else(|| set("EDITOR")).unwrap_or_else(|| {
+        let graphical = set("DISPLAY").is_some() || set("WAYLAND_DISPLAY").is_some();
+        String::from(if graphical { "xdg-open" } else { "vi" })
+    });
+    let mut argv: Vec<String> = program.split_whitespace().map(String::from).collect();
+    argv.push(dir.display().to_string());
+    argv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(number: u32, snapshot_type: &str, description: &str) -> Snapshot {
+        Snapshot {
+            config: String::from("root"),
+            number,
+            snapshot_type: snapshot_type.to_string(),
+            description: description.to_string(),
+            user: String::from("root"),
+            ..Default::default()
+        }
+    }
+
+    // `(config, number)` key of a root snapshot
+    fn root_key(number: u32) -> (String, u32) {
+        (String::from("root"), number)
+    }
+
+    fn root_keys(numbers: &[u32]) -> Vec<(String, u32)> {
+        numbers.iter().copied().map(root_key).collect()
+    }
+
+    fn app_with(snapshots: Vec<Snapshot>) -> App {
+        let mut app = App::with_config(Config::default());
+        app.snapshots = snapshots;
+        app.table_state.select(Some(0));
+        app
+    }
+
+    #[test]
+    fn test_selection_resolves_through_filter() {
+        let mut app = app_with(vec![
+            snap(1, "single", "timeline"),
+            snap(2, "pre", "zypper install"),
+            snap(3, "single", "timeline"),
+            snap(4, "post", "zypper install"),
+        ]);
+        app.filter_input = String::from("zypper");
+        app.table_state.select(Some(1));
+
+        let highlighted = app.get_filtered_snapshots()[1].number;
+        assert_eq!(highlighted, 4);
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(4));
+        assert_eq!(app.get_target_for_apply(), Some(4));
+        assert_eq!(app.get_targets_for_delete(), root_keys(&[4]));
+    }
+
+    #[test]
+    fn test_multi_selection_survives_filter_and_sort() {
+        let mut app = app_with(vec![
+            snap(1, "single", "timeline"),
+            snap(2, "pre", "zypper install"),
+            snap(3, "post", "zypper install"),
+        ]);
+        app.filter_input = String::from("zypper");
+        app.toggle_selection();
+        assert!(app.selected_numbers.contains(&root_key(2)));
+
+        app.filter_input.clear();
+        app.set_sort_key(SortKey::Number); // toggles to descending
+        assert_eq!(app.get_targets_for_delete(), root_keys(&[2]));
+    }
+
+    #[test]
+    fn test_selection_tells_configs_apart() {
+        let home = Snapshot { config: String::from("home"), ..snap(5, "single", "home five") };
+        let mut app = app_with(vec![snap(5, "single", "root five"), home.clone(), snap(9, "single", "root nine")]);
+        app.toggle_selection();
+        assert!(app.is_selected(&snap(5, "single", "")) && !app.is_selected(&home));
+        assert_eq!(app.get_targets_for_delete(), root_keys(&[5]));
+
+        app.invert_selection();
+        assert_eq!(app.get_visible_selection(), [(String::from("home"), 5), root_key(9)]);
+    }
+
+    #[test]
+    fn test_hidden_types_compose_with_filter() {
+        let mut app = app_with(vec![
+            snap(1, "single", "timeline"),
+            snap(2, "pre", "zypper install"),
+            snap(3, "single", "zypper by hand"),
+            snap(4, "post", "zypper install"),
+        ]);
+        app.toggle_hidden_type(0);
+        let numbers = |app: &App| app.get_filtered_snapshots().iter().map(|s| s.number).collect::<Vec<_>>();
+        assert_eq!(numbers(&app), vec![2, 4]);
+
+        app.filter_input = String::from("zypper");
+        app.toggle_hidden_type(2);
+        assert_eq!(numbers(&app), vec![2]);
+
+        app.toggle_hidden_type(0);
+        assert_eq!(numbers(&app), vec![2, 3]);
+        app.toggle_hidden_type(7);
+        assert_eq!(app.hidden_types.len(), 1);
+    }
+
+    #[test]
+    fn test_bulk_selection_respects_filter() {
+        let mut app = app_with(vec![
+            snap(1, "single", "timeline"),
+            snap(2, "pre", "zypper install"),
+            snap(3, "single", "timeline"),
+            snap(4, "post", "zypper install"),
+        ]);
+        app.filter_input = String::from("timeline");
+        app.select_all_filtered();
+        assert_eq!(app.get_targets_for_delete(), root_keys(&[1, 3]));
+
+        app.filter_input.clear();
+        app.invert_selection();
+        assert_eq!(app.get_targets_for_delete(), root_keys(&[2, 4]));
+
+        // A selection hidden by the filter is not a delete target
+        app.filter_input = String::from("timeline");
+        assert_eq!(app.get_selected_count(), 0);
+        assert_eq!(app.get_targets_for_delete(), root_keys(&[1]));
+
+        app.clear_selections();
+        app.filter_input.clear();
+        assert_eq!(app.get_selected_count(), 0);
+    }
+
+    #[test]
+    fn test_filtered_summary_follows_filter() {
+        let mut app = app_with(vec![
+            Snapshot { used_space: Some(1024), ..snap(1, "single", "timeline") },
+            Snapshot { used_space: Some(4096), ..snap(2, "pre", "zypper install") },
+            Snapshot { used_space: None, ..snap(3, "post", "zypper install") },
+        ]);
+        assert_eq!(app.filtered_summary(), (3, Some(5120)));
+
+        app.filter_input = String::from("zypper");
+        assert_eq!(app.filtered_summary(), (2, Some(4096)));
+
+        app.snapshots.iter_mut().for_each(|s| s.used_space = None);
+        assert_eq!(app.filtered_summary(), (2, None));
+    }
+
+    #[test]
+    fn test_loading_clock_runs_while_loading() {
+        let mut app = App::with_config(Config::default());
+        app.loading = false;
+        app.on_tick();
+        assert_eq!(app.loading_start, None);
+
+        app.loading = true;
+        app.on_tick();
+        let started = app.loading_start.expect("started on the first tick");
+        app.on_tick();
+        assert_eq!(app.loading_start, Some(started), "kept while still loading");
+
+        app.cancel_operation();
+        app.on_tick();
+        assert_eq!(app.loading_start, None);
+    }
+
+    #[test]
+    fn test_animations_off_skips_splash_and_spinner() {
+        let config = Config { animations: false, spinner_style: String::from("line"), ..Config::default() };
+        let mut app = App::with_config(config);
+        assert!(!app.show_splash);
+        assert_eq!(app.spinner_frames, vec!["-", "\\", "|", "/"]);
+        app.loading = true;
+        app.on_tick();
+        assert_eq!(app.spinner_state, 0);
+
+        let mut app = App::with_config(Config { spinner_style: String::from("comet"), ..Config::default() });
+        assert_eq!(app.spinner_frames, SPINNERS[0].1);
+        assert!(app.message.contains("spinner_style"), "{}", app.message);
+        app.loading = true;
+        app.on_tick();
+        assert_eq!(app.spinner_state, 1);
+    }
+
+    #[test]
+    fn test_splash_times_out_on_tick() {
+        let mut app = App::with_config(Config::default());
+        assert!(app.show_splash);
+        app.splash_until = Some(Instant::now());
+        app.on_tick();
+        assert!(!app.show_splash);
+        assert_eq!(app.splash_until, None);
+    }
+
+    #[test]
+    fn test_comparison_needs_exactly_two_of_one_config() {
+        let mut app = app_with(vec![
+            snap(3, "single", "timeline"),
+            snap(9, "single", "timeline"),
+            snap(5, "single", "timeline"),
+            Snapshot { config: String::from("home"), ..snap(12, "single", "timeline") },
+        ]);
+        app.selected_numbers.insert(root_key(9));
+        assert!(app.comparison_range().is_err());
+
+        app.selected_numbers.insert(root_key(3));
+        let range = app.comparison_range().unwrap();
+        assert_eq!((range.config.as_str(), range.pre, range.post), ("root", 3, 9));
+
+        app.selected_numbers.remove(&root_key(3));
+        app.selected_numbers.insert((String::from("home"), 12));
+        assert!(app.comparison_range().is_err());
+
+        // The status key and button both go to the comparison
+        app.selected_numbers.remove(&(String::from("home"), 12));
+        app.status_or_compare();
+        assert!(app.message.contains("Select exactly two"), "{}", app.message);
+    }
+
+    #[test]
+    fn test_comparison_ignores_same_number_in_other_config() {
+        let home = Snapshot { config: String::from("home"), ..snap(5, "single", "home five") };
+        let mut app = app_with(vec![snap(5, "single", "a"), home, snap(9, "single", "b")]);
+        app.selected_numbers.extend(root_keys(&[5, 9]));
+        let range = app.comparison_range().unwrap();
+        assert_eq!((range.config.as_str(), range.pre, range.post), ("root", 5, 9));
+    }
+
+    #[test]
+    fn test_status_without_range_clears_panel() {
+        let mut app = app_with(vec![snap(1, "single", "first")]);
+        app.status_text = String::from("c..... /etc/stale");
+        app.request_status();
+        assert!(!app.status_streaming());
+        assert!(app.status_text.is_empty());
+        assert!(app.message.contains("No comparison range"));
+    }
+
+    #[test]
+    fn test_navigation_wraps_only_when_enabled() {
+        let mut app = app_with(vec![snap(1, "single", "a"), snap(2, "single", "b"), snap(3, "single", "c")]);
+        app.previous();
+        assert_eq!(app.table_state.selected(), Some(2));
+        app.next();
+        assert_eq!(app.table_state.selected(), Some(0));
+
+        app.config.wrap_navigation = false;
+        app.previous();
+        assert_eq!(app.table_state.selected(), Some(0));
+        app.table_state.select(Some(2));
+        app.next();
+        assert_eq!(app.table_state.selected(), Some(2));
+    }
+
+    #[test]
+    fn test_toasts_stack_and_expire() {
+        let mut app = app_with(Vec::new());
+        for n in 0..6 {
+            app.toast(Severity::Info, format!("toast {}", n));
+        }
+        let texts: Vec<&str> = app.toasts.iter().map(|(text, _, _)| text.as_str()).collect();
+        assert_eq!(texts, vec!["toast 2", "toast 3", "toast 4", "toast 5"]);
+
+        app.toasts[0].1 = Instant::now() - TOAST_DURATION;
+        assert!(app.on_tick(), "an expired toast needs a redraw");
+        assert_eq!(app.toasts.len(), 3);
+        app.loading = false; // Stops the spinner
+        assert!(!app.on_tick());
+    }
+
+    #[test]
+    fn test_preferences_round_trip_through_config() {
+        let config = Config {
+            default_sort: SortKey::User,
+            sort_ascending: false,
+            default_config: Some(String::from("home")),
+            ..Config::default()
+        };
+        let mut app = App::with_config(config);
+        assert_eq!(app.current_sort_key, SortKey::User);
+        assert!(!app.sort_ascending);
+
+        app.snapshots = vec![snap(1, "single", "a"), snap(2, "single", "b")];
+        app.snapshots[1].config = String::from("home");
+        assert_eq!(app.get_filtered_snapshots().len(), 1);
+
+        app.set_sort_key(SortKey::Date);
+        let prefs = app.preferences();
+        assert_eq!(prefs.default_sort, SortKey::Date);
+        assert!(prefs.sort_ascending);
+        assert_eq!(prefs.default_config.as_deref(), Some("home"));
+    }
+
+    #[test]
+    fn test_theme_picker_persists_choice() {
+        let config = Config { theme: String::from("gruvbox"), ..Config::default() };
+        let mut app = App::with_config(config);
+        assert_eq!(app.theme, theme::GRUVBOX);
+
+        app.open_theme_popup();
+        app.move_theme_cursor(true);
+        app.apply_theme_cursor();
+        assert_eq!(app.theme, theme::SOLARIZED_DARK);
+        assert_eq!(app.preferences().theme, "solarized-dark");
+
+        let app = App::with_config(Config { theme: String::from("neon"), ..Config::default() });
+        assert_eq!(app.theme, theme::DRACULA);
+        assert!(app.message.contains("neon"));
+    }
+
+    #[test]
+    fn test_reload_keeps_sort_order() {
+        let mut app = app_with(Vec::new());
+        app.set_sort_key(SortKey::Date);
+        app.set_sort_key(SortKey::Date); // descending
+
+        let mut older = snap(1, "single", "a");
+        older.date = String::from("2023-10-01 10:00:00");
+        let mut newer = snap(2, "single", "b");
+        newer.date = String::from("2023-10-02 10:00:00");
+        app.set_snapshots(vec![older, newer]);
+
+        let numbers: Vec<u32> = app.snapshots.iter().map(|s| s.number).collect();
+        assert_eq!(numbers, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_date_sort_is_chronological_across_formats() {
+        let dated = |number, date: &str| Snapshot { date: date.to_string(), timestamp: data::parse_date(date), ..snap(number, "single", "") };
+        let mut app = app_with(vec![
+            dated(1, "2024-01-15T08:00:00Z"),
+            // Later as text, but 05:00 UTC
+            dated(2, "2024-01-15T10:00:00+05:00"),
+            dated(3, "unknown"),
+            dated(4, "2024-01-14T23:00:00Z"),
+            dated(5, "15.01.2024 09:00"),
+            dated(6, "2024-01-15 10:00:00Z"),
+        ]);
+        app.set_sort_key(SortKey::Date);
+        if !app.sort_ascending {
+            app.set_sort_key(SortKey::Date);
+        }
+        let numbers: Vec<u32> = app.snapshots.iter().map(|s| s.number).collect();
+        assert_eq!(numbers, vec![4, 2, 1, 6, 5, 3]);
+
+        // Unparseable dates stay last when descending too
+        app.set_sort_key(SortKey::Date);
+        let numbers: Vec<u32> = app.snapshots.iter().map(|s| s.number).collect();
+        assert_eq!(numbers, vec![6, 1, 2, 4, 3, 5]);
+    }
+
+    #[test]
+    fn test_max_scroll() {
+        assert_eq!(max_scroll(30, 10), 20);
+        assert_eq!(max_scroll(10, 10), 0);
+        assert_eq!(max_scroll(3, 10), 0);
+        assert_eq!(max_scroll(0, 0), 0);
+    }
+
+    #[test]
+    fn test_scroll_saturates_at_content_end() {
+        let mut app = app_with(Vec::new());
+        app.status_max_scroll = max_scroll(12, 10);
+        for _ in 0..5 {
+            app.scroll_status(false, 1);
+        }
+        assert_eq!(app.status_scroll, 2);
+
+        app.scroll_status(true, 1);
+        app.scroll_status(true, 1);
+        app.scroll_status(true, 1);
+        assert_eq!(app.status_scroll, 0);
+
+        app.details_max_scroll = 0;
+        app.scroll_details(false, 1);
+        assert_eq!(app.details_scroll, 0);
+    }
+
+    #[test]
+    fn test_keys_follow_focused_panel() {
+        let mut app = app_with((1..=25).map(|n| snap(n, "single", "timeline")).collect());
+        app.details_max_scroll = 30;
+        app.status_max_scroll = 4;
+
+        app.move_in_focused(false, true);
+        assert_eq!(app.table_state.selected(), Some(10));
+        app.move_in_focused(false, true);
+        app.move_in_focused(false, true);
+        assert_eq!(app.table_state.selected(), Some(24), "paging stops at the last row");
+
+        app.cycle_focus(false);
+        assert_eq!(app.focused_panel, Panel::Details);
+        assert_eq!(app.side_tab, Panel::Details);
+        app.move_in_focused(false, true);
+        app.move_in_focused(false, false);
+        assert_eq!((app.details_scroll, app.table_state.selected()), (11, Some(24)));
+
+        app.cycle_focus(false);
+        app.move_in_focused(false, true);
+        assert_eq!(app.status_scroll, 4);
+
+        // Back on the table, the last side panel stays the visible tab
+        app.cycle_focus(false);
+        assert_eq!(app.focused_panel, Panel::Table);
+        assert_eq!(app.side_tab, Panel::Status);
+        app.cycle_focus(true);
+        assert_eq!(app.focused_panel, Panel::Status);
+    }
+
+    #[test]
+    fn test_unwrapped_status_scrolls_sideways() {
+        let mut app = app_with(Vec::new());
+        app.status_max_hscroll = 20;
+        app.scroll_status_sideways(false);
+        assert_eq!(app.status_hscroll, 8);
+        app.scroll_status_sideways(false);
+        app.scroll_status_sideways(false);
+        assert_eq!(app.status_hscroll, 20);
+        app.scroll_status_sideways(true);
+        assert_eq!(app.status_hscroll, 12);
+
+        app.toggle_status_wrap();
+        assert!(!app.status_wrap);
+        assert_eq!(app.status_hscroll, 0);
+
+        app.open_diff(String::from("/etc/fstab"), String::from("+x"));
+        app.scroll_diff_sideways(false);
+        assert_eq!(app.diff_hscroll, 0, "nothing to scroll before a draw");
+    }
+
+    #[test]
+    fn test_paging_uses_table_height_and_clamps() {
+        let mut app = app_with((1..=25).map(|n| snap(n, "single", "timeline")).collect());
+        app.select_last();
+        assert_eq!(app.table_state.selected(), Some(24));
+        app.page_down(5);
+        assert_eq!(app.table_state.selected(), Some(24));
+        app.page_up(5);
+        assert_eq!(app.table_state.selected(), Some(19));
+        app.page_up(100);
+        assert_eq!(app.table_state.selected(), Some(0));
+
+        // Borders and the header row take three lines of the drawn table
+        app.hit_areas.table = Rect::new(0, 0, 40, 9);
+        app.move_in_focused(false, true);
+        assert_eq!(app.table_state.selected(), Some(6));
+        app.select_last();
+        app.select_first();
+        assert_eq!(app.table_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn test_report_needs_a_status_and_reports_write_errors() {
+        let mut app = app_with(Vec::new());
+        app.open_report_popup(false);
+        assert!(!app.show_report_popup);
+
+        app.status_range = Some(StatusRange { config: String::from("root"), pre: 1, post: 2 });
+        app.status_text = String::from("c..... /etc/fstab");
+        app.open_report_popup(true);
+        assert!(app.show_report_popup);
+        assert_eq!(app.report_input, "snapper-diff-1-2.txt");
+
+        app.report_input = String::from("/nonexistent-dir/report.txt");
+        app.save_report();
+        assert!(!app.show_report_popup);
+        assert!(app.message.starts_with("❌ Report not saved"), "{}", app.message);
+    }
+
+    #[test]
+    fn test_size_with_bytes_matches_table() {
+        assert_eq!(format_size_with_bytes(12_678_901), "12.1M (12678901 bytes)");
+        assert!(format_size_with_bytes(12_678_901).starts_with(&format_size(12_678_901)));
+        assert_eq!(format_size_with_bytes(512), "512B");
+    }
+
+    #[test]
+    fn test_space_deltas_follow_date_order_per_config() {
+        let at = |number: u32, config: &str, timestamp: i64, space: Option<u64>| Snapshot {
+            config: config.to_string(),
+            number,
+            timestamp: Some(timestamp),
+            used_space: space,
+            ..Default::default()
+        };
+        let deltas = space_deltas(&[
+            at(3, "root", 300, Some(5000)),
+            at(1, "root", 100, Some(1000)),
+            at(2, "root", 200, Some(4000)),
+            at(9, "home", 150, Some(10)),
+            at(4, "root", 400, None),
+            at(5, "root", 500, Some(6000)),
+        ]);
+        let root = |n: u32| deltas.get(&(String::from("root"), n)).copied();
+        assert_eq!(root(1), None, "first of its config");
+        assert_eq!(root(2), Some(3000));
+        assert_eq!(root(3), Some(1000));
+        assert_eq!((root(4), root(5)), (None, None), "unknown sizes break the chain");
+        assert_eq!(deltas.get(&(String::from("home"), 9)), None);
+    }
+
+    #[test]
+    fn test_status_cursor_path() {
+        let mut app = app_with(Vec::new());
+        let range = StatusRange { config: String::from("root"), pre: 1, post: 2 };
+        app.set_status(range, String::from("c..... /etc/fstab\n+..... /etc/new.conf\n"));
+        assert_eq!(app.get_status_cursor_path().as_deref(), Some("/etc/fstab"));
+
+        app.status_next_file();
+        app.status_next_file(); // clamps at the last line
+        assert_eq!(app.get_status_cursor_path().as_deref(), Some("/etc/new.conf"));
+
+        app.status_previous_file();
+        assert_eq!(app.status_cursor, 0);
+    }
+
+    #[test]
+    fn test_tick_starts_auto_refresh_once_due() {
+        let mut app = app_with(vec![snap(1, "single", "a")]);
+        app.config.auto_refresh_secs = Some(30);
+        app.on_tick();
+        assert!(app.auto_refresh_task.is_none());
+
+        app.refresh_started = Instant::now().checked_sub(Duration::from_secs(31)).unwrap();
+        app.on_tick();
+        assert!(app.auto_refresh_task.is_some());
+    }
+
+    #[test]
+    fn test_auto_refresh_keeps_highlight_by_number() {
+        let mut app = app_with(vec![snap(1, "single", "a"), snap(2, "single", "b"), snap(3, "single", "c")]);
+        app.table_state.select(Some(1));
+        app.apply_auto_refresh(vec![snap(0, "single", "new"), snap(1, "single", "a"), snap(2, "single", "b"), snap(3, "single", "c")]);
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(2));
+        assert!(app.last_refresh.is_some());
+
+        // Once the highlighted snapshot is gone, the row index is kept
+        app.apply_auto_refresh(vec![snap(0, "single", "new"), snap(1, "single", "a")]);
+        assert_eq!(app.table_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn test_refresh_with_active_filter_keeps_highlight_and_selection() {
+        let mut app = app_with(vec![
+            snap(1, "single", "timeline"),
+            snap(2, "pre", "zypper install"),
+            snap(3, "single", "timeline"),
+            snap(4, "post", "zypper install"),
+        ]);
+        app.config.privilege = data::Privilege::Direct;
+        app.filter_input = String::from("zypper");
+        app.table_state.select(Some(1));
+        app.selected_numbers.insert(root_key(2));
+
+        // As the refresh key does it: the list is emptied while loading
+        app.request_refresh();
+        app.snapshots.clear();
+        app.load_snapshots(vec![
+            snap(1, "single", "timeline"),
+            snap(2, "pre", "zypper install"),
+            snap(4, "post", "zypper install"),
+            snap(5, "pre", "zypper remove"),
+            snap(6, "post", "zypper remove"),
+        ]);
+
+        let shown: Vec<u32> = app.get_filtered_snapshots().iter().map(|s| s.number).collect();
+        assert_eq!(shown, vec![2, 4, 5, 6]);
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(4));
+        assert_eq!(app.get_visible_selection(), root_keys(&[2]));
+
+        // Gone after the reload: back to the top of the filtered rows
+        app.request_refresh();
+        app.load_snapshots(vec![snap(5, "pre", "zypper remove"), snap(6, "post", "zypper remove")]);
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(5));
+        app.cancel_operation();
+    }
+
+    #[test]
+    fn test_browse_navigation() {
+        let mut app = app_with(Vec::new());
+        let entries = vec![
+            DirEntry { name: String::from("etc"), is_dir: true },
+            DirEntry { name: String::from("fstab"), is_dir: false },
+        ];
+        app.browse = Some(Browse {
+            config: String::from("root"),
+            number: 5,
+            root: PathBuf::from("/.snapshots/5/snapshot"),
+            dir: PathBuf::from("/.snapshots/5/snapshot"),
+            entries: entries.clone(),
+            cursor: 0,
+            file: None,
+            file_scroll: 0,
+        });
+        app.browse_move(false);
+        app.browse_move(false); // clamps at the last entry
+        assert_eq!(app.browse.as_ref().unwrap().cursor, 1);
+
+        // Never climbs above the mount point
+        app.browse_back();
+        assert!(!app.tasks.is_busy());
+
+        app.set_browse_listing(PathBuf::from("/.snapshots/5/snapshot/etc"), entries);
+        let browse = app.browse.as_mut().unwrap();
+        assert_eq!(browse.cursor, 0);
+        browse.file = Some((PathBuf::from("/.snapshots/5/snapshot/etc/fstab"), String::from("/ btrfs")));
+        app.browse_back();
+        assert!(app.browse.as_ref().unwrap().file.is_none());
+        assert!(!app.tasks.is_busy());
+    }
+
+    #[test]
+    fn test_quit_confirmation() {
+        let mut app = app_with(vec![snap(1, "single", "a")]);
+        app.loading = false;
+        assert!(!app.quit_needs_confirm());
+        app.toggle_selection();
+        assert!(app.quit_needs_confirm());
+        app.clear_selections();
+        app.loading = true;
+        assert!(app.quit_needs_confirm());
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        assert_eq!(describe_elapsed(30), "just now");
+        assert_eq!(describe_elapsed(60), "1 minute ago");
+        assert_eq!(describe_elapsed(2 * 3600 + 59), "2 hours ago");
+        assert_eq!(describe_elapsed(45 * 86400), "1 month ago");
+        assert_eq!(describe_elapsed(3 * 365 * 86400), "3 years ago");
+        assert_eq!(describe_elapsed(-300), "in 5 minutes");
+
+        let earlier = data::parse_date("2024-01-15 10:00:00").unwrap();
+        let later = data::parse_date("2024-01-15 12:30:00").unwrap();
+        assert_eq!(later - earlier, 9000);
+        assert_eq!(format_relative("not a date"), "not a date");
+        assert_eq!(format_relative("2024-13-01 00:00:00"), "2024-13-01 00:00:00");
+    }
+
+    #[test]
+    fn test_streamed_status_keeps_cursor() {
+        let mut app = app_with(Vec::new());
+        let range = StatusRange { config: String::from("root"), pre: 1, post: 2 };
+        app.status_range = Some(range.clone());
+        app.append_status(range.clone(), vec![String::from("c..... /etc/a"), String::from("c..... /etc/b")]);
+        // Lines from another range's fetch are ignored
+        app.append_status(StatusRange { post: 3, ..range.clone() }, vec![String::from("c..... /etc/x")]);
+        assert_eq!(app.status_text, "c..... /etc/a\nc..... /etc/b\n");
+
+        app.status_next_file();
+        app.toggle_status_mark();
+        app.set_status(range, String::from("c..... /etc/a\nc..... /etc/b\nc..... /etc/c\n"));
+        assert_eq!(app.status_cursor, 1);
+        assert!(app.status_marked.contains(&1));
+    }
+
+    #[test]
+    fn test_undo_targets() {
+        let mut app = app_with(Vec::new());
+        let range = StatusRange { config: String::from("root"), pre: 1, post: 2 };
+        app.set_status(range, String::from("c..... /etc/a\n+..... /etc/b\n-..... /etc/c\n"));
+
+        // Without marks the cursor file is the target
+        app.status_cursor = 1;
+        assert_eq!(app.get_undo_targets(), vec!["/etc/b"]);
+
+        app.toggle_status_mark();
+        app.status_cursor = 2;
+        app.toggle_status_mark();
+        app.status_cursor = 0;
+        assert_eq!(app.get_undo_targets(), vec!["/etc/b", "/etc/c"]);
+
+        app.handle_undo_result(1, 1);
+        assert!(app.toasts[0].0.contains("1 failed"));
+        assert_eq!(app.get_undo_targets(), vec!["/etc/a"]);
+    }
+
+    #[test]
+    fn test_apply_requires_typed_number() {
+        let mut app = app_with(vec![snap(41, "single", "a"), snap(42, "single", "b")]);
+        app.table_state.select(Some(1));
+        app.open_apply_popup();
+        assert!(!app.apply_confirmed());
+
+        app.apply_confirm_input = String::from("4");
+        assert!(!app.apply_confirmed());
+        app.apply_confirm_input = String::from("42");
+        assert!(app.apply_confirmed());
+
+        // Reopening starts over, and the safeguard can be switched off
+        app.open_apply_popup();
+        assert!(app.apply_confirm_input.is_empty());
+        app.config.confirm_apply_by_number = false;
+        assert!(app.apply_confirmed());
+
+        // Whichever way it is opened, a multi-selection blocks it
+        app.show_apply_popup = false;
+        app.selected_numbers.insert(root_key(41));
+        app.open_apply_popup();
+        assert!(!app.show_apply_popup);
+        assert!(app.message.contains("multi-selection"), "{}", app.message);
+    }
+
+    #[test]
+    fn test_read_only_refuses_changes() {
+        let mut app = app_with(vec![snap(1, "single", "a")]);
+        app.read_only = true;
+        app.open_create_popup();
+        app.open_delete_popup();
+        app.open_apply_popup();
+        app.open_modify_popup();
+        assert!(!app.show_create_popup && !app.show_delete_popup && !app.show_apply_popup && !app.show_modify_popup);
+        assert!(app.message.starts_with("🔒 Read-only"), "{}", app.message);
+
+        app.read_only = false;
+        app.open_delete_popup();
+        assert!(app.show_delete_popup);
+    }
+
+    #[test]
+    fn test_rollback_is_refused_off_btrfs() {
+        let mut home = snap(2, "single", "b");
+        home.config = String::from("home");
+        let mut app = app_with(vec![snap(1, "single", "a"), home]);
+        app.config_fs.insert(String::from("root"), Some(ConfigFs { fstype: String::from("btrfs") }));
+        app.config_fs.insert(String::from("home"), Some(ConfigFs { fstype: String::from("lvm(xfs)") }));
+        assert!(app.apply_supported());
+
+        app.next();
+        assert!(!app.apply_supported());
+        app.open_apply_popup();
+        assert!(!app.show_apply_popup);
+        assert!(app.message.contains("lvm(xfs)"), "{}", app.message);
+
+        // Until the lookup answers, btrfs is assumed
+        app.config_fs.insert(String::from("home"), None);
+        assert!(app.apply_supported());
+    }
+
+    #[test]
+    fn test_delete_rereads_usage_only_when_asked() {
+        let mut root = snap(1, "single", "a");
+        root.subvolume = String::from("/");
+        let mut app = app_with(vec![root]);
+        app.fs_usage = Some((10, 100));
+        assert_eq!(app.delete_usage_subvolume(), None);
+
+        app.config.live_usage_on_delete = true;
+        assert_eq!(app.delete_usage_subvolume().as_deref(), Some("/"));
+        app.config_fs.insert(String::from("root"), Some(ConfigFs { fstype: String::from("ext4") }));
+        assert_eq!(app.delete_usage_subvolume(), None);
+        app.config_fs.clear();
+        app.fs_usage = None;
+        assert_eq!(app.delete_usage_subvolume(), None, "no bar to update");
+    }
+
+    #[test]
+    fn test_pre_is_remembered_until_its_post_is_taken() {
+        let mut app = app_with(vec![snap(1, "single", "a")]);
+        app.read_only = false;
+        app.create_matching_post();
+        assert!(app.message.contains("No pre snapshot"), "{}", app.message);
+
+        let pre = data::NewSnapshot {
+            description: String::from("kernel build"),
+            snapshot_type: String::from("pre"),
+            config: Some(String::from("home")),
+            ..Default::default()
+        };
+        app.handle_create_result(&pre, Some(7));
+        let home = |number| Some((String::from("home"), number));
+        assert_eq!(app.pending_pre, home(7));
+        let mut listed = snap(7, "pre", "kernel build");
+        listed.config = String::from("home");
+        listed.cleanup = Some(String::from("number"));
+        // Root has a #7 of its own, closed by a post; that one isn't ours
+        let mut root_post = snap(8, "post", "other");
+        root_post.pre_number = Some(7);
+        let snapshots = vec![snap(1, "single", "a"), snap(7, "pre", "other"), root_post, listed.clone()];
+        app.set_snapshots(snapshots);
+        assert_eq!(app.pending_pre, home(7));
+
+        // The create popup's post goes to it too when no pre is highlighted
+        app.table_state.select(Some(0));
+        app.create_form.values = [String::from("done"), String::from("post"), String::new(), String::new()];
+        let form_post = app.new_snapshot().unwrap();
+        assert_eq!((form_post.pre_number, form_post.config.as_deref()), (Some(7), Some("home")));
+
+        let matching = app.matching_post().unwrap();
+        assert_eq!((matching.snapshot_type.as_str(), matching.pre_number), ("post", Some(7)));
+        assert_eq!((matching.description.as_str(), matching.cleanup.as_deref()), ("kernel build", Some("number")));
+        assert_eq!(matching.config.as_deref(), Some("home"));
+
+        // A root post of #7 doesn't close it; home's does
+        let created = data::NewSnapshot { snapshot_type: String::from("post"), pre_number: Some(7), ..pre.clone() };
+        app.handle_create_result(&data::NewSnapshot { config: None, ..created.clone() }, Some(9));
+        assert_eq!(app.pending_pre, home(7));
+        app.handle_create_result(&created, Some(8));
+        assert_eq!(app.pending_pre, None);
+
+        // Deleting the pre forgets it as well
+        app.handle_create_result(&pre, Some(9));
+        let mut post = snap(8, "post", "kernel build");
+        post.config = String::from("home");
+        post.pre_number = Some(7);
+        app.set_snapshots(vec![listed, post]);
+        assert_eq!(app.pending_pre, None);
+    }
+
+    #[test]
+    fn test_esc_closes_each_modal_topmost_first() {
+        let mut app = app_with(vec![snap(1, "single", "a")]);
+        assert_eq!(app.open_modal(), None);
+        assert!(!app.close_modal());
+
+        type Opener = fn(&mut App);
+        let openers: [(Modal, Opener); 19] = [
+            (Modal::Quit, |app| app.show_quit_popup = true),
+            (Modal::Help, |app| app.show_help_popup = true),
+            (Modal::About, |app| app.show_about_popup = true),
+            (Modal::History, |app| app.show_history_popup = true),
+            (Modal::Theme, |app| app.show_theme_popup = true),
+            (Modal::Quota, |app| app.quota_popup = Some(String::from("root"))),
+            (Modal::Cleanup, |app| app.show_cleanup_popup = true),
+            (Modal::Report, |app| app.show_report_popup = true),
+            (Modal::Diff, |app| app.show_diff_popup = true),
+            (Modal::Undo, |app| app.show_undo_popup = true),
+            (Modal::Delete, |app| app.show_delete_popup = true),
+            (Modal::Apply, |app| app.show_apply_popup = true),
+            (Modal::Modify, |app| app.show_modify_popup = true),
+            (Modal::Goto, |app| app.show_goto_popup = true),
+            (Modal::Label, |app| app.show_label_popup = true),
+            (Modal::Export, |app| app.show_export_popup = true),
+            (Modal::Create, |app| app.show_create_popup = true),
+            (Modal::Browse, |app| {
+                app.browse = Some(Browse {
+                    config: String::from("root"),
+                    number: 1,
+                    root: PathBuf::from("/.snapshots/1/snapshot"),
+                    dir: PathBuf::from("/.snapshots/1/snapshot"),
+                    entries: Vec::new(),
+                    cursor: 0,
+                    file: None,
+                    file_scroll: 0,
+                })
+            }),
+            (Modal::Filter, |app| {
+                app.filtering = true;
+                app.filter_input = String::from("typed");
+            }),
+        ];
+        // Each on its own
+        for (modal, open) in &openers {
+            open(&mut app);
+            assert_eq!(app.open_modal(), Some(*modal));
+            assert!(app.close_modal());
+            assert_eq!(app.open_modal(), None, "{:?} is still open", modal);
+        }
+        assert!(app.filter_input.is_empty(), "cancelling the filter drops it");
+
+        // All at once, peeled off in order
+        for (_, open) in &openers {
+            open(&mut app);
+        }
+        for (modal, _) in &openers {
+            assert_eq!(app.open_modal(), Some(*modal));
+            app.close_modal();
+        }
+        assert_eq!(app.open_modal(), None);
+    }
+
+    #[test]
+    fn test_rollback_is_refused_outside_the_system_root() {
+        let mut root = snap(1, "single", "a");
+        root.subvolume = String::from("/");
+        let mut home = snap(2, "single", "b");
+        home.config = String::from("home");
+        home.subvolume = String::from("/home");
+        let mut app = app_with(vec![root, home]);
+        app.open_apply_popup();
+        assert!(app.show_apply_popup);
+
+        app.show_apply_popup = false;
+        app.next();
+        assert!(!app.apply_supported());
+        app.open_apply_popup();
+        assert!(!app.show_apply_popup);
+        assert!(app.message.contains("/home") && app.message.contains("undo (u)"), "{}", app.message);
+    }
+
+    #[test]
+    fn test_external_browser_prefers_file_manager_then_editor() {
+        let dir = Path::new("/.snapshots/4/snapshot");
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| pairs.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+        };
+        let open = |pairs| external_browser(env(pairs), dir).join(" ");
+        assert_eq!(open(&[("EDITOR", "nvim"), ("FILE_MANAGER", "ranger")]), "ranger /.snapshots/4/snapshot");
+        assert_eq!(open(&[("EDITOR", "code --wait"), ("FILE_MANAGER", " ")]), "code --wait /.snapshots/4/snapshot");
+        assert_eq!(open(&[("VISUAL", "hx"), ("EDITOR", "nano")]), "hx /.snapshots/4/snapshot");
+        assert_eq!(open(&[("WAYLAND_DISPLAY", "wayland-0")]), "xdg-open /.snapshots/4/snapshot");
+        assert_eq!(open(&[]), "vi /.snapshots/4/snapshot");
+
+        let mut current = snap(0, "single", "current");
+        current.subvolume = String::from("/");
+        let mut app = app_with(vec![current, snap(4, "single", "a")]);
+        assert_eq!(app.external_browse_command(), None);
+        app.table_state.select(Some(1));
+        assert!(app.external_browse_command().is_some());
+        app.remote = Some(String::from("admin@nas"));
+        assert_eq!(app.external_browse_command(), None);
+        assert!(app.message.contains("admin@nas"), "{}", app.message);
+    }
+
+    #[test]
+    fn test_quota_offered_only_when_space_missing_on_btrfs() {
+        let mut current = snap(0, "single", "current");
+        current.used_space = None;
+        let mut sized = snap(1, "single", "a");
+        sized.used_space = Some(4096);
+        let mut home = snap(2, "single", "b");
+        home.config = String::from("home");
+        let mut app = app_with(vec![current, sized, home]);
+        app.read_only = false;
+        assert!(!app.used_space_missing("root"));
+        assert!(app.used_space_missing("home"));
+        assert!(!app.used_space_missing("srv"), "no snapshots, nothing to explain");
+
+        app.open_quota_popup();
+        assert_eq!(app.quota_popup, None);
+        assert!(app.message.contains("already"), "{}", app.message);
+
+        app.table_state.select(Some(2));
+        app.open_quota_popup();
+        assert_eq!(app.quota_popup, None, "filesystem not known yet");
+        app.config_fs.insert(String::from("home"), Some(ConfigFs { fstype: String::from("lvm(xfs)") }));
+        app.open_quota_popup();
+        assert!(app.quota_popup.is_none() && app.message.contains("lvm(xfs)"), "{}", app.message);
+        app.config_fs.insert(String::from("home"), Some(ConfigFs { fstype: String::from("btrfs") }));
+        app.open_quota_popup();
+        assert_eq!(app.quota_popup.as_deref(), Some("home"));
+    }
+
+    #[test]
+    fn test_goto_selects_number_in_filtered_view() {
+        let mut app = app_with(vec![
+            snap(1, "single", "timeline"),
+            snap(2, "pre", "zypper install"),
+            snap(3, "single", "timeline"),
+            snap(4, "post", "zypper install"),
+        ]);
+        app.filter_input = String::from("zypper");
+
+        app.open_goto_popup();
+        app.goto_input = String::from("4");
+        app.goto_snapshot();
+        assert!(!app.show_goto_popup);
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(4));
+
+        // Filtered out, so the highlight stays put
+        app.goto_input = String::from("3");
+        app.goto_snapshot();
+        assert!(app.message.contains("not in the list"), "{}", app.message);
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(4));
+    }
+
+    #[test]
+    fn test_fuzzy_filter_orders_by_score() {
+        let mut app = app_with(vec![
+            snap(1, "single", "cleanup"),
+            snap(2, "single", "timeline"),
+            snap(3, "single", "before upgrade"),
+        ]);
+        app.filter_input = String::from("up");
+        let numbers: Vec<u32> = app.get_filtered_snapshots().iter().map(|s| s.number).collect();
+        assert_eq!(numbers, vec![3, 1]);
+
+        app.filter_input = String::from("tmln");
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(2));
+
+        app.config.fuzzy_filter = false;
+        assert!(app.get_filtered_snapshots().is_empty());
+    }
+
+    fn pair(app_snapshots: &mut [Snapshot], pre: usize, post: usize) {
+        let (pre_number, post_number) = (app_snapshots[pre].number, app_snapshots[post].number);
+        app_snapshots[pre].post_number = Some(post_number);
+        app_snapshots[post].pre_number = Some(pre_number);
+    }
+
+    #[test]
+    fn test_grouping_folds_pre_post_pairs() {
+        let mut snapshots = vec![
+            snap(1, "single", "timeline"),
+            snap(2, "pre", "zypper install"),
+            snap(3, "post", "zypper install"),
+            snap(4, "pre", "interrupted"),
+        ];
+        pair(&mut snapshots, 1, 2);
+        let mut app = app_with(snapshots);
+        app.toggle_grouping();
+
+        let rows = app.get_display_rows();
+        assert_eq!(rows.len(), 3);
+        assert!(matches!(rows[1], DisplayRow::Pair { pre, post, expanded: false } if pre.number == 2 && post.number == 3));
+        // A pre without its post stays a plain row
+        assert!(matches!(rows[2], DisplayRow::Single(s) if s.number == 4));
+
+        // Pair rows act on both halves, and on the post for single targets
+        app.table_state.select(Some(1));
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(3));
+        assert_eq!(app.get_targets_for_delete(), root_keys(&[2, 3]));
+        app.toggle_selection();
+        assert_eq!(app.get_visible_selection(), root_keys(&[2, 3]));
+        app.toggle_selection();
+        assert!(app.get_visible_selection().is_empty());
+    }
+
+    #[test]
+    fn test_pair_expand_and_collapse() {
+        let mut snapshots = vec![snap(2, "pre", "a"), snap(3, "post", "a"), snap(5, "single", "b")];
+        pair(&mut snapshots, 0, 1);
+        let mut app = app_with(snapshots);
+        app.toggle_grouping();
+        app.table_state.select(Some(0));
+
+        app.set_pair_expanded(true);
+        let numbers: Vec<Vec<u32>> = app
+            .get_display_rows()
+            .iter()
+            .map(|row| row.snapshots().iter().map(|s| s.number).collect())
+            .collect();
+        assert_eq!(numbers, vec![vec![2, 3], vec![2], vec![3], vec![5]]);
+
+        // Collapsing from a member row returns to the pair row
+        app.table_state.select(Some(2));
+        app.set_pair_expanded(false);
+        assert_eq!(app.table_state.selected(), Some(0));
+        assert_eq!(app.get_display_rows().len(), 2);
+
+        // Turning grouping off keeps the highlight on the same snapshot
+        app.toggle_grouping();
+        assert_eq!(app.get_selected_snapshot().map(|s| s.number), Some(3));
+    }
+
+    #[test]
+    fn test_expanding_a_pair_leaves_other_configs_alone() {
+        let home = |snapshot: Snapshot| Snapshot { config: String::from("home"), ..snapshot };
+        let mut snapshots = vec![snap(2, "pre", "a"), snap(3, "post", "a"), home(snap(2, "pre", "b")), home(snap(3, "post", "b"))];
+        pair(&mut snapshots, 0, 1);
+        pair(&mut snapshots, 2, 3);
+        let mut app = app_with(snapshots);
+        app.toggle_grouping();
+        assert_eq!(app.get_display_rows().len(), 2);
+
+        app.table_state.select(Some(0));
+        app.set_pair_expanded(true);
+        assert_eq!(app.get_display_rows().len(), 4);
+        assert_eq!(app.expanded_pairs.len(), 1);
+    }
+
+    #[test]
+    fn test_status_fetch_is_debounced() {
+        let mut app = app_with(vec![snap(1, "single", "a"), snap(2, "single", "b")]);
+        app.config.privilege = data::Privilege::Direct;
+
+        app.next();
+        app.schedule_status_fetch();
+        app.on_tick();
+        assert!(app.status_fetch_pending);
+        assert!(app.status_task.is_none());
+
+        app.last_nav = Instant::now() - STATUS_DEBOUNCE;
+        app.on_tick();
+        assert!(!app.status_fetch_pending);
+        assert!(app.status_task.is_some());
+        assert_eq!(app.message, "⏳ Fetching status for 2...");
+
+        // `s` and the footer button run on a worker too, behind the overlay
+        app.request_status();
+        assert!(app.tasks.is_busy());
+        assert!(app.status_streaming());
+    }
+
+    #[test]
+    fn test_config_info_is_fetched_once_per_config() {
+        let mut home = snap(2, "single", "b");
+        home.config = String::from("home");
+        let mut app = app_with(vec![snap(1, "single", "a"), home]);
+        app.config.privilege = data::Privilege::Direct;
+
+        app.toggle_config_info();
+        assert!(app.config_info.contains_key("root"));
+        app.config_info.insert(String::from("root"), Some(Ok(HashMap::new())));
+        app.on_tick();
+        assert_eq!(app.config_info.len(), 1);
+
+        app.next();
+        app.on_tick();
+        assert!(matches!(app.config_info.get("home"), Some(None)));
+
+        // An aborted fetch is retried rather than left loading forever
+        app.cancel_operation();
+        assert_eq!(app.config_info.keys().collect::<Vec<_>>(), ["root"]);
+    }
+
+    #[test]
+    fn test_create_form_is_validated() {
+        let mut app = app_with(vec![snap(7, "pre", "zypper install"), snap(8, "single", "timeline")]);
+        app.open_create_popup();
+        assert!(app.new_snapshot().is_err(), "description is required");
+
+        app.create_input().push_str("manual");
+        app.create_move_field(true);
+        app.create_input().push_str("a=1, b");
+        let err = app.new_snapshot().unwrap_err().to_string();
+        assert!(err.contains("not key=value"), "{}", err);
+        app.create_form.values[3] = String::from("a=1");
+        app.create_form.values[2] = String::from("weekly");
+        assert!(app.new_snapshot().is_err());
+        app.create_form.values[2] = String::from("number");
+
+        let snapshot = app.new_snapshot().unwrap();
+        assert_eq!(snapshot.snapshot_type, "single");
+        assert_eq!(snapshot.cleanup.as_deref(), Some("number"));
+        assert_eq!(snapshot.userdata.unwrap()["a"], "1");
+
+        // A post snapshot pairs with the highlighted pre
+        app.create_form.values[1] = String::from("Post");
+        assert_eq!(app.new_snapshot().unwrap().pre_number, Some(7));
+        app.table_state.select(Some(1));
+        assert!(app.new_snapshot().is_err());
+        app.create_form.values[1] = String::from("middle");
+        assert!(app.new_snapshot().is_err());
+    }
+
+    #[test]
+    fn test_created_snapshots_are_tagged_and_recognised() {
+        let mut app = app_with(Vec::new());
+        app.open_create_popup();
+        app.create_input().push_str("manual");
+        let userdata = app.new_snapshot().unwrap().userdata.unwrap();
+        assert_eq!(userdata["created_by"], CREATED_TAG_VALUE);
+
+        let mut made = snap(3, "single", "manual");
+        made.userdata = Some(userdata);
+        assert!(app.created_here(&made));
+        assert!(!app.created_here(&snap(4, "single", "timeline")));
+
+        // The form's own value is kept, and the tag can be turned off
+        app.create_form.values[3] = String::from("created_by=me");
+        assert_eq!(app.new_snapshot().unwrap().userdata.unwrap()["created_by"], "me");
+        app.config.tag_created = false;
+        app.create_form.values[3].clear();
+        assert_eq!(app.new_snapshot().unwrap().userdata, None);
+    }
+
+    #[test]
+    fn test_quick_create_description_template() {
+        let time = data::format_local_time(1_700_000_000);
+        assert_eq!(expand_description("manual {time}", 1_700_000_000), format!("manual {}", time));
+        assert_eq!(expand_description("{date}: before update", 1_700_000_000), format!("{}: before update", &time[..10]));
+        assert_eq!(expand_description("fixed", 0), "fixed");
+
+        let mut app = app_with(Vec::new());
+        app.read_only = true;
+        app.quick_create();
+        assert!(!app.tasks.is_busy() && app.message.contains("Read-only"), "{}", app.message);
+    }
+
+    #[test]
+    fn test_orphan_pre_snapshots() {
+        let mut pre = snap(2, "pre", "zypper install");
+        pre.post_number = Some(3);
+        let mut post = snap(3, "post", "zypper install");
+        post.pre_number = Some(2);
+        let mut gone = snap(6, "pre", "zypper remove");
+        gone.post_number = Some(7); // Its post was deleted
+        let mut home = snap(5, "pre", "running");
+        home.config = String::from("home");
+        let mut app = app_with(vec![snap(1, "single", "a"), pre, post, snap(4, "pre", "dnf upgrade"), home, gone]);
+
+        let mut orphans: Vec<(&str, u32)> = app.orphan_pre_numbers().into_iter().collect();
+        orphans.sort();
+        assert_eq!(orphans, [("home", 5), ("root", 4), ("root", 6)]);
+
+        app.selected_numbers.extend(root_keys(&[1, 4, 6]));
+        assert_eq!(app.orphan_delete_targets(), [4, 6]);
+        app.selected_numbers.clear();
+        app.table_state.select(Some(0));
+        assert!(app.orphan_delete_targets().is_empty());
+    }
+
+    #[test]
+    fn test_deleting_active_or_default_needs_acknowledgement() {
+        let mut active = snap(3, "single", "booted");
+        active.active = true;
+        let mut default = snap(5, "single", "next boot");
+        default.default = true;
+        let mut app = app_with(vec![snap(1, "single", "a"), active, snap(4, "single", "b"), default]);
+
+        app.table_state.select(Some(0));
+        app.open_delete_popup();
+        assert!(app.boot_critical_delete_targets().is_empty());
+        assert!(!app.delete_needs_acknowledgement());
+
+        app.selected_numbers.extend(root_keys(&[1, 3, 5]));
+        let numbers: Vec<u32> = app.boot_critical_delete_targets().iter().map(|s| s.number).collect();
+        assert_eq!(numbers, [3, 5]);
+        assert!(app.delete_needs_acknowledgement());
+        app.delete_acknowledged = true;
+        assert!(!app.delete_needs_acknowledgement());
+
+        // Reopening asks again
+        app.show_delete_popup = false;
+        app.open_delete_popup();
+        assert!(app.delete_needs_acknowledgement());
+    }
+
+    #[test]
+    fn test_label_popup_labels_the_highlighted_snapshot() {
+        let mut pre = snap(2, "pre", "zypper install");
+        pre.post_number = Some(3);
+        let mut post = snap(3, "post", "zypper install");
+        post.pre_number = Some(2);
+        let mut app = app_with(vec![snap(1, "single", "a"), pre, post]);
+        app.table_state.select(Some(0));
+
+        app.open_label_popup();
+        assert!(app.show_label_popup && app.label_input.is_empty());
+        app.label_input = String::from("known good");
+        app.save_label();
+        assert_eq!(app.labels.get("root", 1), Some("known good"));
+        let rows = app.get_display_rows();
+        assert_eq!(app.row_label(rows[0]), Some("known good"));
+
+        // Editing starts from the current label; clearing it removes it
+        app.open_label_popup();
+        assert_eq!(app.label_input, "known good");
+        app.label_input.clear();
+        app.save_label();
+        assert_eq!(app.labels.get("root", 1), None);
+
+        // A folded pair shows its pre's label until the post has one
+        app.labels.set("root", 2, "before install").unwrap();
+        app.group_pairs = true;
+        let rows = app.get_display_rows();
+        assert_eq!(app.row_label(rows[1]), Some("before install"));
+
+        // Reloading without snapshot 2 drops its label
+        app.set_snapshots(vec![snap(1, "single", "a")]);
+        assert_eq!(app.labels.get("root", 2), None);
+    }
+
+    #[test]
+    fn test_orphan_cleanup_picks_unmatched_halves() {
+        let mut pre = snap(2, "pre", "zypper install");
+        pre.post_number = Some(3);
+        let mut post = snap(3, "post", "zypper install");
+        post.pre_number = Some(2);
+        let mut lost_pre = snap(5, "post", "zypper remove");
+        lost_pre.pre_number = Some(4); // Its pre was deleted
+        let mut app = app_with(vec![snap(1, "single", "a"), pre, post, lost_pre, snap(6, "pre", "dnf upgrade")]);
+        app.selected_numbers.insert(root_key(1));
+
+        let root = |number| (String::from("root"), number);
+        assert_eq!(app.find_orphan_pairs(), [root(5), root(6)]);
+        app.open_orphan_cleanup();
+        assert!(app.show_delete_popup && !app.orphan_targets.is_empty());
+        let numbers: Vec<u32> = app.delete_target_snapshots().iter().map(|s| s.number).collect();
+        assert_eq!(numbers, [5, 6]);
+        assert_eq!(app.selected_numbers, HashSet::from([root_key(1)]));
+
+        // An ordinary delete afterwards isn't labelled as a cleanup
+        app.show_delete_popup = false;
+        app.open_delete_popup();
+        assert!(app.orphan_targets.is_empty());
+
+        let mut app = app_with(vec![snap(1, "single", "a")]);
+        app.open_orphan_cleanup();
+        assert!(!app.show_delete_popup && app.selected_numbers.is_empty());
+        assert!(app.message.contains("No orphan"), "{}", app.message);
+    }
+
+    #[test]
+    fn test_orphan_cleanup_keeps_to_the_orphans_config() {
+        let mut orphan = snap(5, "pre", "interrupted");
+        orphan.config = String::from("home");
+        let mut app = app_with(vec![snap(5, "single", "root five"), orphan]);
+
+        assert_eq!(app.find_orphan_pairs(), [(String::from("home"), 5)]);
+        app.open_orphan_cleanup();
+        let targets: Vec<(&str, u32)> = app.delete_target_snapshots().iter().map(|s| (s.config.as_str(), s.number)).collect();
+        assert_eq!(targets, [("home", 5)]);
+        assert!(app.boot_critical_delete_targets().is_empty());
+    }
+
+    #[test]
+    fn test_delete_of_highlight_stays_in_its_config() {
+        let mut home = snap(5, "single", "home five");
+        home.config = String::from("home");
+        let mut app = app_with(vec![snap(5, "single", "root five"), home]);
+        app.table_state.select(Some(0));
+        let highlighted = app.get_selected_snapshot().map(|s| s.config.clone());
+
+        let targets: Vec<(String, u32)> = app.delete_target_snapshots().iter().map(|s| (s.config.clone(), s.number)).collect();
+        assert_eq!(targets, [(highlighted.unwrap(), 5)]);
+    }
+
+    #[test]
+    fn test_modify_only_sends_edited_fields() {
+        let mut snapshot = snap(7, "single", "old description");
+        snapshot.cleanup = Some(String::from("number"));
+        let mut app = app_with(vec![snapshot]);
+
+        app.open_modify_popup();
+        assert!(app.show_modify_popup);
+        assert_eq!(app.modify_form.values[1], "number");
+        assert_eq!(app.modify_changes().unwrap(), None);
+
+        app.modify_input().push_str(" (fixed)");
+        app.modify_move_field(true); // Wraps round to userdata
+        app.modify_input().push_str("important=yes");
+        let changes = app.modify_changes().unwrap().unwrap();
+        assert_eq!(changes.number, 7);
+        assert_eq!(changes.description.as_deref(), Some("old description (fixed)"));
+        assert_eq!(changes.cleanup, None);
+        assert_eq!(changes.userdata.unwrap()["important"], "yes");
+
+        app.modify_input().push_str(", broken");
+        assert!(app.modify_changes().is_err());
+    }
+
+    #[test]
+    fn test_delete_result_lists_failures_by_number() {
+        let mut app = app_with(Vec::new());
+        let failed: Vec<(u32, String)> = [5, 9].into_iter().map(|n| (n, format!("Failed to delete snapshot {}: busy", n))).collect();
+        app.handle_delete_result(&[3, 4], &failed);
+
+        let (text, _, severity) = &app.toasts[0];
+        assert_eq!(*severity, Severity::Error);
+        assert_eq!(text, "🗑️ Deleted 2 snapshots (2 failed ❌)\n  Failed: #5, #9");
+        assert_eq!(app.status_text, "#5  Failed to delete snapshot 5: busy\n#9  Failed to delete snapshot 9: busy");
+        assert!(app.status_range.is_none());
+
+        app.toasts.clear();
+        app.handle_delete_result(&[], &failed[..1]);
+        assert_eq!(app.toasts[0].0, "❌ Failed to delete #5");
+
+        // A clean run leaves the status panel alone
+        app.toasts.clear();
+        app.handle_delete_result(&[7], &[]);
+        assert_eq!(app.toasts[0].0, "🗑️ Deleted 1 snapshot");
+        assert_eq!(app.status_text.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_visual_mode_selects_range_in_filtered_view() {
+        let mut app = app_with(vec![
+            snap(1, "single", "timeline"),
+            snap(2, "single", "zypper"),
+            snap(3, "single", "timeline"),
+            snap(4, "single", "zypper"),
+            snap(5, "single", "zypper"),
+            snap(6, "single", "zypper"),
+        ]);
+        app.filter_input = String::from("zypper");
+        app.table_state.select(Some(0));
+        app.selected_numbers.insert(root_key(1)); // Hidden by the filter
+
+        app.toggle_visual_mode();
+        app.next();
+        app.next();
+        assert_eq!(app.get_visible_selection(), root_keys(&[2, 4, 5]));
+
+        // Moving back shrinks the range towards the anchor
+        app.previous();
+        assert_eq!(app.get_visible_selection(), root_keys(&[2, 4]));
+        assert!(app.selected_numbers.contains(&root_key(1)));
+
+        // Leaving visual mode keeps the selection and stops extending it
+        app.toggle_visual_mode();
+        app.next();
+        assert_eq!(app.select_anchor, None);
+        assert_eq!(app.get_visible_selection(), root_keys(&[2, 4]));
     }
 }
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+
+/// Where `copy` put the text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Copied {
+    Clipboard(&'static str),
+    /// No clipboard tool worked, so the text was saved here instead.
+    File(PathBuf),
+}
+
+// Clipboard helpers tried in order, depending on the session type.
+fn tools() -> Vec<(&'static str, &'static [&'static str])> {
+    let mut tools = Vec::new();
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        tools.push(("wl-copy", &[][..]));
+    }
+    if env::var_os("DISPLAY").is_some() {
+        tools.push(("xclip", &["-selection", "clipboard"][..]));
+        tools.push(("xsel", &["--clipboard", "--input"][..]));
+    }
+    tools
+}
+
+/// Puts `text` on the system clipboard, falling back to a file in the temp
+/// directory when no clipboard is reachable (e.g. over SSH or on a console).
+pub fn copy(text: &str) -> Result<Copied> {
+    for (program, args) in tools() {
+        if pipe_to(program, args, text).is_ok() {
+            return Ok(Copied::Clipboard(program));
+        }
+    }
+
+    let (path, mut file) = create_fallback(&env::temp_dir())?;
+    file.write_all(text.as_bytes()).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(Copied::File(path))
+}
+
+// A new file only this user can read, under a name nothing else holds: the
+// TUI often runs as root, and the temp directory is shared, so an existing
+// path (perhaps a planted symlink) is never opened
+fn create_fallback(dir: &Path) -> Result<(PathBuf, File)> {
+    const ATTEMPTS: u32 = 100;
+
+    for attempt in 0..ATTEMPTS {
+        let name = match attempt {
+            0 => format!("snapper-tui-{}.txt", process::id()),
+            n => format!("snapper-tui-{}-{}.txt", process::id(), n),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to create {}", path.display())),
+        }
+    }
+    anyhow::bail!("Failed to create a file for the copied text in {}", dir.display())
+}
+
+fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<()> {
+    // wl-copy and xclip leave a process behind to serve the selection, which
+    // would keep captured output pipes open, so their output is discarded
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    anyhow::ensure!(status.success(), "{} exited with {}", program, status);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_fallback_never_opens_an_existing_path() {
+        let dir = env::temp_dir().join(format!("snapper-tui-clipboard-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let victim = dir.join("victim");
+        fs::write(&victim, "keep me").unwrap();
+        std::os::unix::fs::symlink(&victim, dir.join(format!("snapper-tui-{}.txt", process::id()))).unwrap();
+
+        let (path, mut file) = create_fallback(&dir).unwrap();
+        file.write_all(b"copied").unwrap();
+        assert_eq!(path, dir.join(format!("snapper-tui-{}-1.txt", process::id())));
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "keep me");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,537 @@
+use crate::app::{Column, SortKey, DEFAULT_COLUMNS};
+use crate::data::Privilege;
+use crate::keys::{self, Action, KeyMap, ACTIONS};
+use ratatui::crossterm::event::KeyCode;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// User preferences stored in `~/.config/snapper-tui/config.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub default_sort: SortKey,
+    pub sort_ascending: bool,
+    pub default_config: Option<String>,
+    pub theme: String,
+    /// Escalation helper for commands that need root.
+    pub privilege: Privilege,
+    /// Require typing the snapshot number before a rollback.
+    pub confirm_apply_by_number: bool,
+    /// Fuzzy-match the filter text; `false` means plain substring search.
+    pub fuzzy_filter: bool,
+    /// Moving past the last row goes back to the first, and vice versa.
+    pub wrap_navigation: bool,
+    /// Reload the snapshot list this often; `None` (written as 0) disables it.
+    pub auto_refresh_secs: Option<u64>,
+    /// After this long the loading overlay suggests cancelling; `None`
+    /// (written as 0) never does.
+    pub slow_hint_secs: Option<u64>,
+    /// How often a snapper call that lost a lock race is retried.
+    pub lock_retries: u32,
+    /// Run `sudo -A` so the password comes from `SUDO_ASKPASS` rather than
+    /// a TTY prompt. Only meaningful with `privilege = "sudo"`.
+    pub sudo_askpass: bool,
+    /// Append privileged commands to `history.log` as well as keeping them
+    /// in memory for the `L` overlay.
+    pub history_file: bool,
+    /// Add `<created_tag_key>=snapper-tui` to the userdata of snapshots made
+    /// with the create popup.
+    pub tag_created: bool,
+    /// Userdata key that tags, and picks out, snapshots made by the TUI.
+    pub created_tag_key: String,
+    /// Description of snapshots made with the quick-create key (`N`);
+    /// `{time}` and `{date}` become the current local time and date.
+    pub default_description_template: String,
+    /// Name of the loading spinner preset, one of `app::SPINNERS`.
+    pub spinner_style: String,
+    /// Fades, the splash and spinner motion; off suits slow or remote terminals.
+    pub animations: bool,
+    /// Re-read btrfs usage after every snapshot a bulk delete removes, so
+    /// the header's usage bar tracks the space freed. One extra `btrfs`
+    /// call per snapshot.
+    pub live_usage_on_delete: bool,
+    /// Dense view: no gaps, a one-line header and footer and single-line
+    /// panel borders, so small screens fit more rows. Toggled with `z`.
+    pub dense: bool,
+    /// Columns of the snapshot table, in order.
+    pub columns: Vec<Column>,
+    /// `[keys]` rebindings, in `keys::ACTIONS` order; unlisted actions keep
+    /// their default keys.
+    pub keys: Vec<(Action, Vec<KeyCode>)>,
+    /// Problems found while loading, surfaced once in the status panel.
+    pub warnings: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            default_sort: SortKey::Number,
+            sort_ascending: true,
+            default_config: None,
+            theme: String::from("dracula"),
+            privilege: Privilege::Sudo,
+            confirm_apply_by_number: true,
+            fuzzy_filter: true,
+            wrap_navigation: true,
+            auto_refresh_secs: None,
+            slow_hint_secs: Some(30),
+            lock_retries: 3,
+            sudo_askpass: false,
+            history_file: false,
+            tag_created: true,
+            created_tag_key: String::from("created_by"),
+            default_description_template: String::from("manual {time}"),
+            spinner_style: String::from("braille"),
+            animations: true,
+            live_usage_on_delete: false,
+            dense: false,
+            columns: DEFAULT_COLUMNS.to_vec(),
+            keys: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("snapper-tui").join("config.toml"))
+    }
+
+    /// Loads the config file, falling back to defaults when it is missing.
+    pub fn load() -> Config {
+        let Some(path) = Config::path() else {
+            return Config::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => Config::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => Config {
+                warnings: vec![format!("Could not read {}: {}", path.display(), e)],
+                ..Config::default()
+            },
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Config::path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+
+    pub fn parse(text: &str) -> Config {
+        let mut config = Config::default();
+        let (doc, mut warnings) = parse_toml(text);
+        let root = doc.get("").cloned().unwrap_or_default();
+
+        for (key, value) in root {
+            match (key.as_str(), value) {
+                ("default_sort", Value::String(name)) => match SortKey::from_name(&name) {
+                    Some(sort) => config.default_sort = sort,
+                    None => warnings.push(format!("Unknown default_sort '{}'", name)),
+                },
+                ("sort_ascending", Value::Bool(b)) => config.sort_ascending = b,
+                ("default_config", Value::String(name)) => {
+                    config.default_config = Some(name).filter(|n| !n.is_empty());
+                }
+                ("theme", Value::String(name)) => config.theme = name,
+                ("privilege", Value::String(name)) => match Privilege::from_name(&name) {
+                    Some(privilege) => config.privilege = privilege,
+                    None => warnings.push(format!("Unknown privilege '{}'", name)),
+                },
+                ("confirm_apply_by_number", Value::Bool(b)) => config.confirm_apply_by_number = b,
+                ("fuzzy_filter", Value::Bool(b)) => config.fuzzy_filter = b,
+                ("wrap_navigation", Value::Bool(b)) => config.wrap_navigation = b,
+                ("auto_refresh_secs", Value::Integer(secs)) => match u64::try_from(secs) {
+                    Ok(secs) => config.auto_refresh_secs = Some(secs).filter(|&s| s > 0),
+                    Err(_) => warnings.push(format!("Invalid auto_refresh_secs {}", secs)),
+                },
+                ("slow_hint_secs", Value::Integer(secs)) => match u64::try_from(secs) {
+                    Ok(secs) => config.slow_hint_secs = Some(secs).filter(|&s| s > 0),
+                    Err(_) => warnings.push(format!("Invalid slow_hint_secs {}", secs)),
+                },
+                ("history_file", Value::Bool(b)) => config.history_file = b,
+                ("default_description_template", Value::String(template)) => match template.trim() {
+                    "" => warnings.push(String::from("default_description_template is empty")),
+                    _ => config.default_description_template = template,
+                },
+                ("spinner_style", Value::String(name)) => config.spinner_style = name,
+                ("animations", Value::Bool(b)) => config.animations = b,
+                ("live_usage_on_delete", Value::Bool(b)) => config.live_usage_on_delete = b,
+                ("dense", Value::Bool(b)) => config.dense = b,
+                ("columns", Value::Array(names)) => {
+                    let mut columns = Vec::new();
+                    for name in names {
+                        match name {
+                            Value::String(name) => match Column::from_name(&name) {
+                                Some(column) if columns.contains(&column) => {
+                                    warnings.push(format!("Column '{}' is listed twice", name));
+                                }
+                                Some(column) => columns.push(column),
+                                None => warnings.push(format!("Unknown column '{}'", name)),
+                            },
+                            _ => warnings.push(String::from("Invalid entry in columns")),
+                        }
+                    }
+                    // An empty table would be no use
+                    if columns.is_empty() {
+                        warnings.push(String::from("columns names no known column; using the defaults"));
+                    } else {
+                        config.columns = columns;
+                    }
+                }
+                ("lock_retries", Value::Integer(n)) => match u32::try_from(n) {
+                    Ok(n) => config.lock_retries = n,
+                    Err(_) => warnings.push(format!("Invalid lock_retries {}", n)),
+                },
+                ("sudo_askpass", Value::Bool(b)) => config.sudo_askpass = b,
+                ("tag_created", Value::Bool(b)) => config.tag_created = b,
+                // It ends up in `--userdata key=value,...`
+                ("created_tag_key", Value::String(key)) => match key.trim() {
+                    key if key.is_empty() || key.contains(['=', ',']) => {
+                        warnings.push(format!("Invalid created_tag_key '{}'", key));
+                    }
+                    key => config.created_tag_key = key.to_string(),
+                },
+                (key, _) => warnings.push(format!("Ignoring config key '{}'", key)),
+            }
+        }
+        // pkexec and doas have their own prompts; `-A` is sudo's alone
+        if config.sudo_askpass && config.privilege != Privilege::Sudo {
+            warnings.push(format!("sudo_askpass has no effect with privilege '{}'", config.privilege.name()));
+        }
+
+        for (name, value) in doc.get("keys").cloned().unwrap_or_default() {
+            let Some(action) = Action::from_name(&name) else {
+                warnings.push(format!("Unknown key action '{}'", name));
+                continue;
+            };
+            let specs = match value {
+                Value::String(spec) => vec![Value::String(spec)],
+                Value::Array(specs) => specs,
+                _ => {
+                    warnings.push(format!("Invalid key binding for {}", name));
+                    continue;
+                }
+            };
+            let mut codes = Vec::new();
+            for spec in specs {
+                match spec {
+                    Value::String(spec) => match keys::parse_key(&spec) {
+                        Some(code) => codes.push(code),
+                        None => warnings.push(format!("Unknown key '{}' for {}", spec, name)),
+                    },
+                    _ => warnings.push(format!("Invalid key binding for {}", name)),
+                }
+            }
+            // Nothing usable keeps the defaults rather than unbinding the action
+            if !codes.is_empty() {
+                config.keys.push((action, codes));
+            }
+        }
+        // A fixed order keeps conflict resolution independent of the file
+        config.keys.sort_by_key(|(action, _)| ACTIONS.iter().position(|a| a == action));
+        warnings.extend(KeyMap::build(&config.keys).1);
+
+        config.warnings = warnings;
+        config
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from("# snapper-TUI-rust preferences\n");
+        out.push_str(&format!("default_sort = {}\n", quote(self.default_sort.name())));
+        out.push_str(&format!("sort_ascending = {}\n", self.sort_ascending));
+        out.push_str(&format!(
+            "default_config = {}\n",
+            quote(self.default_config.as_deref().unwrap_or(""))
+        ));
+        out.push_str(&format!("theme = {}\n", quote(&self.theme)));
+        out.push_str(&format!("privilege = {}\n", quote(self.privilege.name())));
+        out.push_str(&format!("confirm_apply_by_number = {}\n", self.confirm_apply_by_number));
+        out.push_str(&format!("fuzzy_filter = {}\n", self.fuzzy_filter));
+        out.push_str(&format!("wrap_navigation = {}\n", self.wrap_navigation));
+        out.push_str(&format!("auto_refresh_secs = {}\n", self.auto_refresh_secs.unwrap_or(0)));
+        out.push_str(&format!("slow_hint_secs = {}\n", self.slow_hint_secs.unwrap_or(0)));
+        out.push_str(&format!("lock_retries = {}\n", self.lock_retries));
+        out.push_str(&format!("sudo_askpass = {}\n", self.sudo_askpass));
+        out.push_str(&format!("history_file = {}\n", self.history_file));
+        out.push_str(&format!("tag_created = {}\n", self.tag_created));
+        out.push_str(&format!("created_tag_key = {}\n", quote(&self.created_tag_key)));
+        out.push_str(&format!("default_description_template = {}\n", quote(&self.default_description_template)));
+        out.push_str(&format!("spinner_style = {}\n", quote(&self.spinner_style)));
+        out.push_str(&format!("animations = {}\n", self.animations));
+        out.push_str(&format!("live_usage_on_delete = {}\n", self.live_usage_on_delete));
+        out.push_str(&format!("dense = {}\n", self.dense));
+        let columns: Vec<String> = self.columns.iter().map(|column| quote(column.name())).collect();
+        out.push_str(&format!("columns = [{}]\n", columns.join(", ")));
+        if !self.keys.is_empty() {
+            out.push_str("\n[keys]\n");
+            for (action, codes) in &self.keys {
+                let specs: Vec<String> = codes.iter().map(|&code| quote(&keys::key_name(code))).collect();
+                out.push_str(&format!("{} = [{}]\n", action.name(), specs.join(", ")));
+            }
+        }
+        out
+    }
+}
+
+// A deliberately small TOML subset: `[section]` headers, `key = value` pairs
+// with string, integer, boolean and flat array values, and `#` comments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+type Document = HashMap<String, HashMap<String, Value>>;
+
+fn parse_toml(text: &str) -> (Document, Vec<String>) {
+    let mut doc: Document = HashMap::new();
+    let mut warnings = Vec::new();
+    let mut section = String::new();
+
+    for (lineno, raw) in text.lines().enumerate() {
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_string();
+            continue;
+        }
+        let parsed = line
+            .split_once('=')
+            .and_then(|(key, value)| Some((key.trim().trim_matches('"').to_string(), parse_value(value.trim())?)));
+        match parsed {
+            Some((key, value)) if !key.is_empty() => {
+                doc.entry(section.clone()).or_default().insert(key, value);
+            }
+            _ => warnings.push(format!("Invalid config line {}: {}", lineno + 1, raw.trim())),
+        }
+    }
+
+    (doc, warnings)
+}
+
+// Drops a trailing `# comment`, ignoring `#` inside quoted strings.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        return split_array(inner)
+            .into_iter()
+            .map(parse_value)
+            .collect::<Option<Vec<_>>>()
+            .map(Value::Array);
+    }
+    if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        return unescape(inner).map(Value::String);
+    }
+    match text {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => text.replace('_', "").parse().ok().map(Value::Integer),
+    }
+}
+
+fn split_array(inner: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (idx, c) in inner.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                items.push(inner[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(inner[start..].trim());
+    items.into_iter().filter(|item| !item.is_empty()).collect()
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            '"' => out.push('"'),
+            '\\' => out.push('\\'),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+            # comment
+            default_sort = "date"   # trailing comment
+            sort_ascending = false
+            default_config = "home"
+            theme = "gruvbox"
+            privilege = "pkexec"
+            auto_refresh_secs = 30
+            lock_retries = 5
+            "#,
+        );
+        assert_eq!(config.default_sort, SortKey::Date);
+        assert!(!config.sort_ascending);
+        assert_eq!(config.default_config.as_deref(), Some("home"));
+        assert_eq!(config.theme, "gruvbox");
+        assert_eq!(config.privilege, Privilege::Pkexec);
+        assert_eq!(config.auto_refresh_secs, Some(30));
+        assert_eq!(config.lock_retries, 5);
+        assert!(config.warnings.is_empty());
+        assert_eq!(Config::parse("auto_refresh_secs = 0").auto_refresh_secs, None);
+
+        assert!(Config::parse("sudo_askpass = true").warnings.is_empty());
+        let config = Config::parse("sudo_askpass = true\nprivilege = \"doas\"");
+        assert!(config.sudo_askpass);
+        assert_eq!(config.warnings, ["sudo_askpass has no effect with privilege 'doas'"]);
+    }
+
+    #[test]
+    fn test_invalid_entries_fall_back_to_defaults() {
+        let config = Config::parse("default_sort = \"bogus\"\nsort_ascending = maybe\nunknown = 1\n");
+        assert_eq!(config.default_sort, SortKey::Number);
+        assert!(config.sort_ascending);
+        assert_eq!(config.warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_created_tag_key_must_fit_userdata() {
+        let config = Config::parse("tag_created = false\ncreated_tag_key = \"origin\"\n");
+        assert!(!config.tag_created);
+        assert_eq!(config.created_tag_key, "origin");
+
+        for bad in ["", "a=b", "a,b"] {
+            let config = Config::parse(&format!("created_tag_key = \"{}\"\n", bad));
+            assert_eq!(config.created_tag_key, "created_by");
+            assert_eq!(config.warnings.len(), 1, "{:?}", config.warnings);
+        }
+    }
+
+    #[test]
+    fn test_columns_skip_unknown_names() {
+        let config = Config::parse("columns = [\"number\", \"cleanup\", \"bogus\", \"number\", \"description\"]\n");
+        assert_eq!(config.columns, [Column::Number, Column::Cleanup, Column::Description]);
+        assert_eq!(config.warnings.len(), 2, "{:?}", config.warnings);
+
+        let config = Config::parse("columns = [\"bogus\"]\n");
+        assert_eq!(config.columns, DEFAULT_COLUMNS);
+        assert_eq!(config.warnings.len(), 2, "{:?}", config.warnings);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config = Config {
+            default_sort: SortKey::UsedSpace,
+            sort_ascending: false,
+            default_config: Some(String::from("root")),
+            theme: String::from("say \"hi\" # not a comment"),
+            privilege: Privilege::Direct,
+            confirm_apply_by_number: false,
+            fuzzy_filter: false,
+            wrap_navigation: false,
+            auto_refresh_secs: Some(60),
+            slow_hint_secs: None,
+            lock_retries: 0,
+            sudo_askpass: false,
+            history_file: true,
+            tag_created: false,
+            created_tag_key: String::from("made-by"),
+            default_description_template: String::from("quick {date}"),
+            spinner_style: String::from("line"),
+            animations: false,
+            live_usage_on_delete: true,
+            dense: true,
+            columns: vec![Column::Config, Column::Number, Column::Description, Column::Subvolume],
+            keys: vec![
+                (Action::Create, vec![KeyCode::Char('n')]),
+                (Action::Quit, vec![KeyCode::Char('"'), KeyCode::F(10)]),
+            ],
+            warnings: Vec::new(),
+        };
+        assert_eq!(Config::parse(&config.to_toml()), config);
+    }
+
+    #[test]
+    fn test_key_bindings() {
+        let config = Config::parse("[keys]\nquit = \"Z\"\ndelete = [\"delete\", \"D\"]\nfly = \"f\"\nstatus = \"hyper\"\n");
+        assert_eq!(
+            config.keys,
+            vec![
+                (Action::Delete, vec![KeyCode::Delete, KeyCode::Char('D')]),
+                (Action::Quit, vec![KeyCode::Char('Z')]),
+            ]
+        );
+        assert_eq!(config.warnings.len(), 2, "{:?}", config.warnings);
+
+        // Conflicts are reported at load
+        let config = Config::parse("[keys]\ncreate = \"d\"\n");
+        assert_eq!(config.warnings.len(), 1, "{:?}", config.warnings);
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let (doc, warnings) = parse_toml("[keys]\nquit = [\"q\", \"ctrl-c\"]\ncount = 1_000\n");
+        assert!(warnings.is_empty());
+        let keys = &doc["keys"];
+        assert_eq!(
+            keys["quit"],
+            Value::Array(vec![Value::String("q".into()), Value::String("ctrl-c".into())])
+        );
+        assert_eq!(keys["count"], Value::Integer(1000));
+    }
+}
@@ -1,48 +1,515 @@
+use crate::history;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::process::Command;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Snapshot {
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub config: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub subvolume: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub number: u32,
-    #[serde(rename = "type", default)]
+    #[serde(rename = "type", default, deserialize_with = "null_as_default")]
     pub snapshot_type: String,
     #[serde(rename = "pre-number")]
     pub pre_number: Option<u32>,
     #[serde(rename = "post-number")]
     pub post_number: Option<u32>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub date: String,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub user: String,
     pub cleanup: Option<String>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub description: String,
     pub userdata: Option<HashMap<String, String>>,
     #[serde(rename = "used-space")]
     pub used_space: Option<u64>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub default: bool,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub active: bool,
+    /// `date` in seconds since the epoch, when it parses.
+    #[serde(skip)]
+    pub timestamp: Option<i64>,
+    /// Fields this version doesn't know, such as columns a newer snapper
+    /// added or renamed, kept so they are still shown and exported.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-pub fn list_snapshots() -> Result<Vec<Snapshot>> {
-    let output = Command::new("snapper")
-        .args(&[
+// snapper prints `null` for columns it has no value for, which `default`
+// alone doesn't cover
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+/// Shared handle that lets the UI thread abort the snapper process a worker
+/// thread is currently waiting on.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<Mutex<CancelState>>);
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: bool,
+    child: Option<Child>,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.cancelled = true;
+        if let Some(child) = state.child.as_mut() {
+            // Killing a child that has already exited is a harmless no-op
+            let _ = child.kill();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).cancelled
+    }
+}
+
+/// Failures the UI handles specially rather than printing as a one-liner.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapperError {
+    /// The executable could not be launched at all.
+    NotFound(String),
+    /// The user dismissed the privilege escalation prompt.
+    AuthCancelled,
+    /// ssh couldn't connect to or log in on `destination`.
+    Unreachable { destination: String, reason: String },
+}
+
+impl std::fmt::Display for SnapperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapperError::NotFound(program) => {
+                write!(f, "{} not found — is it installed and on PATH?", program)
+            }
+            SnapperError::AuthCancelled => f.write_str("Authentication cancelled"),
+            SnapperError::Unreachable { destination, reason } => {
+                write!(f, "Can't reach {} over SSH: {}", destination, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapperError {}
+
+/// How commands that modify snapshots gain root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Privilege {
+    Sudo,
+    /// Polkit; shows a graphical auth dialog, so it works without a TTY.
+    Pkexec,
+    Doas,
+    /// Run snapper directly, e.g. when the TUI itself runs as root.
+    Direct,
+}
+
+impl Privilege {
+    pub fn name(self) -> &'static str {
+        match self {
+            Privilege::Sudo => "sudo",
+            Privilege::Pkexec => "pkexec",
+            Privilege::Doas => "doas",
+            Privilege::Direct => "none",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Privilege> {
+        match name {
+            "sudo" => Some(Privilege::Sudo),
+            "pkexec" => Some(Privilege::Pkexec),
+            "doas" => Some(Privilege::Doas),
+            "none" => Some(Privilege::Direct),
+            _ => None,
+        }
+    }
+
+    fn snapper(self) -> Command {
+        self.command("snapper")
+    }
+
+    fn command(self, program: &str) -> Command {
+        self.command_with(program, SUDO_ASKPASS.load(Ordering::Relaxed))
+    }
+
+    fn command_with(self, program: &str, askpass: bool) -> Command {
+        match self {
+            Privilege::Direct => Command::new(program),
+            helper => {
+                let mut cmd = Command::new(helper.name());
+                if askpass && helper == Privilege::Sudo {
+                    cmd.arg("-A");
+                }
+                cmd.arg(program);
+                cmd
+            }
+        }
+    }
+
+    // pkexec exits 126 when the dialog is dismissed and 127 when
+    // authorization is refused.
+    fn auth_cancelled(self, status: &ExitStatus) -> bool {
+        self == Privilege::Pkexec && matches!(status.code(), Some(126 | 127))
+    }
+}
+
+// sudo can't prompt on the alternate screen, but an askpass helper can
+static SUDO_ASKPASS: AtomicBool = AtomicBool::new(false);
+
+/// Makes sudo ask for the password through `SUDO_ASKPASS` (`sudo -A`).
+/// The other privilege helpers are unaffected.
+pub fn set_sudo_askpass(askpass: bool) {
+    SUDO_ASKPASS.store(askpass, Ordering::Relaxed);
+}
+
+/// Whether privileged commands stand a chance: the TUI runs as root, or the
+/// helper `privilege` names is installed. A password may still be needed.
+/// `Direct` leaves the decision to snapper's `ALLOW_USERS`, so it passes.
+pub fn can_escalate(privilege: Privilege) -> bool {
+    // The helper lives on the other machine, so it can only be tried
+    if runner().is_remote() {
+        return true;
+    }
+    // SAFETY: geteuid has no preconditions and cannot fail
+    if unsafe { libc::geteuid() } == 0 || privilege == Privilege::Direct {
+        return true;
+    }
+    std::env::var_os("PATH").is_some_and(|path| in_path(privilege.name(), &path))
+}
+
+fn in_path(program: &str, path: &std::ffi::OsStr) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::env::split_paths(path).any(|dir| {
+        fs::metadata(dir.join(program)).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    })
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn cancelled_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "operation cancelled")
+}
+
+// Like `Command::output`, but parks the child in `cancel` so it can be killed
+// while we wait. Output is drained on reader threads to avoid pipe stalls,
+// and complete stdout lines are handed to `on_lines` as they arrive.
+fn run_streaming(cmd: &mut Command, cancel: &CancelToken, mut on_lines: impl FnMut(Vec<String>)) -> io::Result<Output> {
+    if cancel.is_cancelled() {
+        return Err(cancelled_error());
+    }
+
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let (chunk_tx, chunk_rx) = mpsc::channel();
+    let stdout_reader = thread::spawn(move || read_pipe_chunks(stdout, chunk_tx));
+    let stderr_reader = thread::spawn(move || read_pipe(stderr));
+
+    cancel.0.lock().unwrap_or_else(PoisonError::into_inner).child = Some(child);
+
+    // Bytes received after the last newline
+    let mut partial = Vec::new();
+    let mut forward = |partial: &mut Vec<u8>| {
+        partial.extend(chunk_rx.try_iter().flatten());
+        let lines = take_lines(partial);
+        if !lines.is_empty() {
+            on_lines(lines);
+        }
+    };
+
+    let status = loop {
+        {
+            let mut guard = cancel.0.lock().unwrap_or_else(PoisonError::into_inner);
+            let state = &mut *guard;
+            let Some(child) = state.child.as_mut() else {
+                return Err(cancelled_error());
+            };
+            if state.cancelled {
+                let _ = child.kill();
+                let _ = child.wait();
+                state.child = None;
+                return Err(cancelled_error());
+            }
+            if let Some(status) = child.try_wait()? {
+                state.child = None;
+                break status;
+            }
+        }
+        forward(&mut partial);
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let stdout = stdout_reader.join().unwrap_or_default();
+    forward(&mut partial);
+    if !partial.is_empty() {
+        on_lines(vec![String::from_utf8_lossy(&partial).into_owned()]);
+    }
+
+    Ok(Output {
+        status,
+        stdout,
+        stderr: stderr_reader.join().unwrap_or_default(),
+    })
+}
+
+// Removes every newline-terminated line from the front of `partial`.
+fn take_lines(partial: &mut Vec<u8>) -> Vec<String> {
+    let Some(end) = partial.iter().rposition(|&b| b == b'\n') else {
+        return Vec::new();
+    };
+    let complete: Vec<u8> = partial.drain(..=end).collect();
+    complete[..end]
+        .split(|&b| b == b'\n')
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect()
+}
+
+/// Runs the commands the data layer builds, on this machine or another one.
+pub trait CommandRunner: Send + Sync {
+    /// Runs `cmd` to completion, handing complete stdout lines to `on_lines`
+    /// as they arrive. A missing executable is `SnapperError::NotFound`.
+    /// Each argument must reach the program exactly as given; a runner that
+    /// goes through a shell quotes every word with `shell_quote`.
+    fn run(&self, cmd: &mut Command, cancel: &CancelToken, on_lines: &mut dyn FnMut(Vec<String>)) -> Result<Output>;
+
+    /// Whether the commands run on another machine, whose files can't be
+    /// read directly.
+    fn is_remote(&self) -> bool {
+        false
+    }
+}
+
+/// Runs commands here.
+pub struct Local;
+
+impl CommandRunner for Local {
+    fn run(&self, cmd: &mut Command, cancel: &CancelToken, on_lines: &mut dyn FnMut(Vec<String>)) -> Result<Output> {
+        run_streaming(cmd, cancel, on_lines).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => SnapperError::NotFound(cmd.get_program().to_string_lossy().into_owned()).into(),
+            _ => e.into(),
+        })
+    }
+}
+
+/// Runs commands on `destination` (`user@host`, or a host from
+/// `~/.ssh/config`) through `ssh`.
+pub struct Ssh {
+    destination: String,
+}
+
+impl Ssh {
+    pub fn new(destination: String) -> Ssh {
+        Ssh { destination }
+    }
+
+    // Never prompts: a password or host key question would land on the
+    // alternate screen. The remote shell splits the command again, so every
+    // word is quoted.
+    fn command(&self, cmd: &Command) -> Command {
+        let words: Vec<String> = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|word| shell_quote(&word.to_string_lossy()))
+            .collect();
+        let mut ssh = Command::new("ssh");
+        ssh.args(["-n", "-o", "BatchMode=yes", "-o", "ConnectTimeout=10", "--", &self.destination])
+            .arg(words.join(" "));
+        ssh
+    }
+}
+
+impl CommandRunner for Ssh {
+    fn run(&self, cmd: &mut Command, cancel: &CancelToken, on_lines: &mut dyn FnMut(Vec<String>)) -> Result<Output> {
+        let output = Local.run(&mut self.command(cmd), cancel, on_lines)?;
+        match output.status.code() {
+            // ssh's own failures; the remote command's status is passed through
+            Some(255) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = stderr.lines().rfind(|line| !line.trim().is_empty()).unwrap_or("ssh failed");
+                Err(SnapperError::Unreachable { destination: self.destination.clone(), reason: reason.trim().to_string() }.into())
+            }
+            // The remote shell couldn't find the program
+            Some(127) => Err(SnapperError::NotFound(format!("{} on {}", cmd.get_program().to_string_lossy(), self.destination)).into()),
+            _ => Ok(output),
+        }
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+}
+
+/// `word` as sh reads it back literally: plain words pass through, anything
+/// else is single-quoted, so quotes, `$(...)`, globs and `;` stay text.
+pub fn shell_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c)) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+static RUNNER: OnceLock<Arc<dyn CommandRunner>> = OnceLock::new();
+
+#[cfg(test)]
+thread_local! {
+    // Takes over from `RUNNER` on one test's thread, so tests can run side
+    // by side with different canned replies
+    static TEST_RUNNER: std::cell::RefCell<Option<Arc<dyn CommandRunner>>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Sends every command through `runner` instead of running it here. Only
+/// the first call has an effect; it belongs before the first command.
+pub fn set_runner(runner: Box<dyn CommandRunner>) {
+    let _ = RUNNER.set(Arc::from(runner));
+}
+
+fn runner() -> Arc<dyn CommandRunner> {
+    #[cfg(test)]
+    if let Some(runner) = TEST_RUNNER.with(|runner| runner.borrow().clone()) {
+        return runner;
+    }
+    RUNNER.get().cloned().unwrap_or_else(|| Arc::new(Local))
+}
+
+// A file on the machine being managed; `None` when it can't be read
+fn read_target_file(path: &Path, cancel: &CancelToken) -> Option<String> {
+    if !runner().is_remote() {
+        return fs::read_to_string(path).ok();
+    }
+    let output = execute(Command::new("cat").arg("--").arg(path), cancel).ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// `run_streaming` through the runner, with a missing executable reported as
+// `SnapperError::NotFound`.
+fn execute(cmd: &mut Command, cancel: &CancelToken) -> Result<Output> {
+    execute_streaming(cmd, cancel, |_| {})
+}
+
+// Also retries, with exponential backoff, while snapper reports that another
+// instance holds its lock.
+fn execute_streaming(cmd: &mut Command, cancel: &CancelToken, mut on_lines: impl FnMut(Vec<String>)) -> Result<Output> {
+    let retries = LOCK_RETRIES.load(Ordering::Relaxed);
+    let mut attempt = 0;
+    loop {
+        let output = runner().run(cmd, cancel, &mut on_lines)?;
+        if !should_retry(&output, attempt, retries) {
+            return Ok(output);
+        }
+        backoff(retry_delay(attempt), cancel)?;
+        attempt += 1;
+    }
+}
+
+static LOCK_RETRIES: AtomicU32 = AtomicU32::new(3);
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Sets how many times a command that lost a lock race is retried.
+pub fn set_lock_retries(retries: u32) {
+    LOCK_RETRIES.store(retries, Ordering::Relaxed);
+}
+
+// Only lock contention is worth retrying; any other failure would just fail
+// the same way again.
+fn should_retry(output: &Output, attempt: u32, retries: u32) -> bool {
+    attempt < retries && !output.status.success() && is_lock_contention(&output.stderr)
+}
+
+fn is_lock_contention(stderr: &[u8]) -> bool {
+    String::from_utf8_lossy(stderr).to_lowercase().contains("already locked")
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY * 2u32.saturating_pow(attempt.min(10))
+}
+
+// Sleeps for `delay`, waking early if the operation is cancelled.
+fn backoff(delay: Duration, cancel: &CancelToken) -> Result<()> {
+    let deadline = Instant::now() + delay;
+    while Instant::now() < deadline {
+        if cancel.is_cancelled() {
+            return Err(cancelled_error().into());
+        }
+        thread::sleep(POLL_INTERVAL.min(deadline - Instant::now()));
+    }
+    Ok(())
+}
+
+// Every privileged command also lands in the session history
+fn execute_privileged(privilege: Privilege, cmd: &mut Command, cancel: &CancelToken) -> Result<Output> {
+    let outcome = execute(cmd, cancel).and_then(|output| check_auth(privilege, output));
+    history::record(cmd, &outcome);
+    outcome
+}
+
+fn check_auth(privilege: Privilege, output: Output) -> Result<Output> {
+    if privilege.auth_cancelled(&output.status) {
+        return Err(SnapperError::AuthCancelled.into());
+    }
+    Ok(output)
+}
+
+fn read_pipe(pipe: Option<impl Read>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buf);
+    }
+    buf
+}
+
+// `read_pipe` that also sends a copy of each chunk as it is read.
+fn read_pipe_chunks(pipe: Option<impl Read>, tx: Sender<Vec<u8>>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let Some(mut pipe) = pipe else {
+        return buf;
+    };
+    let mut chunk = [0; 8192];
+    loop {
+        match pipe.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => {
+                buf.extend_from_slice(&chunk[..n]);
+                let _ = tx.send(chunk[..n].to_vec());
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    }
+    buf
+}
+
+/// Lists the snapshots of every config; an active config only filters the
+/// table, since plain `snapper list` would show `root` alone.
+pub fn list_snapshots(cancel: &CancelToken) -> Result<Vec<Snapshot>> {
+    let output = execute(
+        Command::new("snapper").args([
             "--jsonout",
             "list",
+            "--all-configs",
             "--columns",
             "config,subvolume,number,type,pre-number,post-number,date,user,cleanup,description,userdata,used-space,default,active",
-        ])
-        .output()
-        .context("Failed to execute snapper command")?;
+        ]),
+        cancel,
+    )
+    .context("Failed to execute snapper command")?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
@@ -50,52 +517,183 @@ pub fn list_snapshots() -> Result<Vec<Snapshot>> {
     }
 
     let output_str = String::from_utf8(output.stdout)?;
-    let payload: HashMap<String, Vec<Snapshot>> = serde_json::from_str(&output_str)
-        .context("Failed to parse snapper JSON output")?;
+    parse_snapshot_list(&output_str)
+}
+
+// An empty object, or a config listed as `null`, just means no snapshots;
+// anything else that doesn't parse is an error, so it isn't mistaken for an
+// empty list.
+fn parse_snapshot_list(text: &str) -> Result<Vec<Snapshot>> {
+    if text.trim().is_empty() {
+        anyhow::bail!("snapper printed no JSON output");
+    }
+    let payload: HashMap<String, Option<Vec<Snapshot>>> = serde_json::from_str(text)
+        .with_context(|| format!("Failed to parse snapper JSON output ({} bytes)", text.len()))?;
 
     let mut snapshots = Vec::new();
-    for (config_name, mut entries) in payload {
-        for entry in &mut entries {
+    for (config_name, entries) in payload {
+        for mut entry in entries.unwrap_or_default() {
             entry.config = config_name.clone();
+            entry.timestamp = parse_date(&entry.date);
+            snapshots.push(entry);
         }
-        snapshots.append(&mut entries);
     }
 
     Ok(snapshots)
 }
 
-pub fn delete_snapshot(number: u32) -> Result<()> {
-    let status = Command::new("sudo")
-        .args(&["snapper", "delete", &number.to_string()])
-        .status()
+/// Seconds since the epoch of a snapper date. Takes the classic local
+/// `YYYY-MM-DD HH:MM:SS` as well as ISO 8601 (`2024-01-15T10:00:00`,
+/// optionally with fractional seconds and a `Z` or `±HH:MM` offset).
+pub fn parse_date(date: &str) -> Option<i64> {
+    let date = date.trim();
+    let (day, time) = date.split_once([' ', 'T'])?;
+    // The zone follows the seconds; without one the time is local
+    let (time, offset) = match time.find(['Z', 'z', '+', '-']) {
+        Some(at) => (&time[..at], Some(parse_utc_offset(&time[at..])?)),
+        None => (time, None),
+    };
+    let time = time.split_once('.').map_or(time, |(whole, fraction)| {
+        if fraction.bytes().all(|b| b.is_ascii_digit()) { whole } else { time }
+    });
+
+    let fields = |text: &str, sep: char| -> Option<[i32; 3]> {
+        let parts: Vec<i32> = text.split(sep).map(|part| part.parse().ok()).collect::<Option<_>>()?;
+        parts.try_into().ok()
+    };
+    let [year, month, mday] = fields(day, '-')?;
+    let [hour, min, sec] = fields(time, ':')?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&mday) || hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+
+    if let Some(offset) = offset {
+        let secs = days_from_civil(year, month, mday) * 86400 + (hour * 3600 + min * 60 + sec) as i64;
+        return Some(secs - offset);
+    }
+    // SAFETY: `tm` is plain old data; all-zero is a valid value to start from
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    tm.tm_year = year - 1900;
+    tm.tm_mon = month - 1;
+    tm.tm_mday = mday;
+    tm.tm_hour = hour;
+    tm.tm_min = min;
+    tm.tm_sec = sec;
+    tm.tm_isdst = -1; // Let mktime work out daylight saving
+    // SAFETY: `tm` is a valid, exclusively borrowed struct for the call
+    let secs = unsafe { libc::mktime(&mut tm) };
+    (secs != -1).then_some(secs as i64)
+}
+
+// `Z`, `+02:00`, `-0530` or `+02` as seconds east of UTC
+fn parse_utc_offset(zone: &str) -> Option<i64> {
+    let sign = match zone.chars().next()? {
+        'Z' | 'z' if zone.len() == 1 => return Some(0),
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = zone[1..].chars().filter(|&c| c != ':').collect();
+    if !matches!(digits.len(), 2 | 4) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits.get(2..).filter(|m| !m.is_empty()).map_or(Some(0), |m| m.parse().ok())?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+// Days from 1970-01-01 to a proleptic Gregorian date (Howard Hinnant's
+// `days_from_civil`)
+fn days_from_civil(year: i32, month: i32, day: i32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year } as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// `secs` since the epoch as a local `YYYY-MM-DD HH:MM:SS`.
+pub fn format_local_time(secs: i64) -> String {
+    let secs = secs as libc::time_t;
+    // SAFETY: `tm` is plain old data, and both pointers are valid for the call
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return String::from("????-??-?? ??:??:??");
+    }
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
+
+pub fn delete_snapshot(config: &str, number: u32, privilege: Privilege, cancel: &CancelToken) -> Result<()> {
+    let output = execute_privileged(privilege, privilege.snapper().args(["-c", config, "delete", &number.to_string()]), cancel)
         .context("Failed to execute snapper delete")?;
 
-    if !status.success() {
-        anyhow::bail!("Failed to delete snapshot {}", number);
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to delete snapshot {}: {}", number, error_msg.trim());
     }
     Ok(())
 }
 
-pub fn rollback_snapshot(number: u32) -> Result<()> {
-    let status = Command::new("sudo")
-        .args(&["snapper", "rollback", &number.to_string()])
-        .status()
+pub fn rollback_snapshot(config: &str, number: u32, privilege: Privilege, cancel: &CancelToken) -> Result<()> {
+    let output = execute_privileged(privilege, privilege.snapper().args(["-c", config, "rollback", &number.to_string()]), cancel)
         .context("Failed to execute snapper rollback")?;
 
-    if !status.success() {
-        anyhow::bail!("Failed to rollback to snapshot {}", number);
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to rollback to snapshot {}: {}", number, error_msg.trim());
     }
     Ok(())
 }
 
-pub fn get_snapshot_status(snap: &Snapshot) -> Result<String> {
-    let start = snap.pre_number.unwrap_or_else(|| snap.number.saturating_sub(1));
-    let range = format!("{}..{}", start, snap.number);
-    
-    let output = Command::new("sudo")
-        .args(&["snapper", "status", &range])
-        .output()
-        .context("Failed to execute snapper status")?;
+/// The pair of snapshots a `snapper status` listing compares.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusRange {
+    pub config: String,
+    pub pre: u32,
+    pub post: u32,
+}
+
+/// What `snapper status` should compare to show `snap`'s changes: a post
+/// against its pre, a pre against its post, and anything else against the
+/// snapshot before it. `None` when there is nothing sensible to compare,
+/// e.g. for #0 (the live system), #1, or a pre whose post never happened.
+pub fn status_range(snap: &Snapshot) -> Option<StatusRange> {
+    let (pre, post) = match (snap.pre_number, snap.post_number) {
+        (Some(pre), _) => (pre, snap.number),
+        (None, Some(post)) => (snap.number, post),
+        _ if snap.snapshot_type == "pre" || snap.number <= 1 => return None,
+        _ => (snap.number - 1, snap.number),
+    };
+    Some(StatusRange { config: snap.config.clone(), pre, post })
+}
+
+/// Runs `snapper status from..to` for two snapshots of `config`, passing
+/// output lines to `on_lines` as they arrive; large ranges can list tens of
+/// thousands of files.
+pub fn get_status_range(
+    config: &str,
+    from: u32,
+    to: u32,
+    privilege: Privilege,
+    cancel: &CancelToken,
+    on_lines: impl FnMut(Vec<String>),
+) -> Result<String> {
+    let range = format!("{}..{}", from, to);
+    let mut cmd = privilege.snapper();
+    cmd.args(["-c", config, "status", &range]);
+    let outcome = execute_streaming(&mut cmd, cancel, on_lines).and_then(|output| check_auth(privilege, output));
+    history::record(&cmd, &outcome);
+    let output = outcome.context("Failed to execute snapper status")?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
@@ -105,21 +703,781 @@ pub fn get_snapshot_status(snap: &Snapshot) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).into_owned())
 }
 
-pub fn create_snapshot(description: &str) -> Result<()> {
-    let status = Command::new("sudo")
-        .args(&["snapper", "create", "--description", description])
-        .status()
+/// Splits a `snapper status` line such as `c..... /etc/fstab` into its
+/// change code and path.
+pub fn parse_status_line(line: &str) -> Option<(&str, &str)> {
+    let (code, path) = line.split_once(' ')?;
+    let path = path.trim_start();
+    if code.is_empty() || !path.starts_with('/') {
+        return None;
+    }
+    Some((code, path))
+}
+
+pub fn get_snapshot_diff(config: &str, pre: u32, post: u32, path: &str, privilege: Privilege, cancel: &CancelToken) -> Result<String> {
+    let range = format!("{}..{}", pre, post);
+
+    let output = execute_privileged(
+        privilege,
+        privilege.snapper().args(["-c", config, "diff", &range, path]),
+        cancel,
+    )
+    .context("Failed to execute snapper diff")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Snapper diff failed: {}", error_msg);
+    }
+
+    if is_binary_diff(&output.stdout) {
+        return Ok(format!("Binary file {} differs", path));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// diff reports binary files with a one-line notice; raw NULs or invalid
+// UTF-8 mean the content itself leaked through and shouldn't be rendered.
+fn is_binary_diff(output: &[u8]) -> bool {
+    match std::str::from_utf8(output) {
+        Ok(text) => {
+            text.contains('\0')
+                || text.lines().any(|l| l.starts_with("Binary files ") && l.ends_with(" differ"))
+        }
+        Err(_) => true,
+    }
+}
+
+pub fn undo_change(config: &str, pre: u32, post: u32, paths: &[String], privilege: Privilege, cancel: &CancelToken) -> Result<()> {
+    let range = format!("{}..{}", pre, post);
+
+    let output = execute_privileged(
+        privilege,
+        privilege
+            .snapper()
+            .args(["-c", config, "undochange", &range])
+            .args(paths),
+        cancel,
+    )
+    .context("Failed to execute snapper undochange")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Snapper undochange failed: {}", error_msg);
+    }
+    Ok(())
+}
+
+/// What `snapper create` is asked to make.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NewSnapshot {
+    pub description: String,
+    /// `single`, `pre` or `post`.
+    pub snapshot_type: String,
+    /// The `pre` snapshot a `post` one closes.
+    pub pre_number: Option<u32>,
+    pub cleanup: Option<String>,
+    pub userdata: Option<HashMap<String, String>>,
+    /// The config to create it in; snapper's default (`root`) when unset.
+    pub config: Option<String>,
+}
+
+/// Runs `snapper create` and returns the new snapshot's number, or `None`
+/// if snapper printed something else.
+pub fn create_snapshot(snapshot: &NewSnapshot, privilege: Privilege, cancel: &CancelToken) -> Result<Option<u32>> {
+    let output = execute_privileged(privilege, privilege.snapper().args(create_args(snapshot)), cancel)
         .context("Failed to execute snapper create")?;
 
-    if !status.success() {
-        anyhow::bail!("Failed to create snapshot");
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to create snapshot: {}", error_msg.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+}
+
+/// Where snapper exposes snapshot `number` of the config rooted at `subvolume`.
+pub fn snapshot_path(subvolume: &str, number: u32) -> PathBuf {
+    Path::new(subvolume).join(".snapshots").join(number.to_string()).join("snapshot")
+}
+
+/// Mounts a snapshot with `snapper mount`, which keeps read-only snapshots
+/// read-only, and returns the directory it is reachable at.
+pub fn mount_snapshot(snap: &Snapshot, privilege: Privilege, cancel: &CancelToken) -> Result<PathBuf> {
+    let number = snap.number.to_string();
+    let output = execute_privileged(
+        privilege,
+        privilege.snapper().args(["-c", &snap.config, "mount", &number]),
+        cancel,
+    )
+    .context("Failed to execute snapper mount")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Snapper mount failed: {}", error_msg.trim());
+    }
+    Ok(snapshot_path(&snap.subvolume, snap.number))
+}
+
+pub fn umount_snapshot(config: &str, number: u32, privilege: Privilege, cancel: &CancelToken) -> Result<()> {
+    let number = number.to_string();
+    let output = execute_privileged(privilege, privilege.snapper().args(["-c", config, "umount", &number]), cancel)
+        .context("Failed to execute snapper umount")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Snapper umount failed: {}", error_msg.trim());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Lists `dir` with directories first. Goes through the privilege helper
+/// since snapshot trees are usually only readable by root.
+pub fn list_directory(dir: &Path, privilege: Privilege, cancel: &CancelToken) -> Result<Vec<DirEntry>> {
+    let output = execute_privileged(privilege, privilege.command("ls").arg("-1Ap").arg("--").arg(dir), cancel)
+        .context("Failed to execute ls")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Cannot list {}: {}", dir.display(), error_msg.trim());
+    }
+    Ok(parse_ls(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// `ls -1Ap` prints one name per line with a `/` after directories.
+fn parse_ls(text: &str) -> Vec<DirEntry> {
+    let mut entries: Vec<DirEntry> = text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.strip_suffix('/') {
+            Some(name) => DirEntry { name: name.to_string(), is_dir: true },
+            None => DirEntry { name: line.to_string(), is_dir: false },
+        })
+        .collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+const FILE_PREVIEW_BYTES: &str = "65536";
+
+/// The start of a file for previewing, or a notice for binary files.
+pub fn read_file_head(path: &Path, privilege: Privilege, cancel: &CancelToken) -> Result<String> {
+    let output = execute_privileged(
+        privilege,
+        privilege.command("head").args(["-c", FILE_PREVIEW_BYTES, "--"]).arg(path),
+        cancel,
+    )
+    .context("Failed to execute head")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Cannot read {}: {}", path.display(), error_msg.trim());
+    }
+    Ok(preview_text(&output.stdout))
+}
+
+fn preview_text(bytes: &[u8]) -> String {
+    // The byte limit may split the last character; that alone isn't binary
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+        Err(_) => return String::from("(binary file)"),
+    };
+    if text.contains('\0') {
+        String::from("(binary file)")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Algorithms accepted by `snapper cleanup`.
+pub const CLEANUP_ALGORITHMS: [&str; 3] = ["number", "timeline", "empty-pre-post"];
+
+pub fn run_cleanup(config: &str, algorithm: &str, privilege: Privilege, cancel: &CancelToken) -> Result<()> {
+    let output = execute_privileged(
+        privilege,
+        privilege.snapper().args(["-c", config, "cleanup", algorithm]),
+        cancel,
+    )
+    .context("Failed to execute snapper cleanup")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Snapper cleanup failed: {}", error_msg.trim());
+    }
+    Ok(())
+}
+
+/// `snapper setup-quota`: enables btrfs quota groups for `config`, which
+/// snapper needs to report used space.
+pub fn setup_quota(config: &str, privilege: Privilege, cancel: &CancelToken) -> Result<()> {
+    let output = execute_privileged(privilege, privilege.snapper().args(["-c", config, "setup-quota"]), cancel)
+        .context("Failed to execute snapper setup-quota")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Snapper setup-quota failed: {}", error_msg.trim());
+    }
+    Ok(())
+}
+
+/// Changes `snapper modify` should apply; `None` fields are left alone.
+pub fn modify_snapshot(
+    config: &str,
+    number: u32,
+    description: Option<&str>,
+    cleanup: Option<&str>,
+    userdata: Option<&HashMap<String, String>>,
+    privilege: Privilege,
+    cancel: &CancelToken,
+) -> Result<()> {
+    let args = modify_args(config, number, description, cleanup, userdata);
+    let output = execute_privileged(privilege, privilege.snapper().args(&args), cancel)
+        .context("Failed to execute snapper modify")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Snapper modify failed: {}", error_msg);
     }
     Ok(())
 }
 
+/// Reads the `KEY="value"` parameters of a snapper config, such as
+/// `NUMBER_LIMIT` and `TIMELINE_LIMIT_DAILY`.
+pub fn get_config(name: &str, privilege: Privilege, cancel: &CancelToken) -> Result<HashMap<String, String>> {
+    let output = execute_privileged(
+        privilege,
+        privilege.snapper().args(["--jsonout", "-c", name, "get-config"]),
+        cancel,
+    )
+    .context("Failed to execute snapper get-config")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Snapper get-config failed: {}", error_msg.trim());
+    }
+
+    parse_config(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_config(text: &str) -> Result<HashMap<String, String>> {
+    serde_json::from_str(text).context("Failed to parse snapper get-config output")
+}
+
+/// The filesystem a snapper config manages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigFs {
+    /// snapper's `FSTYPE`: `btrfs`, `ext4`, `lvm(xfs)` and so on.
+    pub fstype: String,
+}
+
+impl ConfigFs {
+    pub fn is_btrfs(&self) -> bool {
+        self.fstype == "btrfs"
+    }
+
+    /// `snapper rollback` only works by switching btrfs default subvolumes.
+    pub fn supports_rollback(&self) -> bool {
+        self.is_btrfs()
+    }
+}
+
+/// Reads `FSTYPE` from `/etc/snapper/configs/<name>`, asking
+/// `snapper get-config` when the file can't be read without privileges.
+pub fn get_config_fs(name: &str, privilege: Privilege, cancel: &CancelToken) -> Result<ConfigFs> {
+    let from_file = read_target_file(&Path::new("/etc/snapper/configs").join(name), cancel)
+        .and_then(|text| config_file_value(&text, "FSTYPE"));
+    let fstype = match from_file {
+        Some(fstype) => fstype,
+        None => get_config(name, privilege, cancel)?
+            .remove("FSTYPE")
+            .context("snapper get-config printed no FSTYPE")?,
+    };
+    Ok(ConfigFs { fstype })
+}
+
+// Config files are shell variable assignments: `KEY="value"`
+fn config_file_value(text: &str, key: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let (name, value) = line.trim().split_once('=')?;
+        (name.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn create_args(snapshot: &NewSnapshot) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(config) = &snapshot.config {
+        args.extend([String::from("-c"), config.clone()]);
+    }
+    args.extend([String::from("create"), String::from("--print-number"), String::from("--type"), snapshot.snapshot_type.clone()]);
+    if let Some(pre) = snapshot.pre_number {
+        args.extend([String::from("--pre-number"), pre.to_string()]);
+    }
+    args.extend([String::from("--description"), one_line(&snapshot.description)]);
+    if let Some(cleanup) = &snapshot.cleanup {
+        args.extend([String::from("--cleanup-algorithm"), cleanup.clone()]);
+    }
+    if let Some(userdata) = &snapshot.userdata {
+        args.extend([String::from("--userdata"), userdata_arg(userdata)]);
+    }
+    args
+}
+
+// Descriptions go into snapper's one-line list columns and onto the
+// terminal, so control characters (newlines, escape sequences) become spaces
+fn one_line(text: &str) -> String {
+    text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
+
+// `--userdata` value; sorted so the command line is predictable
+fn userdata_arg(userdata: &HashMap<String, String>) -> String {
+    let mut pairs: Vec<String> = userdata.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    pairs.sort();
+    pairs.join(",")
+}
+
+fn modify_args(
+    config: &str,
+    number: u32,
+    description: Option<&str>,
+    cleanup: Option<&str>,
+    userdata: Option<&HashMap<String, String>>,
+) -> Vec<String> {
+    let mut args = vec![String::from("-c"), config.to_string(), String::from("modify")];
+    if let Some(description) = description {
+        args.extend([String::from("--description"), one_line(description)]);
+    }
+    if let Some(cleanup) = cleanup {
+        args.extend([String::from("--cleanup-algorithm"), cleanup.to_string()]);
+    }
+    if let Some(userdata) = userdata {
+        args.extend([String::from("--userdata"), userdata_arg(userdata)]);
+    }
+    args.push(number.to_string());
+    args
+}
+
+/// Userdata as the `key=value, key=value` text shown in the edit popup.
+pub fn format_userdata(userdata: Option<&HashMap<String, String>>) -> String {
+    let mut pairs: Vec<String> = userdata
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect();
+    pairs.sort();
+    pairs.join(", ")
+}
+
+pub fn parse_userdata(text: &str) -> Result<HashMap<String, String>> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => anyhow::bail!("Userdata entry '{}' is not key=value", pair),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// `.csv` exports CSV, anything else pretty-printed JSON.
+    pub fn from_path(path: &Path) -> ExportFormat {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Json,
+        }
+    }
+}
+
+pub fn export_snapshots(snaps: &[Snapshot], path: &Path, format: ExportFormat) -> Result<()> {
+    let contents = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(snaps)? + "\n",
+        ExportFormat::Csv => snapshots_to_csv(snaps),
+    };
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The lines that open a saved status or diff report: what was compared,
+/// the file for a diff, and when the report was written.
+pub fn report_header(range: &StatusRange, file: Option<&str>, time: &str) -> String {
+    let mut header = format!("# snapper status {}..{} (config {})\n", range.pre, range.post, range.config);
+    if let Some(file) = file {
+        header.push_str(&format!("# diff of {}\n", file));
+    }
+    header.push_str(&format!("# saved {}\n\n", time));
+    header
+}
+
+/// Writes a report, replacing any file already at `path`.
+pub fn save_report(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+const EXPORT_HEADER: [&str; 12] = [
+    "config", "number", "type", "pre-number", "post-number", "date", "user", "cleanup", "description",
+    "used-space", "default", "active",
+];
+
+fn export_row(snap: &Snapshot) -> [String; 12] {
+    let opt = |n: Option<u32>| n.map(|n| n.to_string()).unwrap_or_default();
+    [
+        snap.config.clone(),
+        snap.number.to_string(),
+        snap.snapshot_type.clone(),
+        opt(snap.pre_number),
+        opt(snap.post_number),
+        snap.date.clone(),
+        snap.user.clone(),
+        snap.cleanup.clone().unwrap_or_default(),
+        snap.description.clone(),
+        snap.used_space.map(|n| n.to_string()).unwrap_or_default(),
+        snap.default.to_string(),
+        snap.active.to_string(),
+    ]
+}
+
+fn snapshots_to_csv(snaps: &[Snapshot]) -> String {
+    let mut out = EXPORT_HEADER.join(",") + "\n";
+    for snap in snaps {
+        let row: Vec<String> = export_row(snap).iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Plain-text summary of one snapshot, one `Field: value` per line.
+pub fn snapshot_summary(snap: &Snapshot) -> String {
+    let mut out = format!(
+        "Config: {}\nNumber: {}\nType: {}\nDate: {}\nUser: {}\nCleanup: {}\nDescription: {}\n",
+        snap.config,
+        snap.number,
+        snap.snapshot_type,
+        snap.date,
+        snap.user,
+        snap.cleanup.as_deref().unwrap_or(""),
+        snap.description,
+    );
+    if snap.userdata.as_ref().is_some_and(|data| !data.is_empty()) {
+        out.push_str(&format!("Userdata: {}\n", format_userdata(snap.userdata.as_ref())));
+    }
+    out
+}
+
+/// Tab-separated table with the same columns as the CSV export, for pasting
+/// into spreadsheets and tickets. Tabs and newlines in fields become spaces.
+pub fn snapshots_to_tsv(snaps: &[&Snapshot]) -> String {
+    let mut out = EXPORT_HEADER.join("\t") + "\n";
+    for snap in snaps {
+        let row: Vec<String> = export_row(snap).iter().map(|f| f.replace(['\t', '\n', '\r'], " ")).collect();
+        out.push_str(&row.join("\t"));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Returns `(used, total)` bytes for the btrfs filesystem holding `subvolume`.
+pub fn get_btrfs_usage(subvolume: &str, privilege: Privilege, cancel: &CancelToken) -> Result<(u64, u64)> {
+    let output = execute_privileged(
+        privilege,
+        privilege.command("btrfs").args(["filesystem", "usage", "-b", subvolume]),
+        cancel,
+    )
+    .context("Failed to execute btrfs filesystem usage")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("btrfs filesystem usage failed: {}", error_msg);
+    }
+
+    parse_btrfs_usage(&String::from_utf8_lossy(&output.stdout))
+        .context("Unexpected btrfs filesystem usage output")
+}
+
+// Reads the `Device size:` and `Used:` lines of the `Overall:` section
+// printed by `btrfs filesystem usage -b`.
+fn parse_btrfs_usage(text: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        text.lines()
+            .find_map(|line| line.trim().strip_prefix(name))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    Some((field("Used:")?, field("Device size:")?))
+}
+
+/// The installed snapper's version, e.g. `0.10.4`.
+pub fn get_snapper_version(cancel: &CancelToken) -> Result<String> {
+    let output = execute(Command::new("snapper").arg("--version"), cancel).context("Failed to execute snapper --version")?;
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("snapper --version failed: {}", error_msg.trim());
+    }
+    parse_snapper_version(&String::from_utf8_lossy(&output.stdout)).context("Unexpected snapper --version output")
+}
+
+// The first line reads `snapper 0.10.4`; libsnapper and build flags follow
+fn parse_snapper_version(text: &str) -> Option<String> {
+    let version = text.lines().next()?.trim().strip_prefix("snapper")?.trim();
+    version.starts_with(|c: char| c.is_ascii_digit()).then(|| version.to_string())
+}
+
+// `snapper list` learnt `--jsonout`, which listing depends on, in 0.8.10
+const JSONOUT_SINCE: [u32; 3] = [0, 8, 10];
+
+/// Whether snapper `version` can print the JSON the snapshot list is read
+/// from. Unparsable versions are given the benefit of the doubt.
+pub fn supports_jsonout(version: &str) -> bool {
+    let parts: Option<Vec<u32>> = version.split('.').map(|part| part.parse().ok()).collect();
+    match parts {
+        Some(mut parts) => {
+            parts.resize(3, 0);
+            parts.as_slice() >= JSONOUT_SINCE.as_slice()
+        }
+        None => true,
+    }
+}
+
+/// Which snapshots the root filesystem is running from and will boot into.
+/// `None` means a plain subvolume rather than one of snapper's snapshots.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BootLineage {
+    pub booted: Option<u32>,
+    pub default: Option<u32>,
+}
+
+/// Returns the path of the default subvolume of the filesystem mounted at
+/// `/`, i.e. what it boots into next, as printed by `btrfs subvolume get-default`.
+pub fn get_default_subvolume(privilege: Privilege, cancel: &CancelToken) -> Result<String> {
+    let output = execute_privileged(
+        privilege,
+        privilege.command("btrfs").args(["subvolume", "get-default", "/"]),
+        cancel,
+    )
+    .context("Failed to execute btrfs subvolume get-default")?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("btrfs subvolume get-default failed: {}", error_msg.trim());
+    }
+
+    parse_default_subvolume(&String::from_utf8_lossy(&output.stdout))
+        .context("Unexpected btrfs subvolume get-default output")
+}
+
+// `ID 267 gen 2880 top level 256 path @/.snapshots/1/snapshot`, or
+// `ID 5 (FS_TREE)` when the top level is the default
+fn parse_default_subvolume(text: &str) -> Option<String> {
+    let line = text.lines().next()?.trim();
+    if line.starts_with("ID 5 ") {
+        return Some(String::from("/"));
+    }
+    line.split_once(" path ").map(|(_, path)| path.to_string())
+}
+
+/// Filesystem type and mounted subvolume of `/`, from `/proc/self/mountinfo`.
+pub fn root_mount(cancel: &CancelToken) -> Option<(String, String)> {
+    parse_mountinfo(&read_target_file(Path::new("/proc/self/mountinfo"), cancel)?)
+}
+
+// Fields are `id parent dev root mountpoint options [tags] - fstype source
+// super-options`; the last mount on `/` is the visible one
+fn parse_mountinfo(text: &str) -> Option<(String, String)> {
+    text.lines()
+        .rev()
+        .find_map(|line| {
+            let (mount, fs) = line.split_once(" - ")?;
+            let mut fields = mount.split_whitespace().skip(3);
+            let (root, mount_point) = (fields.next()?, fields.next()?);
+            let fstype = fs.split_whitespace().next()?;
+            (mount_point == "/").then(|| (fstype.to_string(), root.to_string()))
+        })
+}
+
+/// The snapshot number in a subvolume path such as `@/.snapshots/42/snapshot`.
+pub fn snapshot_number_of(path: &str) -> Option<u32> {
+    let mut parts = path.trim_end_matches('/').rsplit('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("snapshot"), Some(number), Some(".snapshots")) => number.parse().ok(),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::VecDeque;
+    use std::os::unix::process::ExitStatusExt;
+
+    // Answers commands with canned `(exit code, stdout, stderr)` replies, in
+    // order, and remembers the argv of each
+    #[derive(Default)]
+    struct MockRunner {
+        replies: Mutex<VecDeque<(i32, String, String)>>,
+        argv: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CommandRunner for MockRunner {
+        fn run(&self, cmd: &mut Command, _cancel: &CancelToken, on_lines: &mut dyn FnMut(Vec<String>)) -> Result<Output> {
+            let argv = std::iter::once(cmd.get_program()).chain(cmd.get_args());
+            self.argv.lock().unwrap().push(argv.map(|arg| arg.to_string_lossy().into_owned()).collect());
+            let (code, stdout, stderr) = self.replies.lock().unwrap().pop_front().unwrap_or_default();
+            if !stdout.is_empty() {
+                on_lines(stdout.lines().map(String::from).collect());
+            }
+            Ok(Output { status: ExitStatus::from_raw(code << 8), stdout: stdout.into_bytes(), stderr: stderr.into_bytes() })
+        }
+    }
+
+    // Runs `f` with the data layer talking to a `MockRunner` and returns its
+    // result along with every argv it ran
+    fn mocked<T>(replies: &[(i32, &str, &str)], f: impl FnOnce() -> T) -> (T, Vec<Vec<String>>) {
+        let mock = Arc::new(MockRunner::default());
+        mock.replies.lock().unwrap().extend(replies.iter().map(|&(code, out, err)| (code, out.to_string(), err.to_string())));
+        TEST_RUNNER.with(|runner| *runner.borrow_mut() = Some(mock.clone()));
+        let result = f();
+        TEST_RUNNER.with(|runner| *runner.borrow_mut() = None);
+        let argv = std::mem::take(&mut *mock.argv.lock().unwrap());
+        (result, argv)
+    }
+
+    #[test]
+    fn test_list_snapshots_through_runner() {
+        let json = r#"{"root": [{"number": 0, "type": "single", "date": "", "description": "current"},
+                                {"number": 5, "type": "pre", "date": "2024-01-15 10:00:00", "description": "zypper"}]}"#;
+        let (snapshots, argv) = mocked(&[(0, json, "")], || list_snapshots(&CancelToken::default()));
+        let mut numbers: Vec<u32> = snapshots.unwrap().iter().map(|s| s.number).collect();
+        numbers.sort();
+        assert_eq!(numbers, [0, 5]);
+        assert_eq!(argv.len(), 1);
+        assert_eq!(argv[0][..5], ["snapper", "--jsonout", "list", "--all-configs", "--columns"]);
+        assert!(argv[0][5].split(',').any(|column| column == "used-space"));
+
+        let (result, _) = mocked(&[(1, "", "Unknown config.\n")], || list_snapshots(&CancelToken::default()));
+        assert_eq!(result.unwrap_err().to_string(), "Snapper failed: Unknown config.\n");
+        let (result, _) = mocked(&[(0, "not json", "")], || list_snapshots(&CancelToken::default()));
+        assert!(result.unwrap_err().to_string().contains("Failed to parse"));
+    }
+
+    #[test]
+    fn test_privileged_commands_build_exact_argv() {
+        let cancel = CancelToken::default();
+        let userdata = HashMap::from([(String::from("important"), String::from("yes"))]);
+        let new = NewSnapshot {
+            description: String::from("before upgrade"),
+            snapshot_type: String::from("pre"),
+            cleanup: Some(String::from("number")),
+            ..Default::default()
+        };
+        let (results, argv) = mocked(&[], || {
+            [
+                delete_snapshot("home", 5, Privilege::Pkexec, &cancel),
+                rollback_snapshot("root", 7, Privilege::Direct, &cancel),
+                create_snapshot(&new, Privilege::Doas, &cancel).map(|_| ()),
+                run_cleanup("home", "timeline", Privilege::Direct, &cancel),
+                modify_snapshot("root", 3, Some("kept"), None, Some(&userdata), Privilege::Direct, &cancel),
+                undo_change("root", 1, 2, &[String::from("/etc/fstab")], Privilege::Direct, &cancel),
+                umount_snapshot("root", 4, Privilege::Direct, &cancel),
+                setup_quota("home", Privilege::Sudo, &cancel),
+            ]
+        });
+        assert!(results.iter().all(Result::is_ok));
+        let expected: [&[&str]; 8] = [
+            &["pkexec", "snapper", "-c", "home", "delete", "5"],
+            &["snapper", "-c", "root", "rollback", "7"],
+            &["doas", "snapper", "create", "--print-number", "--type", "pre", "--description", "before upgrade", "--cleanup-algorithm", "number"],
+            &["snapper", "-c", "home", "cleanup", "timeline"],
+            &["snapper", "-c", "root", "modify", "--description", "kept", "--userdata", "important=yes", "3"],
+            &["snapper", "-c", "root", "undochange", "1..2", "/etc/fstab"],
+            &["snapper", "-c", "root", "umount", "4"],
+            &["sudo", "snapper", "-c", "home", "setup-quota"],
+        ];
+        assert_eq!(argv, expected.map(|args| args.iter().map(|a| a.to_string()).collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn test_runner_failures_and_outputs_are_interpreted() {
+        let cancel = CancelToken::default();
+
+        let (result, _) = mocked(&[(1, "", "Snapshot '5' not found.\n")], || delete_snapshot("root", 5, Privilege::Direct, &cancel));
+        assert_eq!(result.unwrap_err().to_string(), "Failed to delete snapshot 5: Snapshot '5' not found.");
+
+        let (result, _) = mocked(&[(126, "", "")], || delete_snapshot("root", 5, Privilege::Pkexec, &cancel));
+        assert_eq!(result.unwrap_err().root_cause().downcast_ref::<SnapperError>(), Some(&SnapperError::AuthCancelled));
+
+        // Lock contention is retried with the same command
+        let (result, argv) = mocked(&[(1, "", "Config is already locked.\n"), (0, "", "")], || {
+            rollback_snapshot("root", 7, Privilege::Direct, &cancel)
+        });
+        assert!(result.is_ok());
+        assert_eq!(argv.len(), 2);
+        assert_eq!(argv[0], argv[1]);
+
+        let (config, argv) = mocked(&[(0, r#"{"FSTYPE": "btrfs", "NUMBER_LIMIT": "50"}"#, "")], || {
+            get_config("root", Privilege::Direct, &cancel)
+        });
+        assert_eq!(config.unwrap().get("NUMBER_LIMIT").map(String::as_str), Some("50"));
+        assert_eq!(argv[0], ["snapper", "--jsonout", "-c", "root", "get-config"]);
+
+        let mut streamed = Vec::new();
+        let (status, argv) = mocked(&[(0, "c..... /etc/fstab\n+..... /etc/new.conf\n", "")], || {
+            get_status_range("root", 3, 4, Privilege::Direct, &cancel, |lines| streamed.extend(lines))
+        });
+        assert_eq!(status.unwrap().lines().count(), 2);
+        assert_eq!(streamed, ["c..... /etc/fstab", "+..... /etc/new.conf"]);
+        assert_eq!(argv[0], ["snapper", "-c", "root", "status", "3..4"]);
+
+        let (version, _) = mocked(&[(0, "snapper 0.10.6\nlibsnapper 7.4.1\n", "")], || get_snapper_version(&cancel));
+        assert_eq!(version.unwrap(), "0.10.6");
+    }
+
+    #[test]
+    fn test_in_path_needs_an_executable() {
+        let dir = export_path("bin");
+        fs::create_dir_all(&dir).unwrap();
+        let helper = dir.join("doas");
+        fs::write(&helper, "#!/bin/sh\n").unwrap();
+        let path = std::env::join_paths([Path::new("/nonexistent"), &dir]).unwrap();
+        assert!(!in_path("doas", &path), "not executable yet");
+
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&helper, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(in_path("doas", &path));
+        assert!(!in_path("sudo", &path));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_askpass_only_applies_to_sudo() {
+        let argv = |cmd: Command| {
+            std::iter::once(cmd.get_program()).chain(cmd.get_args()).map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>()
+        };
+        assert_eq!(argv(Privilege::Sudo.command_with("snapper", true)), ["sudo", "-A", "snapper"]);
+        assert_eq!(argv(Privilege::Sudo.command_with("snapper", false)), ["sudo", "snapper"]);
+        assert_eq!(argv(Privilege::Doas.command_with("snapper", true)), ["doas", "snapper"]);
+        assert_eq!(argv(Privilege::Direct.command_with("snapper", true)), ["snapper"]);
+    }
 
     #[test]
     fn test_snapshot_parsing() {
@@ -157,4 +1515,421 @@ mod tests {
         assert!(snap.active);
         assert_eq!(snap.userdata.as_ref().unwrap().get("important").unwrap(), "yes");
     }
+
+    #[test]
+    fn test_empty_and_malformed_snapshot_lists() {
+        assert!(parse_snapshot_list("{}").unwrap().is_empty());
+        assert!(parse_snapshot_list(r#"{"root": null, "home": []}"#).unwrap().is_empty());
+
+        // Null columns fall back to their defaults
+        let snaps = parse_snapshot_list(r#"{"root": [{"number": 3, "description": null, "default": null}]}"#).unwrap();
+        assert_eq!((snaps[0].number, snaps[0].description.as_str()), (3, ""));
+
+        // Fields from another snapper version are kept rather than dropped
+        let snaps = parse_snapshot_list(r#"{"root": [{"number": 4, "used_space": 10, "read-only": true}]}"#).unwrap();
+        assert_eq!(snaps[0].used_space, None);
+        assert_eq!(snaps[0].extra.get("used_space"), Some(&serde_json::json!(10)));
+        assert_eq!(snaps[0].extra.get("read-only"), Some(&serde_json::json!(true)));
+        assert!(!snaps[0].extra.contains_key("number"));
+
+        let err = parse_snapshot_list(r#"{"root": [{"number": "three"}]}"#).unwrap_err();
+        assert!(format!("{:#}", err).contains("Failed to parse snapper JSON output"), "{:#}", err);
+        assert!(parse_snapshot_list("  \n").is_err());
+    }
+
+    #[test]
+    fn test_cancel_kills_running_child() {
+        let cancel = CancelToken::default();
+        let canceller = cancel.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+
+        let start = Instant::now();
+        let err = run_streaming(Command::new("sleep").arg("5"), &cancel, |_| {}).unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_only_lock_contention_is_retried() {
+        use std::os::unix::process::ExitStatusExt;
+        let failed = |stderr: &str| Output {
+            status: ExitStatus::from_raw(1 << 8),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        };
+
+        let locked = failed("Failure (/var/lib/snapper/.snapper.lock: file is already locked).\n");
+        assert!(should_retry(&locked, 0, 3));
+        assert!(should_retry(&locked, 2, 3));
+        assert!(!should_retry(&locked, 3, 3));
+        assert!(!should_retry(&locked, 0, 0));
+
+        assert!(!should_retry(&failed("Unknown config.\n"), 0, 3));
+        let ok = Output { status: ExitStatus::from_raw(0), ..failed("file is already locked") };
+        assert!(!should_retry(&ok, 0, 3));
+
+        assert_eq!(retry_delay(1), retry_delay(0) * 2);
+    }
+
+    #[test]
+    fn test_cancel_after_child_finished_is_noop() {
+        let cancel = CancelToken::default();
+        let output = run_streaming(&mut Command::new("true"), &cancel, |_| {}).unwrap();
+        assert!(output.status.success());
+        cancel.cancel();
+
+        // A finished-but-still-parked child must not make cancel panic either
+        let mut child = Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        let cancel = CancelToken::default();
+        cancel.0.lock().unwrap().child = Some(child);
+        cancel.cancel();
+        assert!(cancel.is_cancelled());
+
+        // Once cancelled, no new process is started
+        let err = run_streaming(&mut Command::new("true"), &cancel, |_| {}).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_streams_stdout_lines() {
+        let mut lines = Vec::new();
+        let output = run_streaming(
+            Command::new("sh").args(["-c", "echo one; sleep 0.2; echo two; printf three"]),
+            &CancelToken::default(),
+            |chunk| lines.push(chunk),
+        )
+        .unwrap();
+        // The first line is delivered before the command has finished
+        assert_eq!(lines.first(), Some(&vec![String::from("one")]));
+        assert_eq!(lines.concat(), ["one", "two", "three"]);
+        assert_eq!(output.stdout, b"one\ntwo\nthree");
+    }
+
+    #[test]
+    fn test_missing_binary_is_typed() {
+        let err = execute(&mut Command::new("snapper-tui-no-such-binary"), &CancelToken::default())
+            .context("Failed to execute snapper command")
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SnapperError>(),
+            Some(&SnapperError::NotFound(String::from("snapper-tui-no-such-binary")))
+        );
+    }
+
+    #[test]
+    fn test_ssh_quotes_the_remote_command() {
+        let ssh = Ssh::new(String::from("admin@nas"));
+        let mut cmd = Privilege::Sudo.command_with("snapper", false);
+        cmd.args(["create", "--description", "before it's; rm -rf"]);
+        let wrapped = ssh.command(&cmd);
+        assert_eq!(wrapped.get_program(), "ssh");
+        let args: Vec<_> = wrapped.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(&args[args.len() - 2..], ["admin@nas", r"sudo snapper create --description 'before it'\''s; rm -rf'"]);
+        assert!(args.contains(&String::from("BatchMode=yes")));
+
+        assert_eq!(shell_quote("@/.snapshots/1/snapshot"), "@/.snapshots/1/snapshot");
+        assert_eq!(shell_quote("*.conf; ls"), "'*.conf; ls'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn test_descriptions_reach_snapper_literally() {
+        let nasty = r#"it's "quoted" $(echo injected) `echo injected` ; echo injected * \n"#;
+        let cancel = CancelToken::default();
+        let new = NewSnapshot { description: nasty.to_string(), snapshot_type: String::from("single"), ..Default::default() };
+
+        // Run here, the description is one argv element, untouched
+        let (result, argv) = mocked(&[(0, "42\n", "")], || create_snapshot(&new, Privilege::Direct, &cancel));
+        assert_eq!(result.unwrap(), Some(42));
+        assert_eq!(argv[0][5..7], ["--description", nasty]);
+
+        // Over ssh the remote shell re-splits the command; sh must hand the
+        // same word back without running anything in it
+        let mut cmd = Command::new("printf");
+        cmd.args(["%s", nasty]);
+        let remote = Ssh::new(String::from("admin@nas")).command(&cmd).get_args().last().unwrap().to_owned();
+        let output = Command::new("sh").arg("-c").arg(remote).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), nasty);
+
+        // Control characters can't break snapper's listing or the terminal
+        let new = NewSnapshot { description: String::from("two\nlines\x1b[2J"), ..new };
+        assert!(create_args(&new).contains(&String::from("two lines [2J")));
+    }
+
+    #[test]
+    fn test_privilege_command() {
+        let cmd = Privilege::Pkexec.snapper();
+        assert_eq!(cmd.get_program(), "pkexec");
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["snapper"]);
+        assert_eq!(Privilege::Direct.snapper().get_program(), "snapper");
+
+        let dismissed = Command::new("sh").args(["-c", "exit 126"]).status().unwrap();
+        assert!(Privilege::Pkexec.auth_cancelled(&dismissed));
+        assert!(!Privilege::Sudo.auth_cancelled(&dismissed));
+    }
+
+    #[test]
+    fn test_parse_btrfs_usage() {
+        let text = "Overall:
+    Device size:                 107374182400
+    Device allocated:             53687091200
+    Device unallocated:           53687091200
+    Device missing:                         0
+    Used:                         42949672960
+    Free (estimated):             62277025792      (min: 35433480192)
+    Data ratio:                          1.00
+
+Data,single: Size:48318382080, Used:40802189312 (84.44%)
+   /dev/nvme0n1p2   48318382080
+";
+        assert_eq!(parse_btrfs_usage(text), Some((42949672960, 107374182400)));
+        assert_eq!(parse_btrfs_usage("ERROR: not a btrfs filesystem: /home"), None);
+    }
+
+    #[test]
+    fn test_dates_in_both_formats() {
+        let utc = parse_date("2024-01-15T10:00:00Z").unwrap();
+        assert_eq!(utc, 1705312800);
+        assert_eq!(parse_date("2024-01-15T12:00:00+02:00"), Some(utc));
+        assert_eq!(parse_date("2024-01-15T04:30:00.250-0530"), Some(utc));
+        assert_eq!(parse_date("2000-03-01T00:00:00Z"), Some(951868800));
+
+        // Local forms agree with each other and read back unchanged
+        let local = parse_date("2024-01-15 10:00:00").unwrap();
+        assert_eq!(parse_date("2024-01-15T10:00:00"), Some(local));
+        assert_eq!(format_local_time(local), "2024-01-15 10:00:00");
+
+        assert_eq!(parse_date("2024-01-15T10:00:00+2"), None);
+        assert_eq!(parse_date("2024-01-15"), None);
+        assert_eq!(parse_date("yesterday"), None);
+
+        let list = r#"{"root": [{"number": 1, "date": "2024-01-15T10:00:00Z"}, {"number": 2, "date": "?"}]}"#;
+        let snapshots = parse_snapshot_list(list).unwrap();
+        assert_eq!(snapshots[0].timestamp, Some(utc));
+        assert_eq!(snapshots[1].timestamp, None);
+    }
+
+    #[test]
+    fn test_snapper_version() {
+        let version = parse_snapper_version("snapper 0.10.4\nlibsnapper 7.2.0\nflags btrfs,lvm,no-ext4\n").unwrap();
+        assert_eq!(version, "0.10.4");
+        assert!(supports_jsonout(&version));
+        assert!(supports_jsonout("0.8.10"));
+        assert!(!supports_jsonout("0.8.9"));
+        assert!(!supports_jsonout("0.5"));
+        assert!(supports_jsonout("1.0-git"));
+        assert_eq!(parse_snapper_version("command not found"), None);
+    }
+
+    #[test]
+    fn test_boot_lineage_paths() {
+        let mountinfo = "\
+22 1 0:21 /@/.snapshots/7/snapshot / rw,relatime shared:1 - btrfs /dev/sda2 rw,subvol=/@/.snapshots/7/snapshot
+23 22 0:22 / /proc rw,nosuid - proc proc rw
+24 22 0:23 /@/home /home rw,relatime shared:2 - btrfs /dev/sda2 rw,subvol=/@/home
+";
+        let (fstype, root) = parse_mountinfo(mountinfo).unwrap();
+        assert_eq!(fstype, "btrfs");
+        assert_eq!(snapshot_number_of(&root), Some(7));
+        assert_eq!(parse_mountinfo("25 1 8:1 / / rw - ext4 /dev/sda1 rw\n").unwrap().0, "ext4");
+        assert_eq!(parse_mountinfo("23 22 0:22 / /proc rw - proc proc rw\n"), None);
+
+        let default = parse_default_subvolume("ID 268 gen 2911 top level 257 path @/.snapshots/9/snapshot\n").unwrap();
+        assert_eq!(snapshot_number_of(&default), Some(9));
+        assert_eq!(parse_default_subvolume("ID 5 (FS_TREE)\n").as_deref(), Some("/"));
+        assert_eq!(snapshot_number_of("/"), None);
+        assert_eq!(snapshot_number_of("@"), None);
+        assert_eq!(snapshot_number_of("@/.snapshots/x/snapshot"), None);
+    }
+
+    fn export_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("snapper-tui-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_json_export_round_trip() {
+        let snaps = vec![
+            Snapshot { config: "root".into(), number: 7, snapshot_type: "pre".into(), post_number: Some(8), description: "zypper".into(), used_space: Some(4096), ..Default::default() },
+            Snapshot { config: "home".into(), number: 8, snapshot_type: "post".into(), pre_number: Some(7), active: true, ..Default::default() },
+        ];
+        let path = export_path("export.json");
+        assert_eq!(ExportFormat::from_path(&path), ExportFormat::Json);
+        export_snapshots(&snaps, &path, ExportFormat::Json).unwrap();
+
+        let back: Vec<Snapshot> = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back[0].config, "root");
+        assert_eq!((back[0].number, back[0].post_number, back[0].used_space), (7, Some(8), Some(4096)));
+        assert_eq!(back[0].description, "zypper");
+        assert_eq!((back[1].snapshot_type.as_str(), back[1].pre_number, back[1].active), ("post", Some(7), true));
+    }
+
+    #[test]
+    fn test_saved_report_starts_with_header() {
+        let range = StatusRange { config: String::from("root"), pre: 41, post: 42 };
+        let path = export_path("report.txt");
+        let header = report_header(&range, Some("/etc/fstab"), "2025-01-02 03:04:05");
+        save_report(&path, &format!("{}c..... /etc/fstab\n", header)).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            text,
+            "# snapper status 41..42 (config root)\n# diff of /etc/fstab\n# saved 2025-01-02 03:04:05\n\nc..... /etc/fstab\n"
+        );
+        assert!(save_report(Path::new("/nonexistent-dir/report.txt"), "x").is_err());
+    }
+
+    #[test]
+    fn test_csv_export_quotes_fields() {
+        let snaps = vec![Snapshot { number: 3, description: "say \"hi\", then go".into(), ..Default::default() }];
+        assert_eq!(ExportFormat::from_path(Path::new("out.CSV")), ExportFormat::Csv);
+        let csv = snapshots_to_csv(&snaps);
+        let row = csv.lines().nth(1).unwrap();
+        assert_eq!(row, ",3,,,,,,,\"say \"\"hi\"\", then go\",,false,false");
+    }
+
+    #[test]
+    fn test_status_range_boundaries() {
+        let snap = |number, snapshot_type: &str, pre_number, post_number| Snapshot {
+            config: "root".into(),
+            number,
+            snapshot_type: snapshot_type.into(),
+            pre_number,
+            post_number,
+            ..Default::default()
+        };
+        let range = |snap: &Snapshot| status_range(snap).map(|r| (r.pre, r.post));
+
+        assert_eq!(range(&snap(5, "single", None, None)), Some((4, 5)));
+        assert_eq!(range(&snap(8, "post", Some(7), None)), Some((7, 8)));
+        assert_eq!(range(&snap(7, "pre", None, Some(8))), Some((7, 8)));
+
+        // Nothing to compare against: no bogus 0..1 or 0..0
+        assert_eq!(range(&snap(0, "single", None, None)), None);
+        assert_eq!(range(&snap(1, "single", None, None)), None);
+        // A pre whose post never ran only has unrelated neighbours
+        assert_eq!(range(&snap(9, "pre", None, None)), None);
+        // A recorded pre still counts, even for low numbers
+        assert_eq!(range(&snap(1, "post", Some(0), None)), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_snapshot_browsing_helpers() {
+        assert_eq!(snapshot_path("/", 12), PathBuf::from("/.snapshots/12/snapshot"));
+        assert_eq!(snapshot_path("/home", 3), PathBuf::from("/home/.snapshots/3/snapshot"));
+
+        let entries = parse_ls("zeta\netc/\n.hidden\nbin/\n");
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["bin", "etc", ".hidden", "zeta"]);
+        assert!(entries[0].is_dir && !entries[2].is_dir);
+
+        assert_eq!(preview_text(b"plain\n"), "plain\n");
+        assert_eq!(preview_text("caf\u{e9}".as_bytes().split_last().unwrap().1), "caf");
+        assert_eq!(preview_text(b"\x7fELF\x00"), "(binary file)");
+    }
+
+    #[test]
+    fn test_clipboard_text() {
+        let snap = Snapshot {
+            number: 9,
+            snapshot_type: "single".into(),
+            description: "tab\there".into(),
+            ..Default::default()
+        };
+        let tsv = snapshots_to_tsv(&[&snap]);
+        assert!(tsv.starts_with("config\tnumber\ttype\t"));
+        assert_eq!(tsv.lines().nth(1).unwrap(), "\t9\tsingle\t\t\t\t\t\ttab here\t\tfalse\tfalse");
+
+        let summary = snapshot_summary(&snap);
+        assert!(summary.contains("Number: 9\n"));
+        assert!(!summary.contains("Userdata"));
+    }
+
+    #[test]
+    fn test_create_args() {
+        let plain = NewSnapshot { description: String::from("before upgrade"), snapshot_type: String::from("single"), ..Default::default() };
+        assert_eq!(create_args(&plain), ["create", "--print-number", "--type", "single", "--description", "before upgrade"]);
+
+        let post = NewSnapshot {
+            description: String::from("after upgrade"),
+            snapshot_type: String::from("post"),
+            pre_number: Some(41),
+            cleanup: Some(String::from("number")),
+            userdata: Some(parse_userdata("important=yes, by=tui").unwrap()),
+            config: Some(String::from("home")),
+        };
+        assert_eq!(
+            create_args(&post),
+            [
+                "-c", "home", "create", "--print-number", "--type", "post", "--pre-number", "41", "--description", "after upgrade",
+                "--cleanup-algorithm", "number", "--userdata", "by=tui,important=yes",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modify_args_only_include_changed_fields() {
+        assert_eq!(
+            modify_args("root", 12, Some("fixed typo"), None, None),
+            ["-c", "root", "modify", "--description", "fixed typo", "12"]
+        );
+        let userdata = parse_userdata("important=yes, b = 2").unwrap();
+        assert_eq!(
+            modify_args("home", 3, None, Some("number"), Some(&userdata)),
+            ["-c", "home", "modify", "--cleanup-algorithm", "number", "--userdata", "b=2,important=yes", "3"]
+        );
+        assert_eq!(format_userdata(Some(&userdata)), "b=2, important=yes");
+        assert!(parse_userdata("").unwrap().is_empty());
+        assert!(parse_userdata("novalue").is_err());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config(
+            r#"{"SUBVOLUME": "/", "NUMBER_LIMIT": "2-10", "TIMELINE_LIMIT_DAILY": "10", "SPACE_LIMIT": "0.5"}"#,
+        )
+        .unwrap();
+        assert_eq!(config["NUMBER_LIMIT"], "2-10");
+        assert_eq!(config["TIMELINE_LIMIT_DAILY"], "10");
+        assert_eq!(config.len(), 4);
+        assert!(parse_config("Key | Value").is_err());
+    }
+
+    #[test]
+    fn test_config_file_fstype() {
+        let text = "# subvolume to snapshot\nSUBVOLUME=\"/home\"\n\n# filesystem type\nFSTYPE=\"lvm(xfs)\"\n";
+        let fs = ConfigFs { fstype: config_file_value(text, "FSTYPE").unwrap() };
+        assert_eq!(fs.fstype, "lvm(xfs)");
+        assert!(!fs.supports_rollback());
+        assert_eq!(config_file_value(text, "SUBVOLUME").as_deref(), Some("/home"));
+        assert_eq!(config_file_value(text, "QGROUP"), None);
+        assert!(ConfigFs { fstype: String::from("btrfs") }.supports_rollback());
+    }
+
+    #[test]
+    fn test_parse_status_line() {
+        assert_eq!(parse_status_line("c..... /etc/fstab"), Some(("c.....", "/etc/fstab")));
+        assert_eq!(
+            parse_status_line("+..... /home/user/new file.txt"),
+            Some(("+.....", "/home/user/new file.txt"))
+        );
+        assert_eq!(parse_status_line(""), None);
+        assert_eq!(parse_status_line("no changes"), None);
+    }
+
+    #[test]
+    fn test_binary_diff_detection() {
+        assert!(!is_binary_diff(b"--- a\n+++ b\n@@ -1 +1 @@\n-old\n+new\n"));
+        assert!(is_binary_diff(b"Binary files /a/x and /b/x differ\n"));
+        assert!(is_binary_diff(b"\x7fELF\x00\x01"));
+        assert!(is_binary_diff(&[0xff, 0xfe, 0x00]));
+    }
 }
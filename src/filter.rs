@@ -0,0 +1,287 @@
+use crate::data::Snapshot;
+
+/// A parsed filter input. `key:value` tokens become exact or range
+/// predicates; everything else is the free-text search, which ignores case
+/// and matches anywhere in a word unless the options say otherwise.
+#[derive(Debug, Default, PartialEq)]
+pub struct Filter {
+    pub types: Vec<String>,
+    pub users: Vec<String>,
+    /// Inclusive lower bound as `YYYY-MM-DD`.
+    pub after: Option<String>,
+    /// Exclusive upper bound as `YYYY-MM-DD`.
+    pub before: Option<String>,
+    pub text: String,
+    /// The free text must match with the same case.
+    pub case_sensitive: bool,
+    /// The free text must match whole words, not just part of one.
+    pub whole_word: bool,
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Filter {
+        let mut filter = Filter::default();
+        let mut words = Vec::new();
+
+        for word in input.split_whitespace() {
+            match word.split_once(':') {
+                Some(("type", value)) if !value.is_empty() => filter.types.push(value.to_lowercase()),
+                Some(("user", value)) if !value.is_empty() => filter.users.push(value.to_lowercase()),
+                Some(("after", value)) if is_date(value) => filter.after = Some(value.to_string()),
+                Some(("before", value)) if is_date(value) => filter.before = Some(value.to_string()),
+                _ => words.push(word),
+            }
+        }
+
+        filter.text = words.join(" ");
+        filter
+    }
+
+    pub fn matches(&self, snap: &Snapshot) -> bool {
+        self.score(snap, false).is_some()
+    }
+
+    /// `None` when `snap` is filtered out; otherwise a relevance score for
+    /// the free text, higher being better. Plain substring matches all
+    /// score zero.
+    pub fn score(&self, snap: &Snapshot, fuzzy: bool) -> Option<i64> {
+        // Repeating a key widens it: `type:pre type:post` matches either
+        let matches_any = |values: &[String], field: &str| {
+            values.is_empty() || values.iter().any(|v| v.eq_ignore_ascii_case(field))
+        };
+        // snapper dates start with `YYYY-MM-DD`, so string order is date order
+        let day = snap.date.get(..10).unwrap_or(&snap.date);
+
+        let passes = matches_any(&self.types, &snap.snapshot_type)
+            && matches_any(&self.users, &snap.user)
+            && self.after.as_deref().is_none_or(|after| day >= after)
+            && self.before.as_deref().is_none_or(|before| day < before);
+        if !passes {
+            return None;
+        }
+        if self.text.is_empty() {
+            return Some(0);
+        }
+
+        if fuzzy {
+            let haystack = format!("{} {} {} {}", snap.number, snap.snapshot_type, snap.user, snap.description);
+            return match self.case_sensitive {
+                true => fuzzy_score(&self.text, &haystack),
+                false => fuzzy_score(&self.text.to_lowercase(), &haystack.to_lowercase()),
+            };
+        }
+        let found = [snap.description.as_str(), snap.snapshot_type.as_str(), snap.user.as_str()]
+            .into_iter()
+            .any(|field| self.text_matches(field))
+            || self.text_matches(&snap.number.to_string());
+        found.then_some(0)
+    }
+
+    fn text_matches(&self, field: &str) -> bool {
+        let (field, text) = self.fold(field);
+        self.occurrences(&field, &text).next().is_some()
+    }
+
+    /// Whether any `key:value` tokens narrow the view besides the free text.
+    pub fn has_tokens(&self) -> bool {
+        !self.types.is_empty() || !self.users.is_empty() || self.after.is_some() || self.before.is_some()
+    }
+
+    /// Byte ranges of `field` the free text matches, for highlighting. Empty
+    /// when case folding moved the offsets, which only non-ASCII text does.
+    pub fn match_ranges(&self, field: &str) -> Vec<(usize, usize)> {
+        if self.text.is_empty() {
+            return Vec::new();
+        }
+        let (folded, text) = self.fold(field);
+        let ranges: Vec<(usize, usize)> = self.occurrences(&folded, &text).collect();
+        let aligned = folded.len() == field.len()
+            && ranges.iter().all(|&(start, end)| field.is_char_boundary(start) && field.is_char_boundary(end));
+        if aligned { ranges } else { Vec::new() }
+    }
+
+    fn fold(&self, field: &str) -> (String, String) {
+        match self.case_sensitive {
+            true => (field.to_string(), self.text.clone()),
+            false => (field.to_lowercase(), self.text.to_lowercase()),
+        }
+    }
+
+    // Where `text` occurs in `field`, both already folded
+    fn occurrences<'a>(&self, field: &'a str, text: &'a str) -> impl Iterator<Item = (usize, usize)> + 'a {
+        // Every occurrence is tried: `vim` is a word in `vimrc vim`
+        let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+        let whole_word = self.whole_word;
+        field.match_indices(text).map(|(start, found)| (start, start + found.len())).filter(move |&(start, end)| {
+            !whole_word || (!is_word_char(field[..start].chars().next_back()) && !is_word_char(field[end..].chars().next()))
+        })
+    }
+}
+
+/// Scores `needle` as an in-order subsequence of `haystack` (both already
+/// folded to the case being compared), rewarding consecutive runs and word
+/// starts and penalising gaps, in the spirit of skim/fzf.
+pub fn fuzzy_score(needle: &str, haystack: &str) -> Option<i64> {
+    const MATCH: i64 = 16;
+    const CONSECUTIVE: i64 = 12;
+    const WORD_START: i64 = 8;
+    const GAP: i64 = 1;
+
+    let needle: Vec<char> = needle.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut wanted = needle.iter().peekable();
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    let mut prev_matched = false;
+    let mut started = false;
+
+    for c in haystack.chars() {
+        let Some(&&want) = wanted.peek() else { break };
+        if c == want {
+            score += MATCH;
+            if prev_matched {
+                score += CONSECUTIVE;
+            }
+            if prev.is_none_or(|p| !p.is_alphanumeric()) {
+                score += WORD_START;
+            }
+            wanted.next();
+            prev_matched = true;
+            started = true;
+        } else {
+            if started {
+                score -= GAP;
+            }
+            prev_matched = false;
+        }
+        prev = Some(c);
+    }
+
+    wanted.peek().is_none().then_some(score)
+}
+
+fn is_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(number: u32, snapshot_type: &str, user: &str, date: &str, description: &str) -> Snapshot {
+        Snapshot {
+            number,
+            snapshot_type: snapshot_type.to_string(),
+            user: user.to_string(),
+            date: date.to_string(),
+            description: description.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn matching(input: &str) -> Vec<u32> {
+        let snapshots = [
+            snap(1, "single", "root", "2023-09-30 23:59:59", "timeline"),
+            snap(2, "pre", "root", "2023-10-01 08:00:00", "zypper install vim"),
+            snap(3, "post", "root", "2023-10-01 08:01:00", "zypper install vim"),
+            snap(4, "single", "alice", "2023-10-31 12:00:00", "before upgrade"),
+            snap(5, "single", "root", "2023-11-01 00:00:00", "timeline"),
+        ];
+        let filter = Filter::parse(input);
+        snapshots.iter().filter(|s| filter.matches(s)).map(|s| s.number).collect()
+    }
+
+    #[test]
+    fn test_type_token() {
+        assert_eq!(matching("type:pre"), vec![2]);
+        assert_eq!(matching("type:PRE type:post"), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_user_token() {
+        assert_eq!(matching("user:alice"), vec![4]);
+        assert_eq!(matching("user:ali"), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_date_tokens() {
+        assert_eq!(matching("after:2023-10-01"), vec![2, 3, 4, 5]);
+        assert_eq!(matching("before:2023-10-01"), vec![1]);
+        assert_eq!(matching("after:2023-10-01 before:2023-11-01"), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_bare_words_keep_substring_search() {
+        assert_eq!(matching("zypper install"), vec![2, 3]);
+        assert_eq!(matching("5"), vec![5]);
+        // A malformed token is searched for literally
+        assert_eq!(matching("after:yesterday"), Vec::<u32>::new());
+        assert_eq!(Filter::parse("after:yesterday").text, "after:yesterday");
+    }
+
+    #[test]
+    fn test_case_and_whole_word_options() {
+        let snapshots = [
+            snap(1, "single", "root", "", "Kernel update"),
+            snap(2, "single", "root", "", "kernel-default updated"),
+            snap(3, "single", "root", "", "KERNEL"),
+            snap(4, "single", "root", "", "kernels"),
+        ];
+        let matching = |input: &str, case_sensitive, whole_word| {
+            let filter = Filter { case_sensitive, whole_word, ..Filter::parse(input) };
+            snapshots.iter().filter(|s| filter.matches(s)).map(|s| s.number).collect::<Vec<_>>()
+        };
+        assert_eq!(matching("kernel", false, false), vec![1, 2, 3, 4]);
+        assert_eq!(matching("kernel", true, false), vec![2, 4]);
+        assert_eq!(matching("kernel", false, true), vec![1, 2, 3]);
+        assert_eq!(matching("kernel", true, true), vec![2]);
+        assert_eq!(matching("Kernel", true, true), vec![1]);
+        assert_eq!(matching("update", false, true), vec![1]);
+        // Tokens are unaffected by the options
+        assert_eq!(matching("type:SINGLE KERNEL", true, true), vec![3]);
+    }
+
+    #[test]
+    fn test_match_ranges() {
+        let filter = Filter::parse("vim");
+        assert_eq!(filter.match_ranges("Install VIM and vimrc"), vec![(8, 11), (16, 19)]);
+        let filter = Filter { whole_word: true, ..Filter::parse("vim") };
+        assert_eq!(filter.match_ranges("Install VIM and vimrc"), vec![(8, 11)]);
+        let filter = Filter { case_sensitive: true, ..Filter::parse("vim") };
+        assert_eq!(filter.match_ranges("Install VIM and vimrc"), vec![(16, 19)]);
+        // `İ` lowercases to three bytes, so offsets can't be trusted
+        assert_eq!(Filter::parse("vim").match_ranges("İ vim"), Vec::new());
+        assert_eq!(Filter::parse("").match_ranges("vim"), Vec::new());
+
+        assert!(!Filter::parse("zypper vim").has_tokens());
+        assert!(Filter::parse("type:pre vim").has_tokens());
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("tmln", "timeline").is_some());
+        assert!(fuzzy_score("tlmn", "timeline").is_none());
+        // Contiguous and word-start matches beat scattered ones
+        assert!(fuzzy_score("vim", "install vim").unwrap() > fuzzy_score("vim", "very import month").unwrap());
+        assert!(fuzzy_score("up", "before upgrade").unwrap() > fuzzy_score("up", "cleanup").unwrap());
+    }
+
+    #[test]
+    fn test_fuzzy_filter_respects_tokens() {
+        let filter = Filter::parse("type:single tmln");
+        assert!(filter.score(&snap(1, "single", "root", "", "timeline"), true).is_some());
+        assert!(filter.score(&snap(1, "pre", "root", "", "timeline"), true).is_none());
+        assert!(filter.score(&snap(1, "single", "root", "", "timeline"), false).is_none());
+    }
+
+    #[test]
+    fn test_combined_tokens_and_words() {
+        assert_eq!(matching("type:single user:root timeline"), vec![1, 5]);
+        assert_eq!(matching("type:single timeline after:2023-10-01"), vec![5]);
+        assert_eq!(matching("user:root zypper type:post"), vec![3]);
+    }
+}
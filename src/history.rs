@@ -0,0 +1,144 @@
+use crate::data;
+use anyhow::Result;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Oldest entries are forgotten past this; the file keeps everything
+const MAX_ENTRIES: usize = 1000;
+
+/// One privileged command the TUI ran.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Local time it finished, `YYYY-MM-DD HH:MM:SS`.
+    pub time: String,
+    pub argv: Vec<String>,
+    /// `exit N`, or why the command didn't run to completion.
+    pub result: String,
+    pub stderr: String,
+}
+
+impl Entry {
+    pub fn succeeded(&self) -> bool {
+        self.result == "exit 0"
+    }
+
+    /// The command line, quoted where needed so it can be pasted into a shell.
+    pub fn command_line(&self) -> String {
+        self.argv.iter().map(|arg| data::shell_quote(arg)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+struct History {
+    entries: Vec<Entry>,
+    file: Option<PathBuf>,
+}
+
+// Commands run on worker threads, so the log is shared process-wide
+static HISTORY: Mutex<History> = Mutex::new(History { entries: Vec::new(), file: None });
+
+/// `~/.local/share/snapper-tui/history.log`, honouring `$XDG_DATA_HOME`.
+pub fn path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+    Some(base.join("snapper-tui").join("history.log"))
+}
+
+/// Also appends every entry to `file` from now on; `None` keeps the log in
+/// memory only.
+pub fn persist_to(file: Option<PathBuf>) {
+    HISTORY.lock().unwrap_or_else(PoisonError::into_inner).file = file;
+}
+
+pub fn record(cmd: &Command, outcome: &Result<Output>) {
+    let entry = entry_for(cmd, outcome, &local_timestamp(SystemTime::now()));
+    let mut history = HISTORY.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(file) = &history.file {
+        // The audit trail is best effort; a full disk shouldn't fail the command
+        let _ = append(file, &format_entry(&entry));
+    }
+    history.entries.push(entry);
+    if history.entries.len() > MAX_ENTRIES {
+        history.entries.remove(0);
+    }
+}
+
+/// Everything recorded this session, oldest first.
+pub fn entries() -> Vec<Entry> {
+    HISTORY.lock().unwrap_or_else(PoisonError::into_inner).entries.clone()
+}
+
+fn entry_for(cmd: &Command, outcome: &Result<Output>, time: &str) -> Entry {
+    let argv = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let (result, stderr) = match outcome {
+        Ok(output) => (
+            match output.status.code() {
+                Some(code) => format!("exit {}", code),
+                None => String::from("killed by a signal"),
+            },
+            String::from_utf8_lossy(&output.stderr).trim_end().to_string(),
+        ),
+        Err(e) => (format!("error: {:#}", e), String::new()),
+    };
+    Entry { time: time.to_string(), argv, result, stderr }
+}
+
+fn format_entry(entry: &Entry) -> String {
+    let mut text = format!("{}  {}  {}\n", entry.time, entry.result, entry.command_line());
+    for line in entry.stderr.lines() {
+        text.push_str(&format!("    {}\n", line));
+    }
+    text
+}
+
+fn append(file: &PathBuf, text: &str) -> std::io::Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new().create(true).append(true).open(file)?.write_all(text.as_bytes())
+}
+
+fn local_timestamp(time: SystemTime) -> String {
+    data::format_local_time(time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    #[test]
+    fn test_entries_record_argv_status_and_stderr() {
+        let mut cmd = Command::new("sudo");
+        cmd.args(["snapper", "delete", "5"]);
+        let output = Output {
+            status: ExitStatus::from_raw(1 << 8),
+            stdout: Vec::new(),
+            stderr: b"Snapshot '5' not found.\n".to_vec(),
+        };
+        let entry = entry_for(&cmd, &Ok(output), "2025-01-02 03:04:05");
+        assert_eq!(entry.argv, vec!["sudo", "snapper", "delete", "5"]);
+        assert_eq!(entry.result, "exit 1");
+        assert!(!entry.succeeded());
+        assert_eq!(
+            format_entry(&entry),
+            "2025-01-02 03:04:05  exit 1  sudo snapper delete 5\n    Snapshot '5' not found.\n"
+        );
+
+        let mut cmd = Command::new("snapper");
+        cmd.args(["create", "--description", "before it's upgraded"]);
+        let entry = entry_for(&cmd, &Err(anyhow::anyhow!("operation cancelled")), "t");
+        assert_eq!(entry.result, "error: operation cancelled");
+        assert_eq!(entry.command_line(), r"snapper create --description 'before it'\''s upgraded'");
+    }
+}
@@ -0,0 +1,253 @@
+use crate::app::SortKey;
+use ratatui::crossterm::event::KeyCode;
+use std::collections::HashMap;
+
+/// Main-screen commands whose keys can be changed in the `[keys]` section of
+/// the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Create,
+    Delete,
+    Apply,
+    Filter,
+    Status,
+    Refresh,
+    Quit,
+    Sort(SortKey),
+}
+
+pub const ACTIONS: [Action; 12] = [
+    Action::Create,
+    Action::Delete,
+    Action::Apply,
+    Action::Filter,
+    Action::Status,
+    Action::Refresh,
+    Action::Quit,
+    Action::Sort(SortKey::Number),
+    Action::Sort(SortKey::Type),
+    Action::Sort(SortKey::Date),
+    Action::Sort(SortKey::User),
+    Action::Sort(SortKey::UsedSpace),
+];
+
+// Keys the main screen handles itself; binding an action to one of these
+// would hide the built-in command
+const RESERVED: &[char] = &[
+    'C', '?', 'l', 'L', 'o', 'O', 't', 'T', 'e', 'E', 'g', 'G', 'm', 'M', 'k', 'K', 'y', 'Y', 'b', 'B', 'i', 'I', 'v',
+    'V', 'x', 'X', 'u', 'U', ']', '[', ' ', ':', '#', 'w', 'W', 'f', 'F', 'N', 'p', 'P', 'H', 'J', 'j', 'z', '>',
+];
+const RESERVED_KEYS: &[KeyCode] =
+    &[KeyCode::Enter, KeyCode::Tab, KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown];
+
+fn is_reserved(key: KeyCode) -> bool {
+    match key {
+        KeyCode::Char(c) => RESERVED.contains(&c),
+        _ => RESERVED_KEYS.contains(&key),
+    }
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Create => "create",
+            Action::Delete => "delete",
+            Action::Apply => "apply",
+            Action::Filter => "filter",
+            Action::Status => "status",
+            Action::Refresh => "refresh",
+            Action::Quit => "quit",
+            Action::Sort(SortKey::Number) => "sort_number",
+            Action::Sort(SortKey::Type) => "sort_type",
+            Action::Sort(SortKey::Date) => "sort_date",
+            Action::Sort(SortKey::User) => "sort_user",
+            Action::Sort(SortKey::UsedSpace) => "sort_used_space",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.into_iter().find(|action| action.name() == name)
+    }
+
+    fn default_keys(self) -> &'static [char] {
+        match self {
+            Action::Create => &['c'],
+            Action::Delete => &['d', 'D'],
+            Action::Apply => &['a', 'A'],
+            Action::Filter => &['/'],
+            Action::Status => &['s', 'S'],
+            Action::Refresh => &['r', 'R'],
+            Action::Quit => &['q', 'Q'],
+            Action::Sort(SortKey::Number) => &['1'],
+            Action::Sort(SortKey::Type) => &['2'],
+            Action::Sort(SortKey::Date) => &['3'],
+            Action::Sort(SortKey::User) => &['4'],
+            Action::Sort(SortKey::UsedSpace) => &['5'],
+        }
+    }
+}
+
+/// Parses a key spec: a single character (case matters) or a key name
+/// such as `enter`, `space`, `delete` or `f5`.
+pub fn parse_key(spec: &str) -> Option<KeyCode> {
+    let mut chars = spec.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let lower = spec.to_ascii_lowercase();
+    let code = match lower.as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => return None,
+        },
+    };
+    Some(code)
+}
+
+/// The spec `parse_key` reads back as `code`.
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => String::from("space"),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("f{}", n),
+        KeyCode::Enter => String::from("enter"),
+        KeyCode::Tab => String::from("tab"),
+        KeyCode::Backspace => String::from("backspace"),
+        KeyCode::Delete => String::from("delete"),
+        KeyCode::Insert => String::from("insert"),
+        KeyCode::Home => String::from("home"),
+        KeyCode::End => String::from("end"),
+        KeyCode::PageUp => String::from("pageup"),
+        KeyCode::PageDown => String::from("pagedown"),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+/// Which action each remappable key runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMap(HashMap<KeyCode, Action>);
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap::build(&[]).0
+    }
+}
+
+impl KeyMap {
+    /// The default bindings with `overrides` replacing the keys of the
+    /// actions they name. Also returns a warning for every key that ends up
+    /// claimed twice; the configured binding wins over a default one.
+    pub fn build(overrides: &[(Action, Vec<KeyCode>)]) -> (KeyMap, Vec<String>) {
+        let mut map: HashMap<KeyCode, Action> = HashMap::new();
+        let mut warnings = Vec::new();
+
+        let configured = overrides.iter().flat_map(|(action, keys)| keys.iter().map(move |&key| (key, *action)));
+        let defaults = ACTIONS
+            .into_iter()
+            .filter(|action| !overrides.iter().any(|(overridden, _)| overridden == action))
+            .flat_map(|action| action.default_keys().iter().map(move |&c| (KeyCode::Char(c), action)));
+
+        for (key, action) in configured.clone() {
+            if is_reserved(key) {
+                warnings.push(format!("Key '{}' for {} hides a built-in command", key_name(key), action.name()));
+            }
+            match map.get(&key) {
+                Some(&other) if other != action => warnings.push(format!(
+                    "Key '{}' is bound to both {} and {}; using {}",
+                    key_name(key),
+                    other.name(),
+                    action.name(),
+                    other.name()
+                )),
+                _ => {
+                    map.insert(key, action);
+                }
+            }
+        }
+        for (key, action) in defaults {
+            match map.get(&key) {
+                Some(&other) => warnings.push(format!(
+                    "Key '{}' for {} also runs {} by default; {} loses it",
+                    key_name(key),
+                    other.name(),
+                    action.name(),
+                    action.name()
+                )),
+                None => {
+                    map.insert(key, action);
+                }
+            }
+        }
+
+        (KeyMap(map), warnings)
+    }
+
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.0.get(&key).copied()
+    }
+
+    /// A key that runs `action`, for hints in the UI; `None` when it has none.
+    pub fn key_for(&self, action: Action) -> Option<KeyCode> {
+        // Map order is random, so pick the same key every time
+        self.0.iter().filter(|&(_, &bound)| bound == action).map(|(&key, _)| key).min_by_key(|&key| key_name(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_specs_round_trip() {
+        for spec in ["x", "X", "/", "space", "enter", "f5", "pagedown"] {
+            let code = parse_key(spec).unwrap();
+            assert_eq!(key_name(code), spec);
+        }
+        assert_eq!(parse_key("Enter"), Some(KeyCode::Enter));
+        assert_eq!(parse_key("f13"), None);
+        assert_eq!(parse_key("bogus"), None);
+        assert_eq!(parse_key(""), None);
+    }
+
+    #[test]
+    fn test_overrides_replace_defaults_and_conflicts_warn() {
+        let (keys, warnings) = KeyMap::build(&[]);
+        assert!(warnings.is_empty());
+        assert_eq!(keys.action(KeyCode::Char('D')), Some(Action::Delete));
+        assert_eq!(keys.action(KeyCode::Char('3')), Some(Action::Sort(SortKey::Date)));
+
+        let (keys, warnings) = KeyMap::build(&[(Action::Create, vec![KeyCode::Char('n'), KeyCode::F(2)])]);
+        assert!(warnings.is_empty());
+        assert_eq!(keys.action(KeyCode::Char('n')), Some(Action::Create));
+        assert_eq!(keys.action(KeyCode::F(2)), Some(Action::Create));
+        assert_eq!(keys.action(KeyCode::Char('c')), None);
+
+        // Taking another action's default key
+        let (keys, warnings) = KeyMap::build(&[(Action::Quit, vec![KeyCode::Char('d')])]);
+        assert_eq!(keys.action(KeyCode::Char('d')), Some(Action::Quit));
+        assert_eq!(keys.action(KeyCode::Char('D')), Some(Action::Delete));
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+
+        // Two configured actions on one key, and a built-in key
+        let (keys, warnings) = KeyMap::build(&[
+            (Action::Apply, vec![KeyCode::Char('h')]),
+            (Action::Status, vec![KeyCode::Char('h'), KeyCode::Char('k')]),
+        ]);
+        assert_eq!(keys.action(KeyCode::Char('h')), Some(Action::Apply));
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+
+        // Special keys the main screen handles count as built-in too
+        let (_, warnings) = KeyMap::build(&[(Action::Quit, vec![KeyCode::Enter, KeyCode::F(10)])]);
+        assert_eq!(warnings, ["Key 'enter' for quit hides a built-in command"]);
+    }
+}
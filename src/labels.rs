@@ -0,0 +1,127 @@
+use crate::data::Snapshot;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Free-text labels the user attached to snapshots, kept in a file of
+/// their own rather than in snapper's userdata, so labelling needs no root.
+#[derive(Debug, Default)]
+pub struct Labels {
+    /// Label per snapshot number, per config name.
+    entries: BTreeMap<String, BTreeMap<u32, String>>,
+    /// Where changes are saved; `None` keeps them in memory only.
+    file: Option<PathBuf>,
+}
+
+/// `~/.local/share/snapper-tui/labels.json`, honouring `$XDG_DATA_HOME`.
+/// Another machine's snapshots have their own file, since the numbers
+/// don't mean the same there.
+pub fn path(remote: Option<&str>) -> Option<PathBuf> {
+    let name = match remote {
+        Some(destination) => format!("labels-{}.json", destination.replace(['/', '\\'], "_")),
+        None => String::from("labels.json"),
+    };
+    Some(crate::history::path()?.with_file_name(name))
+}
+
+impl Labels {
+    /// The labels saved in `file`, which is also where changes go. A file
+    /// that doesn't exist yet gives no labels.
+    pub fn load(file: PathBuf) -> Result<Labels> {
+        let entries = match fs::read_to_string(&file) {
+            Ok(text) => serde_json::from_str(&text).with_context(|| format!("{} is not valid", file.display()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Could not read {}", file.display())),
+        };
+        Ok(Labels { entries, file: Some(file) })
+    }
+
+    pub fn get(&self, config: &str, number: u32) -> Option<&str> {
+        self.entries.get(config)?.get(&number).map(String::as_str)
+    }
+
+    /// Labels snapshot `number` of `config`, or takes its label off when
+    /// `label` is blank, and saves the result.
+    pub fn set(&mut self, config: &str, number: u32, label: &str) -> Result<()> {
+        let label = label.trim();
+        if label.is_empty() {
+            if let Some(numbers) = self.entries.get_mut(config) {
+                numbers.remove(&number);
+                if numbers.is_empty() {
+                    self.entries.remove(config);
+                }
+            }
+        } else {
+            self.entries.entry(config.to_string()).or_default().insert(number, label.to_string());
+        }
+        self.save()
+    }
+
+    /// Drops the labels of snapshots that are gone, judged only for the
+    /// configs `snapshots` has any of so one that failed to list keeps its
+    /// labels. Saves only when something was dropped.
+    pub fn prune(&mut self, snapshots: &[Snapshot]) -> Result<()> {
+        let listed: HashSet<(&str, u32)> = snapshots.iter().map(|s| (s.config.as_str(), s.number)).collect();
+        let configs: HashSet<&str> = listed.iter().map(|&(config, _)| config).collect();
+        let mut changed = false;
+        self.entries.retain(|config, numbers| {
+            if configs.contains(config.as_str()) {
+                let before = numbers.len();
+                numbers.retain(|&number, _| listed.contains(&(config.as_str(), number)));
+                changed |= numbers.len() != before;
+            }
+            !numbers.is_empty()
+        });
+        if changed { self.save() } else { Ok(()) }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(file, serde_json::to_string_pretty(&self.entries)?).with_context(|| format!("Could not write {}", file.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(config: &str, number: u32) -> Snapshot {
+        Snapshot { config: config.to_string(), number, ..Default::default() }
+    }
+
+    #[test]
+    fn test_labels_persist_and_stale_ones_are_pruned() {
+        let file = std::env::temp_dir().join(format!("snapper-tui-labels-{}.json", std::process::id()));
+        let _ = fs::remove_file(&file);
+
+        let mut labels = Labels::load(file.clone()).unwrap();
+        labels.set("root", 4, "  before kernel 6.9 ").unwrap();
+        labels.set("root", 7, "known good").unwrap();
+        labels.set("home", 2, "photos").unwrap();
+        labels.set("root", 9, "typo").unwrap();
+        labels.set("root", 9, "").unwrap();
+
+        let mut labels = Labels::load(file.clone()).unwrap();
+        assert_eq!(labels.get("root", 4), Some("before kernel 6.9"));
+        assert_eq!(labels.get("root", 9), None);
+        assert_eq!(labels.get("home", 4), None);
+
+        // #7 was deleted; home wasn't listed, so its labels are kept
+        labels.prune(&[snap("root", 4), snap("root", 5)]).unwrap();
+        let labels = Labels::load(file.clone()).unwrap();
+        assert_eq!(labels.get("root", 4), Some("before kernel 6.9"));
+        assert_eq!(labels.get("root", 7), None);
+        assert_eq!(labels.get("home", 2), Some("photos"));
+
+        fs::write(&file, "not json").unwrap();
+        assert!(Labels::load(file.clone()).is_err());
+        let _ = fs::remove_file(&file);
+    }
+}
@@ -1,51 +1,157 @@
 mod app;
+mod clipboard;
+mod config;
 mod data;
+mod filter;
+mod history;
+mod keys;
+mod labels;
+mod tasks;
+mod theme;
 mod ui;
 
-use std::{io, thread, time::Duration};
-use std::sync::mpsc;
+use std::{io, process::Command, time::{Duration, Instant}};
 use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
-        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+        cursor::Show,
+        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
+    layout::Position,
     Terminal,
 };
-use crate::{app::{App, AsyncResult}, ui as app_ui}; // Renamed to avoid conflict
+use clap::{Arg, ArgAction};
+use crate::{app::{App, AppOptions, AsyncResult, FooterAction, Panel, Severity, IDLE_REDRAW}, data::ConfigFs, keys::Action, tasks::TaskError, ui as app_ui}; // Renamed to avoid conflict
+
+// `--help` and `--version` print and exit here, before the terminal is touched
+fn parse_args() -> AppOptions {
+    let matches = clap::Command::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Terminal UI for browsing and managing snapper snapshots")
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("NAME")
+                .help("Only show snapshots of this snapper config"),
+        )
+        .arg(
+            Arg::new("no-splash")
+                .long("no-splash")
+                .action(ArgAction::SetTrue)
+                .help("Skip the splash screen"),
+        )
+        .arg(
+            Arg::new("splash-duration")
+                .long("splash-duration")
+                .value_name("SECONDS")
+                .value_parser(parse_seconds)
+                .help("How long the splash screen stays up (default 2, 0 skips it)"),
+        )
+        .arg(
+            Arg::new("ssh")
+                .long("ssh")
+                .value_name("USER@HOST")
+                .help("Manage snapper on another machine, running every command there over ssh"),
+        )
+        .arg(
+            Arg::new("no-mouse")
+                .long("no-mouse")
+                .action(ArgAction::SetTrue)
+                .help("Leave mouse events to the terminal"),
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .action(ArgAction::SetTrue)
+                .help("Draw without colours; also set by a non-empty NO_COLOR"),
+        )
+        .get_matches();
+
+    AppOptions {
+        config: matches.get_one::<String>("config").cloned(),
+        no_splash: matches.get_flag("no-splash"),
+        splash_duration: matches.get_one::<Duration>("splash-duration").copied(),
+        no_mouse: matches.get_flag("no-mouse"),
+        ssh: matches.get_one::<String>("ssh").cloned(),
+        // https://no-color.org: present and not empty
+        no_color: matches.get_flag("no-color") || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+    }
+}
+
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    text.parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("'{}' is not a number of seconds", text))
+}
+
+/// Raw mode, the alternate screen and (optionally) mouse capture, undone
+/// on drop so an early return or a panic can't leave the terminal garbled.
+struct TerminalGuard {
+    mouse: bool,
+}
+
+impl TerminalGuard {
+    fn enter(mouse: bool) -> io::Result<TerminalGuard> {
+        enable_raw_mode()?;
+        // From here on a failure still restores what was already set up
+        let guard = TerminalGuard { mouse };
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        if mouse {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal(self.mouse);
+    }
+}
+
+// Safe to run more than once, e.g. from the panic hook and then the guard
+fn restore_terminal(mouse: bool) {
+    let _ = disable_raw_mode();
+    if mouse {
+        let _ = execute!(io::stdout(), DisableMouseCapture);
+    }
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
+    let options = parse_args();
+    let mouse = !options.no_mouse;
+
+    // Setup terminal; the guard and the panic hook both put it back
+    let guard = TerminalGuard::enter(mouse)?;
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal(mouse);
+        default_hook(info);
+    }));
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
     // Create app and run
-    let mut app = App::new();
+    let mut app = App::new(options);
     
     // Start initial load in a separate thread
-    let (tx, rx) = mpsc::channel();
-    app.rx = Some(rx);
-    thread::spawn(move || {
-        let res = crate::data::list_snapshots()
+    app.tasks.spawn(move |cancel| {
+        crate::data::list_snapshots(cancel)
             .map(AsyncResult::Snapshots)
-            .map_err(|e| e.to_string());
-        let _ = tx.send(res);
+            .map_err(TaskError::from)
     });
 
-    let res = run_app(&mut terminal, &mut app);
+    let res = run_app(&mut terminal, &mut app, mouse);
 
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    drop(guard);
+
+    // After the terminal is restored, so a password prompt can be answered
+    app.unmount_on_exit();
 
     if let Err(err) = res {
         println!("{:?}", err);
@@ -54,183 +160,545 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+/// Hands the terminal to `argv` until it exits: the TUI's screen is left
+/// and raw mode dropped so the program sees a normal terminal, then both
+/// come back and the next draw repaints everything.
+fn suspend_and_run<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mouse: bool, argv: &[String]) -> io::Result<std::process::ExitStatus> {
+    let (program, args) = argv.split_first().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no command to run"))?;
+    restore_terminal(mouse);
+    let status = Command::new(program).args(args).status();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    if mouse {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    terminal.hide_cursor()?;
+    terminal.clear()?;
+    status
+}
+
+fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App, mouse: bool) -> io::Result<()> {
+    // Only redraw when something changed, or at an idle pace for the clocks
+    let mut dirty = true;
+    let mut last_draw = Instant::now();
     loop {
-        terminal.draw(|f| app_ui::draw(f, app))?;
-
-        // Check for threaded results
-        if let Some(rx) = &app.rx {
-            if let Ok(result) = rx.try_recv() {
-                app.loading = false;
-                app.rx = None; // Stop checking
-                match result {
-                    Ok(AsyncResult::Snapshots(snapshots)) => {
-                        app.snapshots = snapshots;
-                        app.message = format!("✅ Loaded {} snapshots.", app.snapshots.len());
-                        if !app.snapshots.is_empty() {
-                            app.table_state.select(Some(0));
-                        }
+        if dirty || app.is_animating() || last_draw.elapsed() >= IDLE_REDRAW {
+            terminal.draw(|f| app_ui::draw(f, app))?;
+            last_draw = Instant::now();
+            dirty = false;
+        }
+
+        // Drain every result that arrived since the last tick
+        for result in app.tasks.drain() {
+            dirty = true;
+            match result {
+                Ok(AsyncResult::Snapshots(snapshots)) => {
+                    app.load_snapshots(snapshots);
+                    if app.snapshots.is_empty() {
+                        app.toast(Severity::Info, "ℹ️ snapper lists no snapshots.");
+                    } else {
+                        app.toast(Severity::Success, format!("✅ Loaded {} snapshots.", app.snapshots.len()));
                     }
-                    Ok(AsyncResult::Create(name)) => {
-                        app.message = format!("✅ Snapshot created: {}", name);
-                        // Trigger refresh
-                        app.loading = true;
-                        app.loading_message = String::from("Refreshing...");
-                        let (tx, rx) = mpsc::channel();
-                        app.rx = Some(rx);
-                        thread::spawn(move || {
-                            let res = crate::data::list_snapshots()
-                                .map(AsyncResult::Snapshots)
-                                .map_err(|e| e.to_string());
-                            let _ = tx.send(res);
-                        });
-                    }
-                    Ok(AsyncResult::Delete { success, fail }) => {
-                        app.handle_delete_result(success, fail);
-                        // Trigger refresh
-                        app.loading = true;
-                        app.loading_message = String::from("Refreshing...");
-                        let (tx, rx) = mpsc::channel();
-                        app.rx = Some(rx);
-                        thread::spawn(move || {
-                            let res = crate::data::list_snapshots()
-                                .map(AsyncResult::Snapshots)
-                                .map_err(|e| e.to_string());
-                            let _ = tx.send(res);
-                        });
-                    }
-                    Ok(AsyncResult::Apply(number)) => {
-                        app.message = format!("✅ Snapshot {} applied. Reboot to take effect.", number);
-                    }
-                    Ok(AsyncResult::Status(status)) => {
-                        app.status_text = status;
-                        app.message = String::from("✅ Status loaded.");
-                        app.status_scroll = 0;
-                    }
-                    Err(e) => {
-                        app.message = format!("❌ Error: {}", e);
+                    app.request_config_fs();
+                    app.request_usage();
+                    app.request_boot_lineage();
+                }
+                Ok(AsyncResult::AutoRefresh(snapshots)) => {
+                    app.apply_auto_refresh(snapshots);
+                    app.request_config_fs();
+                    app.request_usage();
+                    app.request_boot_lineage();
+                }
+                Ok(AsyncResult::Modify(number)) => {
+                    app.toast(Severity::Success, format!("✏️ Snapshot {} updated.", number));
+                    app.request_refresh();
+                }
+                Ok(AsyncResult::Usage(usage)) => {
+                    app.fs_usage = usage;
+                }
+                Ok(AsyncResult::Lineage(lineage)) => {
+                    app.boot_lineage = lineage;
+                }
+                Ok(AsyncResult::SnapperVersion(version)) => {
+                    app.snapper_version = Some(version);
+                }
+                Ok(AsyncResult::Cleanup { config, algorithm, removed }) => {
+                    app.toast(Severity::Success, format!("🧹 {} cleanup of {} removed {} snapshot(s).", algorithm, config, removed));
+                    app.request_refresh();
+                }
+                Ok(AsyncResult::QuotaSetUp(config)) => {
+                    app.toast(Severity::Success, format!("💾 Quota set up for {}; used space shows once btrfs has counted it.", config));
+                    app.request_refresh();
+                }
+                Ok(AsyncResult::Mounted { config, number, root, entries }) => {
+                    app.toast(Severity::Info, format!("📁 Snapshot {} mounted at {}", number, root.display()));
+                    app.browse = Some(app::Browse {
+                        config,
+                        number,
+                        dir: root.clone(),
+                        root,
+                        entries,
+                        cursor: 0,
+                        file: None,
+                        file_scroll: 0,
+                    });
+                }
+                Ok(AsyncResult::Listing { dir, entries }) => {
+                    app.set_browse_listing(dir, entries);
+                }
+                Ok(AsyncResult::FileView { path, text }) => {
+                    if let Some(browse) = app.browse.as_mut() {
+                        browse.file = Some((path, text));
+                        browse.file_scroll = 0;
                     }
                 }
+                Ok(AsyncResult::Unmounted(number)) => {
+                    app.toast(Severity::Info, format!("📁 Snapshot {} unmounted.", number));
+                }
+                Ok(AsyncResult::ConfigInfo { name, values }) => {
+                    app.config_info.insert(name, Some(values));
+                }
+                Ok(AsyncResult::ConfigFs { name, fs }) => {
+                    let on_btrfs = fs.as_ref().is_none_or(ConfigFs::is_btrfs);
+                    app.config_fs.insert(name, fs);
+                    if !on_btrfs {
+                        // Drop what was fetched while btrfs was assumed
+                        app.request_usage();
+                        app.request_boot_lineage();
+                    }
+                }
+                Ok(AsyncResult::Create(snapshot, number)) => {
+                    app.handle_create_result(&snapshot, number);
+                    // Trigger refresh
+                    app.request_refresh();
+                }
+                Ok(AsyncResult::DeleteProgress { done, total }) => {
+                    app.loading_progress = Some((done, total));
+                }
+                Ok(AsyncResult::Delete { succeeded, failed }) => {
+                    app.handle_delete_result(&succeeded, &failed);
+                    // Trigger refresh
+                    app.request_refresh();
+                }
+                Ok(AsyncResult::Apply { number }) => {
+                    app.toast(Severity::Success, app::rollback_message(number));
+                }
+                Ok(AsyncResult::StatusLines { range, lines }) => {
+                    app.append_status(range, lines);
+                }
+                Ok(AsyncResult::Status { range, text }) => {
+                    app.set_status(range, text);
+                    app.message = String::from("✅ Status loaded.");
+                }
+                Ok(AsyncResult::Undo { success, fail }) => {
+                    app.handle_undo_result(success, fail);
+                }
+                Ok(AsyncResult::Diff { path, text }) => {
+                    app.message = format!("✅ Diff loaded for {}.", path);
+                    app.open_diff(path, text);
+                }
+                Err(e @ (TaskError::MissingBinary(_) | TaskError::Unreachable(_))) => {
+                    app.fatal_error = Some(e.to_string());
+                }
+                Err(e) => {
+                    app.toast(Severity::Error, format!("❌ Error: {}", e));
+                }
             }
         }
+        app.loading = app.tasks.is_busy();
+        if !app.loading {
+            app.loading_progress = None;
+        }
 
         // Handle events
         if event::poll(Duration::from_millis(100))? {
+            dirty = true;
             match event::read()? {
                 Event::Key(key) => {
-                    // Splash Screen Handling
+                    // Any key dismisses the splash and is then handled as usual,
+                    // except Esc, which would otherwise cancel the initial load
                     if app.show_splash {
-                        app.show_splash = false; // Dismiss on any key
+                        app.dismiss_splash();
+                        if key.code == KeyCode::Esc {
+                            continue;
+                        }
+                    }
+
+                    // Nothing else works without snapper; only offer to exit
+                    if app.fatal_error.is_some() {
+                        if matches!(key.code, KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc | KeyCode::Enter) {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    // Ctrl-C always quits, even mid-operation
+                    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                        break;
+                    }
+
+                    // A second q (or Enter) confirms; it sits above the loading overlay
+                    if app.show_quit_popup && matches!(key.code, KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Enter) {
+                        break;
+                    }
+
+                    if key.code == KeyCode::Esc {
+                        // Loading overlay: Esc aborts the running snapper process,
+                        // unless it's answering the quit popup
+                        if app.loading && !app.show_quit_popup {
+                            app.cancel_operation();
+                            continue;
+                        }
+                        // Otherwise it backs out of whichever modal is on top
+                        if app.close_modal() {
+                            continue;
+                        }
+                    }
+                    if app.show_quit_popup {
+                        continue;
+                    }
+
+                    // Help overlay: arrows scroll, any other key dismisses
+                    if app.show_help_popup {
+                        match key.code {
+                            KeyCode::Up => app.scroll_help(true),
+                            KeyCode::Down => app.scroll_help(false),
+                            _ => app.show_help_popup = false,
+                        }
+                        continue;
+                    }
+
+                    if app.show_about_popup {
+                        app.show_about_popup = false;
+                        continue;
+                    }
+
+                    // History overlay: same keys as help
+                    if app.show_history_popup {
+                        match key.code {
+                            KeyCode::Up => app.scroll_history(true),
+                            KeyCode::Down => app.scroll_history(false),
+                            _ => app.show_history_popup = false,
+                        }
                         continue;
                     }
 
                     // Popup Handling
+                    if app.show_theme_popup {
+                        match key.code {
+                            KeyCode::Up => app.move_theme_cursor(true),
+                            KeyCode::Down => app.move_theme_cursor(false),
+                            KeyCode::Enter => app.apply_theme_cursor(),
+                            KeyCode::Char('q') => app.show_theme_popup = false,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.quota_popup.is_some() {
+                        match key.code {
+                            KeyCode::Enter => app.start_setup_quota(),
+                            KeyCode::Char('q') => app.quota_popup = None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.show_cleanup_popup {
+                        match key.code {
+                            KeyCode::Up => app.move_cleanup_cursor(true),
+                            KeyCode::Down => app.move_cleanup_cursor(false),
+                            KeyCode::Enter => app.start_cleanup(),
+                            KeyCode::Char('q') => app.show_cleanup_popup = false,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.show_report_popup {
+                        match key.code {
+                            KeyCode::Enter if !app.report_input.is_empty() => app.save_report(),
+                            KeyCode::Char(c) => {
+                                app.report_input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.report_input.pop();
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.show_diff_popup {
+                        match key.code {
+                            KeyCode::Char('w') | KeyCode::Char('W') => app.open_report_popup(true),
+                            KeyCode::Char('q') => {
+                                app.show_diff_popup = false;
+                            }
+                            KeyCode::Up => app.scroll_diff(true, 1),
+                            KeyCode::Down => app.scroll_diff(false, 1),
+                            KeyCode::PageUp => app.scroll_diff(true, 10),
+                            KeyCode::PageDown => app.scroll_diff(false, 10),
+                            KeyCode::Left => app.scroll_diff_sideways(true),
+                            KeyCode::Right => app.scroll_diff_sideways(false),
+                            KeyCode::Char('u') | KeyCode::Char('U') => {
+                                let path = app.diff_path.clone();
+                                app.open_undo_popup(vec![path]);
+                                if app.show_undo_popup {
+                                    app.show_diff_popup = false;
+                                }
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.show_undo_popup {
+                        match key.code {
+                            KeyCode::Enter => {
+                                if let Some(range) = app.status_range.clone() {
+                                    let targets = app.undo_targets.clone();
+                                    app.loading = true;
+                                    app.loading_message = format!("Reverting {} file(s)...", targets.len());
+
+                                    let privilege = app.config.privilege;
+                                    app.tasks.spawn(move |cancel| {
+                                        let mut success_count = 0;
+                                        let mut error_count = 0;
+
+                                        // One call per file so failures are reported individually
+                                        for path in targets {
+                                            if cancel.is_cancelled() {
+                                                break;
+                                            }
+                                            match crate::data::undo_change(&range.config, range.pre, range.post, &[path], privilege, cancel) {
+                                                Ok(_) => success_count += 1,
+                                                Err(_) => error_count += 1,
+                                            }
+                                        }
+
+                                        Ok(AsyncResult::Undo { success: success_count, fail: error_count })
+                                    });
+                                }
+                                app.show_undo_popup = false;
+                            }
+                            KeyCode::Char('q') => {
+                                app.show_undo_popup = false;
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
                     if app.show_delete_popup {
                         match key.code {
+                            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                app.delete_acknowledged = true;
+                            }
+                            KeyCode::Enter if app.delete_needs_acknowledgement() => {}
                             KeyCode::Enter => {
-                                let targets = app.get_targets_for_delete();
+                                // Exactly what the popup listed, each in its own config
+                                let targets: Vec<(String, u32)> =
+                                    app.delete_target_snapshots().iter().map(|s| (s.config.clone(), s.number)).collect();
                                 if !targets.is_empty() {
                                     app.loading = true;
                                     app.loading_message = format!("Deleting {} snapshot(s)...", targets.len());
                                     
-                                    let (tx, rx) = mpsc::channel();
-                                    app.rx = Some(rx);
-                                    
-                                    thread::spawn(move || {
-                                        let mut success_count = 0;
-                                        let mut error_count = 0;
+                                    app.loading_progress = Some((0, targets.len()));
+
+                                    let privilege = app.config.privilege;
+                                    let usage_subvolume = app.delete_usage_subvolume();
+                                    app.tasks.spawn_streaming(true, move |cancel, progress| {
+                                        let mut succeeded = Vec::new();
+                                        let mut failed = Vec::new();
+                                        let total = targets.len();
                                         
-                                        for number in targets {
-                                            match crate::data::delete_snapshot(number) {
-                                                Ok(_) => success_count += 1,
-                                                Err(_) => error_count += 1,
+                                        for (done, (config, number)) in targets.into_iter().enumerate() {
+                                            if cancel.is_cancelled() {
+                                                break;
                                             }
+                                            match crate::data::delete_snapshot(&config, number, privilege, cancel) {
+                                                Ok(_) => {
+                                                    succeeded.push(number);
+                                                    // A failed reading just leaves the bar where it was
+                                                    if let Some(subvolume) = &usage_subvolume
+                                                        && let Ok(usage) = crate::data::get_btrfs_usage(subvolume, privilege, cancel)
+                                                    {
+                                                        progress.send(AsyncResult::Usage(Some(usage)));
+                                                    }
+                                                }
+                                                Err(e) => failed.push((number, format!("{:#}", e))),
+                                            }
+                                            progress.send(AsyncResult::DeleteProgress { done: done + 1, total });
                                         }
                                         
-                                        let res = Ok(AsyncResult::Delete { success: success_count, fail: error_count });
-                                        let _ = tx.send(res);
+                                        Ok(AsyncResult::Delete { succeeded, failed })
                                     });
                                 }
                                 app.show_delete_popup = false;
                             }
-                            KeyCode::Esc | KeyCode::Char('q') => {
+                            KeyCode::Char('q') => {
                                 app.show_delete_popup = false;
                             }
+                            KeyCode::Up | KeyCode::Down => app.scroll_delete(key.code == KeyCode::Up),
                             _ => {}
                         }
                         continue;
                     }
                     if app.show_apply_popup {
                         match key.code {
-                            KeyCode::Enter => {
-                                if let Some(number) = app.get_target_for_apply() {
+                            // The filesystem lookup may have answered since the popup opened
+                            KeyCode::Enter if let Some(reason) = app.rollback_refusal() => {
+                                app.message = reason;
+                                app.show_apply_popup = false;
+                            }
+                            KeyCode::Enter if app.apply_confirmed() => {
+                                if let Some(snap) = app.get_selected_snapshot() {
+                                    let (number, config) = (snap.number, snap.config.clone());
                                     app.loading = true;
                                     app.loading_message = format!("Applying snapshot {}...", number);
                                     
-                                    let (tx, rx) = mpsc::channel();
-                                    app.rx = Some(rx);
-                                    
-                                    thread::spawn(move || {
-                                        let res = crate::data::rollback_snapshot(number)
-                                            .map(|_| AsyncResult::Apply(number))
-                                            .map_err(|e| e.to_string());
-                                        let _ = tx.send(res);
+                                    let privilege = app.config.privilege;
+                                    app.tasks.spawn(move |cancel| {
+                                        crate::data::rollback_snapshot(&config, number, privilege, cancel)
+                                            .map(|_| AsyncResult::Apply { number })
+                                            .map_err(TaskError::from)
                                     });
                                 }
                                 app.show_apply_popup = false;
                             }
-                            KeyCode::Esc | KeyCode::Char('q') => {
+                            KeyCode::Char('q') => {
                                 app.show_apply_popup = false;
                             }
+                            KeyCode::Char(c) if c.is_ascii_digit() && app.config.confirm_apply_by_number => {
+                                app.apply_confirm_input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.apply_confirm_input.pop();
+                            }
                             _ => {}
                         }
                         continue;
                     }
-                    if app.show_create_popup {
+                    if app.show_modify_popup {
                         match key.code {
-                            KeyCode::Enter => {
-                                if !app.create_input.is_empty() {
+                            KeyCode::Enter => match app.modify_changes() {
+                                Ok(Some(changes)) => {
                                     app.loading = true;
-                                    app.loading_message = String::from("Creating snapshot...");
-                                    
-                                    let input = app.create_input.clone();
-                                    let (tx, rx) = mpsc::channel();
-                                    app.rx = Some(rx);
-                                    
-                                    thread::spawn(move || {
-                                        let res = crate::data::create_snapshot(&input)
-                                            .map(|_| AsyncResult::Create(input))
-                                            .map_err(|e| e.to_string());
-                                        let _ = tx.send(res);
+                                    app.loading_message = format!("Updating snapshot {}...", changes.number);
+                                    app.show_modify_popup = false;
+
+                                    let privilege = app.config.privilege;
+                                    app.tasks.spawn(move |cancel| {
+                                        crate::data::modify_snapshot(
+                                            &changes.config,
+                                            changes.number,
+                                            changes.description.as_deref(),
+                                            changes.cleanup.as_deref(),
+                                            changes.userdata.as_ref(),
+                                            privilege,
+                                            cancel,
+                                        )
+                                        .map(|_| AsyncResult::Modify(changes.number))
+                                        .map_err(TaskError::from)
                                     });
-                                    app.create_input.clear();
-                                    app.show_create_popup = false;
                                 }
+                                Ok(None) => {
+                                    app.message = String::from("ℹ️ Nothing changed.");
+                                    app.show_modify_popup = false;
+                                }
+                                Err(e) => {
+                                    app.message = format!("❌ Error: {}", e);
+                                }
+                            },
+                            KeyCode::Tab | KeyCode::Down => app.modify_move_field(false),
+                            KeyCode::BackTab | KeyCode::Up => app.modify_move_field(true),
+                            KeyCode::Char(c) => {
+                                app.modify_input().push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.modify_input().pop();
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.show_goto_popup {
+                        match key.code {
+                            KeyCode::Enter => app.goto_snapshot(),
+                            KeyCode::Char(c) if c.is_ascii_digit() => {
+                                app.goto_input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.goto_input.pop();
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.show_label_popup {
+                        match key.code {
+                            KeyCode::Enter => app.save_label(),
+                            KeyCode::Char(c) => {
+                                app.label_input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.label_input.pop();
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.show_export_popup {
+                        match key.code {
+                            KeyCode::Enter if !app.export_input.is_empty() => {
+                                app.export_filtered();
+                                app.show_export_popup = false;
                             }
-                            KeyCode::Esc => {
-                                app.show_create_popup = false;
-                                app.create_input.clear();
+                            KeyCode::Char(c) => {
+                                app.export_input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.export_input.pop();
                             }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.show_create_popup {
+                        match key.code {
+                            KeyCode::Enter => match app.new_snapshot() {
+                                Ok(snapshot) => app.start_create(snapshot),
+                                Err(e) => {
+                                    app.message = format!("❌ Error: {}", e);
+                                }
+                            },
+                            KeyCode::Tab | KeyCode::Down => app.create_move_field(false),
+                            KeyCode::BackTab | KeyCode::Up => app.create_move_field(true),
                             KeyCode::Char(c) => {
-                                app.create_input.push(c);
+                                app.create_input().push(c);
                             }
                             KeyCode::Backspace => {
-                                app.create_input.pop();
+                                app.create_input().pop();
                             }
                             _ => {}
                         }
                         continue;
                     }
+                    // Browse mode keeps the arrows until it is closed
+                    if app.browse.is_some() && app.keymap.action(key.code) != Some(Action::Quit) {
+                        match key.code {
+                            KeyCode::Up => app.browse_move(true),
+                            KeyCode::Down => app.browse_move(false),
+                            KeyCode::Enter | KeyCode::Right => app.browse_open(),
+                            KeyCode::Backspace | KeyCode::Left => app.browse_back(),
+                            KeyCode::Char('b') | KeyCode::Char('B') => app.close_browse(),
+                            _ => {}
+                        }
+                        continue;
+                    }
                     if app.filtering {
+                        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
                         match key.code {
+                            KeyCode::Char('s') if ctrl => app.toggle_filter_case_sensitive(),
+                            KeyCode::Char('w') if ctrl => app.toggle_filter_whole_word(),
                             KeyCode::Enter => {
                                 app.filtering = false;
                             }
-                            KeyCode::Esc => {
-                                app.filtering = false;
-                                app.filter_input.clear();
-                                app.table_state.select(Some(0));
-                            }
                             KeyCode::Char(c) => {
                                 app.filter_input.push(c);
                                 app.table_state.select(Some(0));
@@ -244,199 +712,185 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut A
                         continue;
                     }
 
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Char('Q') => return Ok(()),
-                        KeyCode::Char('c') | KeyCode::Char('C') => {
-                            app.show_create_popup = true;
+                    if key.modifiers.contains(KeyModifiers::ALT) {
+                        if let KeyCode::Char(c @ '1'..='3') = key.code {
+                            app.toggle_hidden_type(c as usize - '1' as usize);
                         }
-                        KeyCode::Char('/') => {
-                            app.filtering = true;
-                        }
-                        KeyCode::Char('r') | KeyCode::Char('R') => {
-                            app.loading = true;
-                            app.loading_message = String::from("Refreshing...");
-                            app.snapshots.clear();
-                            
-                            let (tx, rx) = mpsc::channel();
-                            app.rx = Some(rx);
-                            thread::spawn(move || {
-                                let res = crate::data::list_snapshots()
-                                    .map(AsyncResult::Snapshots)
-                                    .map_err(|e| e.to_string());
-                                let _ = tx.send(res);
-                            });
+                        continue;
+                    }
+
+                    if key.modifiers.contains(KeyModifiers::CONTROL) {
+                        if let KeyCode::Char('a') = key.code {
+                            app.select_all_filtered();
                         }
-                        KeyCode::Char('a') | KeyCode::Char('A') => {
-                            if app.get_selected_count() > 0 {
-                                app.message = "❌ Error: Cannot apply with multi-selection active. Clear selections first (select with space to deselect).".to_string();
-                            } else {
-                                app.show_apply_popup = true;
+                        continue;
+                    }
+
+                    if let Some(action) = app.keymap.action(key.code) {
+                        match action {
+                            Action::Quit => {
+                                if !app.quit_needs_confirm() {
+                                    break;
+                                }
+                                app.show_quit_popup = true;
                             }
+                            Action::Create => app.open_create_popup(),
+                            Action::Delete => app.open_delete_popup(),
+                            Action::Apply => app.open_apply_popup(),
+                            Action::Filter => {
+                                app.exit_visual_mode(); // The range is tied to the current rows
+                                app.filtering = true;
+                            }
+                            Action::Status => app.status_or_compare(),
+                            Action::Refresh => {
+                                app.request_refresh();
+                                app.snapshots.clear();
+                            }
+                            Action::Sort(sort_key) => app.set_sort_key(sort_key),
                         }
-                        KeyCode::Down => {
-                            app.next();
-                            app.get_status_selected_snapshot(); // Auto-show status
-                        }
-                        KeyCode::Up => {
-                            app.previous();
-                            app.get_status_selected_snapshot(); // Auto-show status
+                        continue;
+                    }
+
+                    match key.code {
+                        KeyCode::Char('C') => {
+                            app.toggle_config_info();
                         }
-                        KeyCode::Char('d') | KeyCode::Char('D') => app.show_delete_popup = true,
-                        KeyCode::Char('s') | KeyCode::Char('S') => {
-                            if app.get_selected_count() > 0 {
-                                app.message = "❌ Error: Cannot get status with multi-selection active. Clear selections first.".to_string();
-                            } else {
-                                if let Some(snap) = app.get_selected_snapshot().cloned() {
-                                    app.loading = true;
-                                    app.loading_message = format!("Fetching status for {}...", snap.number);
-                                    let (tx, rx) = mpsc::channel();
-                                    app.rx = Some(rx);
-                                    thread::spawn(move || {
-                                        let res = crate::data::get_snapshot_status(&snap)
-                                            .map(AsyncResult::Status)
-                                            .map_err(|e| e.to_string());
-                                        let _ = tx.send(res);
-                                    });
-                                }
+                        KeyCode::Char('?') => app.toggle_help(),
+                        KeyCode::Char('l') | KeyCode::Char('L') => app.toggle_history(),
+                        KeyCode::Char('o') | KeyCode::Char('O') => app.open_about(),
+                        KeyCode::Char('t') => app.open_theme_popup(),
+                        KeyCode::Char('T') => app.toggle_relative_dates(),
+                        KeyCode::Char('z') => app.toggle_dense(),
+                        KeyCode::Char('>') => app.create_matching_post(),
+                        KeyCode::Char('e') | KeyCode::Char('E') => app.open_export_popup(),
+                        KeyCode::Char(':') | KeyCode::Char('#') => app.open_goto_popup(),
+                        KeyCode::Char('w') | KeyCode::Char('W') => app.open_report_popup(false),
+                        KeyCode::Char('g') | KeyCode::Char('G') => app.toggle_grouping(),
+                        KeyCode::Char('m') | KeyCode::Char('M') => app.open_modify_popup(),
+                        KeyCode::Char('k') | KeyCode::Char('K') => app.open_cleanup_popup(),
+                        KeyCode::Char('y') | KeyCode::Char('Y') => app.copy_to_clipboard(),
+                        KeyCode::Char('b') | KeyCode::Char('B') => app.open_browse(),
+                        KeyCode::Char('j') => {
+                            if let Some(argv) = app.external_browse_command() {
+                                app.message = match suspend_and_run(terminal, mouse, &argv) {
+                                    Ok(status) if status.success() => format!("📂 Back from {}.", argv[0]),
+                                    Ok(status) => format!("❌ {} exited with {}.", argv[0], status),
+                                    Err(e) => format!("❌ Could not run {}: {}", argv[0], e),
+                                };
                             }
                         }
+                        KeyCode::Right if app.focused_panel == Panel::Status => app.scroll_status_sideways(false),
+                        KeyCode::Left if app.focused_panel == Panel::Status => app.scroll_status_sideways(true),
+                        KeyCode::Right => app.set_pair_expanded(true),
+                        KeyCode::Left => app.set_pair_expanded(false),
+                        KeyCode::Char('f') | KeyCode::Char('F') => app.toggle_status_wrap(),
+                        KeyCode::Char('N') => app.quick_create(),
+                        KeyCode::Char('p') | KeyCode::Char('P') => app.open_label_popup(),
+                        KeyCode::Char('H') => app.open_orphan_cleanup(),
+                        KeyCode::Char('J') => app.open_quota_popup(),
+                        KeyCode::Down => app.move_in_focused(false, false),
+                        KeyCode::Up => app.move_in_focused(true, false),
+                        KeyCode::PageDown => app.move_in_focused(false, true),
+                        KeyCode::PageUp => app.move_in_focused(true, true),
+                        KeyCode::Home => app.select_first(),
+                        KeyCode::End => app.select_last(),
+                        KeyCode::Tab => app.cycle_focus(false),
+                        KeyCode::BackTab => app.cycle_focus(true),
                         KeyCode::Char(' ') => app.toggle_selection(),
-                        // Sorting keybinds
-                        KeyCode::Char('1') => app.set_sort_key(crate::app::SortKey::Number),
-                        KeyCode::Char('2') => app.set_sort_key(crate::app::SortKey::Type),
-                        KeyCode::Char('3') => app.set_sort_key(crate::app::SortKey::Date),
-                        KeyCode::Char('4') => app.set_sort_key(crate::app::SortKey::User),
-                        KeyCode::Char('5') => app.set_sort_key(crate::app::SortKey::UsedSpace),
+                        KeyCode::Char('i') | KeyCode::Char('I') => app.invert_selection(),
+                        KeyCode::Esc if app.select_anchor.is_some() => app.exit_visual_mode(),
+                        KeyCode::Esc if app.status_streaming() => app.cancel_status(),
+                        KeyCode::Esc => app.clear_selections(),
+                        KeyCode::Char('v') | KeyCode::Char('V') => app.toggle_visual_mode(),
+                        // Status file list / diff viewer
+                        KeyCode::Char(']') => app.status_next_file(),
+                        KeyCode::Char('[') => app.status_previous_file(),
+                        KeyCode::Char('x') | KeyCode::Char('X') => app.toggle_status_mark(),
+                        KeyCode::Char('u') | KeyCode::Char('U') => {
+                            let targets = app.get_undo_targets();
+                            app.open_undo_popup(targets);
+                        }
+                        KeyCode::Enter => {
+                            if let (Some(range), Some(path)) = (app.status_range.clone(), app.get_status_cursor_path()) {
+                                app.loading = true;
+                                app.loading_message = format!("Fetching diff for {}...", path);
+                                let privilege = app.config.privilege;
+                                app.tasks.spawn(move |cancel| {
+                                    crate::data::get_snapshot_diff(&range.config, range.pre, range.post, &path, privilege, cancel)
+                                        .map(|text| AsyncResult::Diff { path, text })
+                                        .map_err(TaskError::from)
+                                });
+                            }
+                        }
                         _ => {}
                     }
                 }
                 Event::Mouse(mouse) => {
                     match mouse.kind {
                         event::MouseEventKind::ScrollDown | event::MouseEventKind::ScrollUp => {
-                            let term_size = terminal.size()?;
                             let is_scroll_up = matches!(mouse.kind, event::MouseEventKind::ScrollUp);
-                            
-                            // Calculate layout boundaries
-                            // Calculate layout boundaries
-                            // Layout: TopGap(1) + Header(5) + Gap(1) + Main + Gap(1) + Footer(3) + BottomGap(1)
-                            let header_offset = 7; // 1 + 5 + 1
-                            let footer_height = 3;
-                            let bottom_gap = 1;
-                            let main_area_start = header_offset;
-                            let main_area_end = term_size.height.saturating_sub(footer_height + bottom_gap + 1); // +1 for the gap above footer
-                            
-                            // Check if mouse is in main area
-                            if mouse.row >= main_area_start && mouse.row < main_area_end {
-                                // Main area is split 50/50 horizontally
-                                let half_width = term_size.width / 2;
-                                
-                                // Right panel (Details + Status)
-                                if mouse.column >= half_width {
-                                    // Right panel is split vertically: 40% Details, 60% Status
-                                    let right_panel_height = main_area_end - main_area_start;
-                                    let details_height = (right_panel_height * 40) / 100;
-                                    let details_end_row = main_area_start + details_height;
-                                    
-                                    if mouse.row < details_end_row {
-                                        // Mouse is in Details pane
-                                        app.scroll_details(is_scroll_up);
-                                    } else {
-                                        // Mouse is in Status pane
-                                        app.scroll_status(is_scroll_up);
-                                    }
+                            if app.show_help_popup {
+                                app.scroll_help(is_scroll_up);
+                                continue;
+                            }
+                            if app.show_history_popup {
+                                app.scroll_history(is_scroll_up);
+                                continue;
+                            }
+                            if app.show_diff_popup {
+                                app.scroll_diff(is_scroll_up, 3);
+                                continue;
+                            }
+
+                            let at = Position::new(mouse.column, mouse.row);
+                            if app.hit_areas.details.contains(at) {
+                                app.scroll_details(is_scroll_up, 1);
+                            } else if app.hit_areas.status.contains(at) {
+                                if app.browse.is_some() {
+                                    app.browse_move(is_scroll_up);
+                                } else {
+                                    app.scroll_status(is_scroll_up, 1);
                                 }
-                                // Left panel (table) - no scrolling needed
                             }
+                            // Table - no scrolling needed
                         }
                         event::MouseEventKind::Down(event::MouseButton::Left) => {
-                            let term_size = terminal.size()?;
-                            // Footer starts at Height - BottomGap(1) - Footer(3)
-                            let footer_row = term_size.height.saturating_sub(4);
-                            let is_in_footer = mouse.row >= footer_row && mouse.row < term_size.height.saturating_sub(1);
-                            
-                            // Layout: TopGap(1) + Header(5) + Gap(1) = 7
-                            let main_area_start = 7;
-                            
-                            if is_in_footer {
-                                // Footer button clicks
-                                let col = mouse.column;
-                                if col >= 10 && col < 20 { app.show_delete_popup = true; }
-                                else if col >= 20 && col < 30 { app.show_apply_popup = true; }
-                                else if col >= 30 && col < 40 { 
-                                    if let Some(snap) = app.get_selected_snapshot().cloned() {
-                                        app.loading = true;
-                                        app.loading_message = format!("Fetching status for {}...", snap.number);
-                                        let (tx, rx) = mpsc::channel();
-                                        app.rx = Some(rx);
-                                        thread::spawn(move || {
-                                            let res = crate::data::get_snapshot_status(&snap)
-                                                .map(AsyncResult::Status)
-                                                .map_err(|e| e.to_string());
-                                            let _ = tx.send(res);
-                                        });
+                            let table = app.hit_areas.table;
+                            if let Some(action) = app.hit_areas.footer_action_at(mouse.column, mouse.row) {
+                                match action {
+                                    FooterAction::Create => app.open_create_popup(),
+                                    FooterAction::Delete => app.open_delete_popup(),
+                                    FooterAction::Apply => app.open_apply_popup(),
+                                    FooterAction::Filter => {
+                                        app.exit_visual_mode();
+                                        app.filtering = true;
                                     }
+                                    FooterAction::Status => app.status_or_compare(),
+                                    FooterAction::Refresh => {
+                                        app.request_refresh();
+                                        app.snapshots.clear();
+                                    }
+                                    FooterAction::Quit if app.quit_needs_confirm() => app.show_quit_popup = true,
+                                    FooterAction::Quit => break,
                                 }
-                                else if col >= 40 && col < 50 { 
-                                    app.loading = true;
-                                    app.loading_message = String::from("Refreshing...");
-                                    app.snapshots.clear();
-                                    let (tx, rx) = mpsc::channel();
-                                    app.rx = Some(rx);
-                                    thread::spawn(move || {
-                                        let res = crate::data::list_snapshots()
-                                            .map(AsyncResult::Snapshots)
-                                            .map_err(|e| e.to_string());
-                                        let _ = tx.send(res);
-                                    });
-                                }
-                                else if col >= 50 && col < 60 { return Ok(()); }
-                            } else if mouse.row >= main_area_start && mouse.row < footer_row {
-                                // Main area - check if left panel (table)
-                                let half_width = term_size.width / 2;
-                                let left_padding = 2;
-                                
-                                if mouse.column >= left_padding && mouse.column < half_width {
-                                    // Adjust column for padding
-                                    let effective_col = mouse.column - left_padding;
-                                    // Table block starts at main_area_start
-                                    // Border = 1 row, Header = 1 row
-                                    // Table block starts at main_area_start
-                                    // Border = 1 row, Header = 1 row
-                                    let table_border_top = main_area_start;
-                                    let table_header_row = table_border_top + 1;
-                                    let first_data_row = table_header_row + 1;
-                                    
-                                    if mouse.row == table_header_row {
-                                        // Clicked on table header - determine column for sorting
-                                        let col_x = effective_col;
-                                        
-                                        // Column boundaries based on UI constraints:
-                                        // Border: 1
-                                        // Col 1 (Number): 8 -> End 9
-                                        // Col 2 (Type): 10 -> End 19
-                                        // Col 3 (Date): 22 -> End 41
-                                        // Col 4 (User): 12 -> End 53
-                                        // Col 5 (Space): 12 -> End 65
-                                        if col_x < 9 {
-                                            app.set_sort_key(crate::app::SortKey::Number);
-                                        } else if col_x < 19 {
-                                            app.set_sort_key(crate::app::SortKey::Type);
-                                        } else if col_x < 41 {
-                                            app.set_sort_key(crate::app::SortKey::Date);
-                                        } else if col_x < 53 {
-                                            app.set_sort_key(crate::app::SortKey::User);
-                                        } else if col_x < 65 {
-                                            app.set_sort_key(crate::app::SortKey::UsedSpace);
-                                        }
-                                    } else if mouse.row >= first_data_row {
-                                        // Clicked on table body - select row
-                                        let row_offset = mouse.row.saturating_sub(first_data_row);
-                                        let target_index = row_offset as usize;
-                                        
-                                        if target_index < app.snapshots.len() {
-                                            app.table_state.select(Some(target_index));
-                                            app.get_status_selected_snapshot(); // Auto-show status
-                                        }
+                            } else if table.contains(Position::new(mouse.column, mouse.row)) {
+                                // Border = 1 row, Header = 1 row
+                                let table_header_row = table.y + 1;
+                                let first_data_row = table_header_row + 1;
+
+                                if mouse.row == table_header_row {
+                                    // Clicked on table header - sort by that column
+                                    if let Some(key) = app.hit_areas.sort_key_at(mouse.column, mouse.row) {
+                                        app.set_sort_key(key);
+                                    }
+                                } else if mouse.row >= first_data_row && mouse.row < table.bottom().saturating_sub(1) {
+                                    // Clicked on table body - select row
+                                    let row_offset = mouse.row - first_data_row;
+                                    let target_index = app.table_state.offset() + row_offset as usize;
+
+                                    if target_index < app.get_display_rows().len() {
+                                        app.table_state.select(Some(target_index));
+                                        app.schedule_status_fetch(); // Auto-show status once navigation pauses
                                     }
                                 }
                             }
@@ -449,6 +903,11 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut A
         }
         
         // Tick animations
-        app.on_tick();
+        dirty |= app.on_tick();
     }
+
+    // Persist preferences before the terminal is restored. A failed write
+    // shouldn't stop the user from quitting.
+    let _ = app.preferences().save();
+    Ok(())
 }
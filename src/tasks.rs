@@ -0,0 +1,242 @@
+use crate::app::AsyncResult;
+use crate::data::{CancelToken, SnapperError};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+pub type TaskId = u64;
+pub type TaskResult = Result<AsyncResult, TaskError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskError {
+    /// A required executable is not installed; the UI cannot do anything useful.
+    MissingBinary(String),
+    /// The remote machine can't be reached, so nothing else will work either.
+    Unreachable(String),
+    Failed(String),
+}
+
+impl From<anyhow::Error> for TaskError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast_ref::<SnapperError>() {
+            Some(SnapperError::NotFound(program)) => TaskError::MissingBinary(program.clone()),
+            Some(unreachable @ SnapperError::Unreachable { .. }) => TaskError::Unreachable(unreachable.to_string()),
+            // Reported by its own message rather than the context wrapping it
+            Some(other) => TaskError::Failed(other.to_string()),
+            None => TaskError::Failed(err.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::MissingBinary(program) => {
+                write!(f, "{}", SnapperError::NotFound(program.clone()))
+            }
+            TaskError::Unreachable(msg) | TaskError::Failed(msg) => f.write_str(msg),
+        }
+    }
+}
+
+enum Event {
+    Progress(AsyncResult),
+    Done(TaskResult),
+}
+
+/// Handed to streaming tasks to deliver partial results before they finish.
+pub struct Progress {
+    id: TaskId,
+    tx: Sender<(TaskId, Event)>,
+}
+
+impl Progress {
+    pub fn send(&self, result: AsyncResult) {
+        let _ = self.tx.send((self.id, Event::Progress(result)));
+    }
+}
+
+/// Runs background operations on worker threads and multiplexes their
+/// results over a single long-lived channel, tagged with the task id.
+pub struct TaskQueue {
+    tx: Sender<(TaskId, Event)>,
+    rx: Receiver<(TaskId, Event)>,
+    pending: HashMap<TaskId, PendingTask>,
+    next_id: TaskId,
+}
+
+struct PendingTask {
+    cancel: CancelToken,
+    /// Background tasks don't hold up the loading overlay.
+    blocking: bool,
+}
+
+impl Default for TaskQueue {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        TaskQueue {
+            tx,
+            rx,
+            pending: HashMap::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl TaskQueue {
+    pub fn spawn<F>(&mut self, task: F) -> TaskId
+    where
+        F: FnOnce(&CancelToken) -> TaskResult + Send + 'static,
+    {
+        self.start(move |cancel, _| task(cancel), true)
+    }
+
+    /// Like `spawn`, but the task does not count towards `is_busy`.
+    pub fn spawn_background<F>(&mut self, task: F) -> TaskId
+    where
+        F: FnOnce(&CancelToken) -> TaskResult + Send + 'static,
+    {
+        self.start(move |cancel, _| task(cancel), false)
+    }
+
+    /// Like `spawn` (or `spawn_background` when not `blocking`), but the task
+    /// can report partial results through `Progress` as it goes.
+    pub fn spawn_streaming<F>(&mut self, blocking: bool, task: F) -> TaskId
+    where
+        F: FnOnce(&CancelToken, &Progress) -> TaskResult + Send + 'static,
+    {
+        self.start(task, blocking)
+    }
+
+    fn start<F>(&mut self, task: F, blocking: bool) -> TaskId
+    where
+        F: FnOnce(&CancelToken, &Progress) -> TaskResult + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancel = CancelToken::default();
+        self.pending.insert(id, PendingTask { cancel: cancel.clone(), blocking });
+
+        let progress = Progress { id, tx: self.tx.clone() };
+        thread::spawn(move || {
+            let res = task(&cancel, &progress);
+            let _ = progress.tx.send((id, Event::Done(res)));
+        });
+        id
+    }
+
+    /// Collects every result, partial or final, that has arrived since the
+    /// last call. Results from cancelled tasks are discarded.
+    pub fn drain(&mut self) -> Vec<TaskResult> {
+        let mut results = Vec::new();
+        while let Ok((id, event)) = self.rx.try_recv() {
+            match event {
+                Event::Progress(result) if self.pending.contains_key(&id) => results.push(Ok(result)),
+                Event::Done(res) if self.pending.remove(&id).is_some() => results.push(res),
+                _ => {}
+            }
+        }
+        results
+    }
+
+    pub fn cancel(&mut self, id: TaskId) {
+        if let Some(task) = self.pending.remove(&id) {
+            task.cancel.cancel();
+        }
+    }
+
+    pub fn cancel_all(&mut self) {
+        for (_, task) in self.pending.drain() {
+            task.cancel.cancel();
+        }
+    }
+
+    pub fn is_pending(&self, id: TaskId) -> bool {
+        self.pending.contains_key(&id)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.pending.values().any(|task| task.blocking)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn drain_until(queue: &mut TaskQueue, count: usize) -> Vec<TaskResult> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut results = Vec::new();
+        while results.len() < count && Instant::now() < deadline {
+            results.extend(queue.drain());
+            thread::sleep(Duration::from_millis(5));
+        }
+        results
+    }
+
+    #[test]
+    fn test_concurrent_tasks_all_deliver() {
+        let mut queue = TaskQueue::default();
+        queue.spawn(|_| Ok(AsyncResult::Modify(1)));
+        queue.spawn(|_| Ok(AsyncResult::Modify(2)));
+        assert!(queue.is_busy());
+
+        let mut numbers: Vec<u32> = drain_until(&mut queue, 2)
+            .into_iter()
+            .filter_map(|res| match res {
+                Ok(AsyncResult::Modify(n)) => Some(n),
+                _ => None,
+            })
+            .collect();
+        numbers.sort();
+        assert_eq!(numbers, vec![1, 2]);
+        assert!(!queue.is_busy());
+    }
+
+    #[test]
+    fn test_cancelled_results_are_dropped() {
+        let mut queue = TaskQueue::default();
+        queue.spawn(|cancel| {
+            while !cancel.is_cancelled() {
+                thread::sleep(Duration::from_millis(5));
+            }
+            Ok(AsyncResult::Modify(1))
+        });
+        queue.cancel_all();
+        assert!(!queue.is_busy());
+        thread::sleep(Duration::from_millis(50));
+        assert!(queue.drain().is_empty());
+    }
+
+    #[test]
+    fn test_streaming_progress_arrives_before_result() {
+        let mut queue = TaskQueue::default();
+        queue.spawn_streaming(false, |_, progress| {
+            progress.send(AsyncResult::Modify(1));
+            progress.send(AsyncResult::Modify(2));
+            Ok(AsyncResult::Modify(3))
+        });
+
+        let numbers: Vec<u32> = drain_until(&mut queue, 3)
+            .into_iter()
+            .filter_map(|res| match res {
+                Ok(AsyncResult::Modify(n)) => Some(n),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_background_tasks_are_not_busy() {
+        let mut queue = TaskQueue::default();
+        queue.spawn_background(|_| {
+            thread::sleep(Duration::from_millis(50));
+            Ok(AsyncResult::Modify(1))
+        });
+        assert!(!queue.is_busy());
+        assert_eq!(drain_until(&mut queue, 1).len(), 1);
+    }
+}
@@ -0,0 +1,122 @@
+use ratatui::style::{Color, Modifier, Style};
+
+/// Named colour slots used by every `draw_*` function in `ui.rs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub primary: Color,
+    pub secondary: Color,
+    pub accent: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+    pub bg_dark: Color,
+    pub fg: Color,
+    pub gray: Color,
+    pub bg_lighter: Color,
+    /// Shown next to the snapshot the system boots into by default.
+    pub default_marker: &'static str,
+    /// Shown next to the snapshot the system is currently running from.
+    pub active_marker: &'static str,
+    /// Every colour is the terminal's default (`NO_COLOR`, `--no-color`).
+    pub monochrome: bool,
+}
+
+impl Theme {
+    /// This theme with the terminal's default colours in every slot, for
+    /// `NO_COLOR`. The name and markers stay, so the choice is still saved.
+    pub fn monochrome(self) -> Theme {
+        Theme {
+            primary: Color::Reset,
+            secondary: Color::Reset,
+            accent: Color::Reset,
+            success: Color::Reset,
+            warning: Color::Reset,
+            error: Color::Reset,
+            bg_dark: Color::Reset,
+            fg: Color::Reset,
+            gray: Color::Reset,
+            bg_lighter: Color::Reset,
+            monochrome: true,
+            ..self
+        }
+    }
+
+    /// `style` for a cursor or toggle that stands out by its background;
+    /// without colours, reverse video does that job.
+    pub fn highlight(&self, style: Style) -> Style {
+        if self.monochrome { style.add_modifier(Modifier::REVERSED) } else { style }
+    }
+}
+
+// Modern Color Palette (Cyberpunk / Dracula inspired)
+pub const DRACULA: Theme = Theme {
+    name: "dracula",
+    primary: Color::Rgb(189, 147, 249),    // Deep Purple
+    secondary: Color::Rgb(139, 233, 253),  // Cyan
+    accent: Color::Rgb(255, 121, 198),     // Pink
+    success: Color::Rgb(80, 250, 123),     // Green
+    warning: Color::Rgb(241, 250, 140),    // Yellow
+    error: Color::Rgb(255, 85, 85),        // Red
+    bg_dark: Color::Rgb(30, 30, 46),       // Darker Background
+    fg: Color::Rgb(248, 248, 242),         // Foreground
+    gray: Color::Rgb(98, 114, 164),        // Gray
+    bg_lighter: Color::Rgb(68, 71, 90),    // Lighter Background
+    default_marker: "★",
+    active_marker: "●",
+    monochrome: false,
+};
+
+pub const SOLARIZED_DARK: Theme = Theme {
+    name: "solarized-dark",
+    primary: Color::Rgb(108, 113, 196),    // Violet
+    secondary: Color::Rgb(42, 161, 152),   // Cyan
+    accent: Color::Rgb(211, 54, 130),      // Magenta
+    success: Color::Rgb(133, 153, 0),      // Green
+    warning: Color::Rgb(181, 137, 0),      // Yellow
+    error: Color::Rgb(220, 50, 47),        // Red
+    bg_dark: Color::Rgb(0, 43, 54),        // base03
+    fg: Color::Rgb(147, 161, 161),         // base1
+    gray: Color::Rgb(88, 110, 117),        // base01
+    bg_lighter: Color::Rgb(7, 54, 66),     // base02
+    default_marker: "★",
+    active_marker: "●",
+    monochrome: false,
+};
+
+pub const GRUVBOX: Theme = Theme {
+    name: "gruvbox",
+    primary: Color::Rgb(211, 134, 155),    // Purple
+    secondary: Color::Rgb(142, 192, 124),  // Aqua
+    accent: Color::Rgb(254, 128, 25),      // Orange
+    success: Color::Rgb(184, 187, 38),     // Green
+    warning: Color::Rgb(250, 189, 47),     // Yellow
+    error: Color::Rgb(251, 73, 52),        // Red
+    bg_dark: Color::Rgb(40, 40, 40),       // bg0
+    fg: Color::Rgb(235, 219, 178),         // fg1
+    gray: Color::Rgb(146, 131, 116),       // gray
+    bg_lighter: Color::Rgb(60, 56, 54),    // bg1
+    default_marker: "★",
+    active_marker: "◆",
+    monochrome: false,
+};
+
+pub const THEMES: &[Theme] = &[DRACULA, SOLARIZED_DARK, GRUVBOX];
+
+pub fn by_name(name: &str) -> Option<Theme> {
+    THEMES.iter().copied().find(|theme| theme.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monochrome_keeps_name_and_reverses_highlights() {
+        let mono = GRUVBOX.monochrome();
+        assert_eq!((mono.name, mono.active_marker), ("gruvbox", "◆"));
+        assert_eq!((mono.accent, mono.bg_dark), (Color::Reset, Color::Reset));
+        assert!(mono.highlight(Style::default()).add_modifier.contains(Modifier::REVERSED));
+        assert_eq!(GRUVBOX.highlight(Style::default()), Style::default());
+    }
+}
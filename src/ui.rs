@@ -1,90 +1,190 @@
-use crate::app::App;
+use crate::app::{SortKey, format_relative, format_size, format_size_with_bytes, max_scroll, App, Column, DisplayRow, FooterAction, HitAreas, Panel, Severity, CREATE_FIELDS, HIDEABLE_TYPES, MODIFY_FIELDS};
+use crate::config::Config;
+use crate::data::{self, BootLineage, Snapshot, CLEANUP_ALGORITHMS};
+use crate::filter::Filter;
+use crate::history;
+use crate::keys::{self, Action};
+use crate::theme::{Theme, THEMES};
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, BorderType, Cell, Paragraph, Row, Table, Wrap, Clear},
+    widgets::{Block, Borders, BorderType, Cell, Gauge, Padding, Paragraph, Row, Table, TableState, Tabs, Wrap, Clear},
     Frame,
 };
 use tachyonfx::{
     fx, Duration, EffectRenderer, Interpolation,
 };
 
-// Modern Color Palette (GitHub Dark / Dracula inspired)
-// Modern Color Palette (Cyberpunk / Dracula inspired)
-const PALETTE_PRIMARY: Color = Color::Rgb(189, 147, 249);    // Deep Purple
-const PALETTE_SECONDARY: Color = Color::Rgb(139, 233, 253);  // Cyan
-const PALETTE_ACCENT: Color = Color::Rgb(255, 121, 198);     // Pink
-const PALETTE_SUCCESS: Color = Color::Rgb(80, 250, 123);     // Green
-const PALETTE_WARNING: Color = Color::Rgb(241, 250, 140);    // Yellow
-const PALETTE_ERROR: Color = Color::Rgb(255, 85, 85);        // Red
-const PALETTE_BG_DARK: Color = Color::Rgb(30, 30, 46);       // Darker Background
-const PALETTE_FG: Color = Color::Rgb(248, 248, 242);         // Foreground
-const PALETTE_GRAY: Color = Color::Rgb(98, 114, 164);        // Gray
-const PALETTE_BG_LIGHTER: Color = Color::Rgb(68, 71, 90);    // Lighter Background
-
 const SLANT_RIGHT: &str = "";
 const SLANT_LEFT: &str = "";
 
+// Type-column flag of a pre snapshot without its post
+const ORPHAN_MARKER: &str = " ⏳";
+
+// Number-column flag of a snapshot made with the create popup
+const CREATED_MARKER: &str = " ✎";
+
+// Drawn before the highlighted table row
+const HIGHLIGHT_SYMBOL: &str = "👉 ";
+
+// Keybinding reference shown by the `?` help overlay
+const HELP_SECTIONS: &[(&str, &[(&str, &str)])] = &[
+    ("Navigation", &[
+        ("↑ / ↓", "Move through the snapshot list, or scroll the focused panel"),
+        ("PgUp / PgDn", "Move or scroll the focused panel a page at a time"),
+        ("Home / End", "Jump to the first / last snapshot in the list"),
+        ("Tab", "Focus the next panel (Shift-Tab: previous); narrow terminals show it as a tab under the table"),
+        (": / #", "Go to a snapshot by number"),
+        ("[ / ]", "Move the file cursor in the status panel"),
+        ("F", "Fold (wrap) / don't fold status lines (unwrapped: ← / → scroll the focused status panel)"),
+        ("Enter", "Show the diff of the file under the status cursor"),
+        ("X", "Mark/unmark the file under the status cursor"),
+        ("U", "Undo changes to the marked (or cursor) files"),
+        ("W", "Save the status listing (or, in the diff viewer, the diff) to a file"),
+        ("Mouse wheel", "Scroll the details/status panel under the pointer"),
+        ("?", "Toggle this help"),
+        ("L", "Log of privileged commands run this session"),
+        ("O", "About: TUI and snapper versions, preferences file"),
+        ("t", "Pick a colour theme"),
+        ("T", "Toggle relative / absolute dates in the table"),
+        ("z", "Dense view: no gaps, one-line header and footer"),
+        ("Q", "Quit (asks first while busy or with a selection)"),
+        ("Ctrl-C", "Quit immediately"),
+    ]),
+    ("Actions", &[
+        ("c", "Create a snapshot with type, cleanup and userdata (Tab moves between fields)"),
+        ("Shift-C", "Show the config's cleanup policy instead of the details"),
+        ("D", "Delete the highlighted or selected snapshot(s)"),
+        ("A", "Apply (rollback) the highlighted snapshot"),
+        ("S", "Show the status of the highlighted snapshot, or compare the two selected ones"),
+        ("R", "Refresh the snapshot list"),
+        ("E", "Export the listed snapshots to CSV or JSON"),
+        ("N", "Create a snapshot now, described by default_description_template"),
+        (">", "Create the post snapshot of the pre made this session"),
+        ("p", "Label the highlighted snapshot locally, without root"),
+        ("j", "Open the snapshot's directory in $FILE_MANAGER or $EDITOR"),
+        ("J", "Run snapper setup-quota when a btrfs config shows no used space"),
+        ("H", "Ask to delete every pre without a post and post without a pre"),
+        ("M", "Modify description, cleanup and userdata"),
+        ("K", "Run a snapper cleanup algorithm on the config"),
+        ("B", "Mount the snapshot and browse its files (Enter opens, Backspace goes up, Esc unmounts)"),
+        ("Y", "Copy the snapshot's details (or the filtered table as TSV) to the clipboard"),
+        ("/", "Filter snapshots (Enter keeps, Esc clears)"),
+    ]),
+    ("Filter", &[
+        ("type:pre", "Only snapshots of this type (repeat to allow several)"),
+        ("user:root", "Only snapshots created by this user"),
+        ("after:DATE", "Taken on or after DATE (YYYY-MM-DD)"),
+        ("before:DATE", "Taken before DATE (YYYY-MM-DD)"),
+        ("other words", "Substring search over description, type, user, number"),
+        ("Ctrl-S", "While typing: toggle case-sensitive matching of the words"),
+        ("Ctrl-W", "While typing: toggle matching whole words only"),
+        ("Alt-1/2/3", "Hide or show all single / pre / post snapshots"),
+    ]),
+    ("Grouping", &[
+        ("G", "Fold pre/post pairs into single rows"),
+        ("→ / ←", "Expand / collapse the highlighted pair"),
+    ]),
+    ("Sorting", &[
+        ("1", "Sort by number"),
+        ("2", "Sort by type"),
+        ("3", "Sort by date"),
+        ("4", "Sort by user"),
+        ("5", "Sort by used space"),
+        ("", "Press the same key again to reverse the order"),
+    ]),
+    ("Selection", &[
+        ("Space", "Toggle selection of the highlighted snapshot"),
+        ("Ctrl-A", "Select every snapshot in the filtered view"),
+        ("I", "Invert the selection within the filtered view"),
+        ("V", "Visual mode: ↑/↓ extend the selection from the anchor (V/Esc ends)"),
+        ("Esc", "Close any popup, filter or browse view, dropping what was typed; else clear the selection"),
+        ("Esc (loading)", "Abort the running snapper operation or status listing"),
+    ]),
+];
+
+// Below this the fixed header, footer and gaps leave the panels no room
+const MIN_WIDTH: u16 = 60;
+const MIN_HEIGHT: u16 = 15;
+
+// Narrower than this, details and status become tabs under the table
+const STACKED_WIDTH: u16 = 100;
+
 pub fn draw(f: &mut Frame, app: &mut App) {
-    // Splash Screen - simple custom implementation
+    // Copied out so it can be borrowed alongside `&mut App`
+    let theme = app.theme;
+    let theme = &theme;
+
+    let area = f.area();
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        // Nothing on screen is clickable until the full UI is back
+        app.hit_areas = HitAreas::default();
+        draw_too_small(f, theme, area);
+        return;
+    }
+
+    // Splash Screen - simple custom implementation. `App::on_tick` takes it
+    // down once its time is up.
     if app.show_splash {
-        if let Some(start) = app.splash_start {
-            if start.elapsed().as_secs() >= 2 {
-                app.show_splash = false;
-            } else {
-                // Render simple centered splash with gradient colors
-                let block = Block::default()
-                    .borders(Borders::ALL)
-                    .border_type(BorderType::Double)
-                    .border_style(Style::default().fg(PALETTE_PRIMARY))
-                    .style(Style::default().bg(Color::Black));
-                f.render_widget(block, f.area());
-                
-                let text = vec![
-                    Line::from(""),
-                    Line::from(""),
-                    Line::from(""),
-                    Line::from(Span::styled(
-                        "█▀▀ █▄░█ █▀█ █▀█ █▀█ █▀▀ █▀█",
-                        Style::default()
-                            .fg(PALETTE_PRIMARY)
-                            .add_modifier(Modifier::BOLD),
-                    )),
-                    Line::from(Span::styled(
-                        "▄▄█ █░▀█ █▀█ █▀▀ █▀▀ ██▄ █▀▄",
-                        Style::default()
-                            .fg(PALETTE_SECONDARY)
-                            .add_modifier(Modifier::BOLD),
-                    )),
-                    Line::from(Span::styled(
-                        "              TUI",
-                        Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::ITALIC),
-                    )),
-                    Line::from(""),
-                    Line::from(Span::styled(
-                        "⚡ Initializing System...",
-                        Style::default().fg(PALETTE_WARNING),
-                    )),
-                ];
-                
-                let para = Paragraph::new(text).alignment(Alignment::Center);
-                let center = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Percentage(35),
-                        Constraint::Length(9),
-                        Constraint::Percentage(40),
-                    ])
-                    .split(f.area())[1];
-                f.render_widget(para, center);
-                return;
-            }
-        }
+        // Render simple centered splash with gradient colors
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(theme.primary))
+            .style(Style::default().bg(backdrop(theme).0));
+        f.render_widget(block, f.area());
+        
+        let text = vec![
+            Line::from(""),
+            Line::from(""),
+            Line::from(""),
+            Line::from(Span::styled(
+                "█▀▀ █▄░█ █▀█ █▀█ █▀█ █▀▀ █▀█",
+                Style::default()
+                    .fg(theme.primary)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(Span::styled(
+                "▄▄█ █░▀█ █▀█ █▀▀ █▀▀ ██▄ █▀▄",
+                Style::default()
+                    .fg(theme.secondary)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(Span::styled(
+                "              TUI",
+                Style::default().fg(theme.accent).add_modifier(Modifier::ITALIC),
+            )),
+            Line::from(""),
+            Line::from(Span::styled(
+                "⚡ Initializing System...",
+                Style::default().fg(theme.warning),
+            )),
+        ];
+        
+        let para = Paragraph::new(text).alignment(Alignment::Center);
+        let center = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(35),
+                Constraint::Length(9),
+                Constraint::Percentage(40),
+            ])
+            .split(f.area())[1];
+        f.render_widget(para, center);
+        return;
+    }
+
+    if let Some(error) = &app.fatal_error {
+        draw_fatal_screen(f, theme, error);
+        return;
     }
 
     // Initialize effect if not present
-    if app.fx.is_none() {
+    // The fade goes through black, which NO_COLOR rules out
+    if app.fx.is_none() && app.config.animations && !theme.monochrome {
         let effect = fx::fade_from(
             ratatui::style::Color::Black,
             ratatui::style::Color::Black,
@@ -99,21 +199,22 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     // But for operations, we want overlay.
     
     if !app.snapshots.is_empty() || !app.loading {
-         // Create a "floating" layout with gaps
+         // Create a "floating" layout with gaps, or a tight one in dense view
+        let (gap, header_height, footer_height) = if app.dense { (0, 1, 1) } else { (1, 5, 3) };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(1), // Top Gap
-                Constraint::Length(5), // Header
-                Constraint::Length(1), // Gap
-                Constraint::Min(0),    // Main
-                Constraint::Length(1), // Gap
-                Constraint::Length(3), // Footer
-                Constraint::Length(1), // Bottom Gap
+                Constraint::Length(gap),           // Top Gap
+                Constraint::Length(header_height), // Header
+                Constraint::Length(gap),           // Gap
+                Constraint::Min(0),                // Main
+                Constraint::Length(gap),           // Gap
+                Constraint::Length(footer_height), // Footer
+                Constraint::Length(gap),           // Bottom Gap
             ])
             .split(f.area());
         let header_area = chunks[1];
-        draw_header(f, app, header_area);
+        draw_header(f, theme, app, header_area);
         
         // Add horizontal padding
         let main_layout = Layout::default()
@@ -133,40 +234,150 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         let main_area = intersection(chunks[3], main_layout[1]);
         let footer_area = intersection(chunks[5], main_layout[1]);
 
-        draw_header(f, app, header_area);
-        draw_main(f, app, main_area);
-        draw_actions_bar(f, footer_area);
+        draw_header(f, theme, app, header_area);
+        draw_main(f, theme, app, main_area);
+        draw_actions_bar(f, theme, app, footer_area);
     }
 
 
 
     // Render TachyonFX effects
-    if let Some(effect) = &mut app.fx {
-        if let Some(start) = app.fx_start {
-            f.render_effect(effect, f.area(), start.elapsed().into());
-        }
+    if let Some(effect) = &mut app.fx
+        && let Some(start) = app.fx_start
+    {
+        f.render_effect(effect, f.area(), start.elapsed().into());
     }
 
     // Custom Popups - render on top
     if app.show_delete_popup {
-        draw_delete_popup(f, app);
+        draw_delete_popup(f, theme, app);
     }
     
     if app.show_create_popup {
-        draw_create_popup(f, app);
+        draw_create_popup(f, theme, app);
+    }
+
+    if app.show_export_popup {
+        draw_export_popup(f, theme, app);
+    }
+
+    if app.show_goto_popup {
+        draw_goto_popup(f, theme, app);
+    }
+
+    if app.show_label_popup {
+        draw_label_popup(f, theme, app);
+    }
+
+    if app.show_modify_popup {
+        draw_modify_popup(f, theme, app);
     }
     
     if app.show_apply_popup {
-        draw_apply_popup(f, app);
+        draw_apply_popup(f, theme, app);
+    }
+
+    if app.show_diff_popup {
+        draw_diff_popup(f, theme, app);
+    }
+
+    if app.show_report_popup {
+        draw_report_popup(f, theme, app);
+    }
+
+    if app.show_undo_popup {
+        draw_undo_popup(f, theme, app);
+    }
+
+    if app.show_help_popup {
+        draw_help_popup(f, theme, app);
+    }
+
+    if app.show_history_popup {
+        draw_history_popup(f, theme, app);
+    }
+
+    if app.show_about_popup {
+        draw_about_popup(f, theme, app);
+    }
+
+    if app.show_theme_popup {
+        draw_theme_popup(f, theme, app);
+    }
+
+    if app.show_cleanup_popup {
+        draw_cleanup_popup(f, theme, app);
+    }
+
+    if let Some(config) = &app.quota_popup {
+        let message = format!(
+            "Run snapper setup-quota for {}?\n\nThis enables btrfs quota groups so snapper can report used space. Quotas can slow down large btrfs filesystems.\n\n[Enter] Run  [Esc] Cancel",
+            config
+        );
+        draw_popup(f, theme, "💾 SET UP QUOTA", &message, theme.warning);
     }
 
     // Overlay Loading Screen if loading (Render last to be on top)
     if app.loading {
-        draw_loading_screen(f, app);
+        draw_loading_screen(f, theme, app);
+    }
+
+    // Above the loading screen, since it is mostly asked for mid-operation
+    if app.show_quit_popup {
+        draw_quit_popup(f, theme, app);
+    }
+
+    draw_toasts(f, theme, app);
+}
+
+// Stacked in the top-right corner, newest first
+fn draw_toasts(f: &mut Frame, theme: &Theme, app: &App) {
+    let screen = f.area();
+    let mut y = screen.y + 1;
+    for (text, _, severity) in app.toasts.iter().rev() {
+        let color = match severity {
+            Severity::Info => theme.secondary,
+            Severity::Success => theme.success,
+            Severity::Warning => theme.warning,
+            Severity::Error => theme.error,
+        };
+        let lines: Vec<Line> = text.lines().map(|line| Line::from(Span::styled(line, Style::default().fg(theme.fg)))).collect();
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 4;
+        let width = width.min(60).min(screen.width);
+        let height = lines.len() as u16 + 2;
+        if y + height > screen.bottom() {
+            break;
+        }
+
+        let area = Rect { x: screen.right() - width, y, width, height };
+        let toast = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(color))
+                .padding(Padding::horizontal(1))
+                .style(Style::default().bg(theme.bg_dark)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(toast, area);
+        y += height;
     }
 }
 
-fn draw_popup(f: &mut Frame, title: &str, message: &str, border_color: Color) {
+// Black and white for the splash and message popups, or the terminal's
+// own colours under NO_COLOR
+fn backdrop(theme: &Theme) -> (Color, Color) {
+    if theme.monochrome { (Color::Reset, Color::Reset) } else { (Color::Black, Color::White) }
+}
+
+fn draw_popup(f: &mut Frame, theme: &Theme, title: &str, message: &str, border_color: Color) {
+    draw_scrolled_popup(f, theme, title, message, border_color, 0);
+}
+
+// `draw_popup` with the message scrolled down `scroll` lines, clamped;
+// returns how far it can scroll
+fn draw_scrolled_popup(f: &mut Frame, theme: &Theme, title: &str, message: &str, border_color: Color, scroll: u16) -> u16 {
+    let (black, white) = backdrop(theme);
     let area = f.area();
     
     // Create centered popup area (65% width, 45% height for better readability)
@@ -194,7 +405,7 @@ fn draw_popup(f: &mut Frame, title: &str, message: &str, border_color: Color) {
     
     // Render fully opaque black background for legibility
     let dark_bg = Block::default()
-        .style(Style::default().bg(Color::Black));
+        .style(Style::default().bg(black));
     f.render_widget(dark_bg, popup_area);
     
     // Render popup border with modern double-line style
@@ -204,13 +415,13 @@ fn draw_popup(f: &mut Frame, title: &str, message: &str, border_color: Color) {
         .title(Span::styled(title, Style::default().fg(border_color).add_modifier(Modifier::BOLD)))
         .title_alignment(Alignment::Center)
         .border_style(Style::default().fg(border_color))
-        .style(Style::default().bg(Color::Black));
+        .style(Style::default().bg(black));
     
     let inner = block.inner(popup_area);
     
     // Fill inner area with black background too
     let inner_bg = Block::default()
-        .style(Style::default().bg(Color::Black));
+        .style(Style::default().bg(black));
     f.render_widget(inner_bg, inner);
     
     f.render_widget(block, popup_area);
@@ -219,39 +430,223 @@ fn draw_popup(f: &mut Frame, title: &str, message: &str, border_color: Color) {
     let para = Paragraph::new(message)
         .alignment(Alignment::Center)
         .wrap(Wrap { trim: true })
-        .style(Style::default().fg(Color::White).bg(Color::Black));
+        .style(Style::default().fg(white).bg(black));
     
     // Center the text vertically within the popup
     let text_area = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Percentage(15), Constraint::Percentage(70), Constraint::Percentage(15)])
         .split(inner)[1];
-    
-    f.render_widget(para, text_area);
+
+    let max_scroll = max_scroll(para.line_count(text_area.width), text_area.height);
+    f.render_widget(para.scroll((scroll.min(max_scroll), 0)), text_area);
+    max_scroll
 }
 
-fn draw_delete_popup(f: &mut Frame, app: &mut App) {
-    let count = if app.get_selected_count() > 0 {
-        app.get_selected_count()
+fn draw_delete_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let targets = app.delete_target_snapshots();
+    let count = targets.len();
+
+    let mut message = if !app.orphan_targets.is_empty() {
+        let noun = if count == 1 { "snapshot" } else { "snapshots" };
+        format!("Delete {} orphan pre/post {}?", count, noun)
+    } else if count > 1 {
+        format!("Delete {} selected snapshots?", count)
     } else {
-        1
+        "Delete selected snapshot?".to_string()
     };
+    message.push_str("\n\n");
+    let configs: HashSet<&str> = app.get_filtered_snapshots().iter().map(|s| s.config.as_str()).collect();
+    message.push_str(&delete_listing(&targets, configs.len() > 1));
+    message.push_str("\nThis action cannot be undone.");
+    let orphans = app.orphan_delete_targets();
+    if !orphans.is_empty() {
+        let numbers: Vec<String> = orphans.iter().map(|n| format!("#{}", n)).collect();
+        message.push_str(&format!(
+            "\n\n⚠️ {} {} no post snapshot yet; the operation that took it may still be running.",
+            numbers.join(", "),
+            if orphans.len() == 1 { "is a pre snapshot with" } else { "are pre snapshots with" }
+        ));
+    }
+    if let Some(warning) = boot_critical_warning(&app.boot_critical_delete_targets()) {
+        message.push_str(&format!("\n\n{}\nDeleting it can leave the system unable to boot.", warning));
+    }
+    if app.delete_needs_acknowledgement() {
+        message.push_str("\n\n[y] Delete it anyway  [Esc] Cancel");
+    } else {
+        message.push_str("\n\n[Enter] Confirm  [Esc] Cancel");
+    }
     
-    let message = if count > 1 {
-        format!("Delete {} selected snapshots?\n\nThis action cannot be undone.\n\n[Enter] Confirm  [Esc] Cancel", count)
+    app.delete_max_scroll = draw_scrolled_popup(f, theme, "🗑 DELETE SNAPSHOT 🗑", &message, theme.error, app.delete_scroll);
+    app.delete_scroll = app.delete_scroll.min(app.delete_max_scroll);
+}
+
+// One line per snapshot the delete popup is about to remove, so a wrong
+// selection shows before it's too late; a long one scrolls with ↑/↓.
+// `show_config` names the config when the table lists several.
+fn delete_listing(targets: &[&Snapshot], show_config: bool) -> String {
+    const MAX_LISTED: usize = 100;
+
+    let mut listing = String::new();
+    for snap in targets.iter().take(MAX_LISTED) {
+        let description = if snap.description.is_empty() { "(no description)" } else { snap.description.as_str() };
+        if show_config {
+            listing.push_str(&format!("{} ", snap.config));
+        }
+        listing.push_str(&format!("#{} {} · {}\n", snap.number, snap.snapshot_type, description));
+    }
+    if targets.len() > MAX_LISTED {
+        listing.push_str(&format!("…and {} more\n", targets.len() - MAX_LISTED));
+    }
+    if targets.len() > 5 {
+        listing.push_str("[↑/↓] Scroll\n");
+    }
+    listing
+}
+
+// The delete popup's warning when the targets include the snapshot that is
+// mounted now or the one booted next
+fn boot_critical_warning(snapshots: &[&Snapshot]) -> Option<String> {
+    let active = snapshots.iter().find(|s| s.active);
+    let default = snapshots.iter().find(|s| s.default);
+    let what = match (active, default) {
+        (Some(a), Some(d)) if a.number == d.number => format!("the ACTIVE and DEFAULT snapshot (#{})", a.number),
+        (Some(a), Some(d)) => format!("the ACTIVE snapshot (#{}) and the DEFAULT snapshot (#{})", a.number, d.number),
+        (Some(a), None) => format!("the ACTIVE snapshot (#{})", a.number),
+        (None, Some(d)) => format!("the DEFAULT snapshot (#{})", d.number),
+        (None, None) => return None,
+    };
+    Some(format!("⚠ This includes {}.", what))
+}
+
+fn draw_about_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    draw_popup(f, theme, "ℹ️ ABOUT", &about_text(app), theme.secondary);
+}
+
+// What a bug report needs: both versions and where the preferences live
+fn about_text(app: &App) -> String {
+    let snapper = match &app.snapper_version {
+        None => String::from("checking…"),
+        Some(Ok(version)) if !data::supports_jsonout(version) => {
+            format!("{} ⚠️ too old for --jsonout; the snapshot list can't be read", version)
+        }
+        Some(Ok(version)) => version.clone(),
+        Some(Err(e)) => format!("unknown ({})", e),
+    };
+    let preferences = Config::path().map_or_else(|| String::from("(no home directory)"), |path| path.display().to_string());
+    format!(
+        "snapper-TUI-rust {}\n\nsnapper: {}\nPreferences: {}\nSnapper config: {}\n\n[Any key] Close",
+        env!("CARGO_PKG_VERSION"),
+        snapper,
+        preferences,
+        app.active_config.as_deref().unwrap_or("all"),
+    )
+}
+
+fn draw_quit_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let reason = if app.loading {
+        format!("\"{}\" is still running and will be abandoned.", app.loading_message)
     } else {
-        "Delete selected snapshot?\n\nThis action cannot be undone.\n\n[Enter] Confirm  [Esc] Cancel".to_string()
+        format!("{} selected snapshot(s) will be forgotten.", app.get_selected_count())
     };
-    
-    draw_popup(
+    let message = format!("Quit anyway?\n\n{}\n\n[Q/Enter] Quit  [Esc] Stay", reason);
+    draw_popup(f, theme, "⚠️ QUIT ⚠️", &message, theme.warning);
+}
+
+fn draw_create_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let form = &app.create_form;
+    draw_form_popup(f, theme, String::from(" ➕ CREATE SNAPSHOT "), &CREATE_FIELDS, &form.values, form.field, " [Enter] Create ");
+}
+
+fn draw_export_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    draw_input_popup(
         f,
-        "🗑 DELETE SNAPSHOT 🗑",
-        &message,
-        PALETTE_ERROR,
+        theme,
+        " 📤 EXPORT SNAPSHOTS ",
+        "Export the listed snapshots to (.csv for CSV, otherwise JSON):",
+        &app.export_input,
+        " [Enter] Export ",
     );
 }
 
-fn draw_create_popup(f: &mut Frame, app: &mut App) {
+fn draw_report_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let prompt = if app.report_is_diff { "Save the diff to:" } else { "Save the status listing to:" };
+    draw_input_popup(f, theme, " 💾 SAVE REPORT ", prompt, &app.report_input, " [Enter] Save ");
+}
+
+fn draw_goto_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    draw_input_popup(f, theme, " #️⃣ GO TO SNAPSHOT ", "Snapshot number:", &app.goto_input, " [Enter] Go ");
+}
+
+fn draw_label_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let number = app.label_target.as_ref().map_or(0, |&(_, number)| number);
+    let prompt = format!("Label for snapshot {} (kept on this machine; empty removes it):", number);
+    draw_input_popup(f, theme, " 🔖 LABEL SNAPSHOT ", &prompt, &app.label_input, " [Enter] Save ");
+}
+
+fn draw_modify_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let form = &app.modify_form;
+    let title = format!(" ✏️ MODIFY SNAPSHOT {} ", form.number);
+    draw_form_popup(f, theme, title, &MODIFY_FIELDS, &form.values, form.field, " [Enter] Save ");
+}
+
+// One input box per field; Tab moves the cursor between them
+fn draw_form_popup(f: &mut Frame, theme: &Theme, title: String, labels: &[&str], values: &[String], field: usize, confirm: &str) {
+    let area = centered_rect(60, 40, f.area());
+    // Tall enough for every field, as far as the terminal allows
+    let height = area.height.max(labels.len() as u16 * 3 + 5).min(f.area().height);
+    let area = Rect { y: f.area().y + (f.area().height - height) / 2, height, ..area };
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(Style::default().fg(theme.accent))
+        .title(Line::from(vec![
+            Span::styled(title, Style::default().fg(theme.bg_dark).bg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(SLANT_RIGHT, Style::default().fg(theme.accent).bg(theme.bg_dark)),
+        ]))
+        .title_alignment(Alignment::Left)
+        .style(Style::default().bg(theme.bg_dark));
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let mut constraints = vec![Constraint::Length(3); labels.len()];
+    constraints.extend([Constraint::Min(0), Constraint::Length(1)]);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .margin(1)
+        .split(inner_area);
+
+    for (i, label) in labels.iter().enumerate() {
+        let focused = i == field;
+        let border = if focused { theme.accent } else { theme.gray };
+        // Away from the cursor the start of the text shows, cut off on the right
+        let text = if focused { input_view(&values[i], chunks[i].width.saturating_sub(2)) } else { values[i].clone() };
+        let input = Paragraph::new(text)
+            .style(Style::default().fg(theme.secondary).bg(theme.bg_lighter))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(border))
+                    .title(Span::styled(format!(" {} ", label), Style::default().fg(border))),
+            );
+        f.render_widget(input, chunks[i]);
+    }
+
+    let buttons = Paragraph::new(Line::from(vec![
+        Span::styled(" [Tab] Next field ", Style::default().fg(theme.gray)),
+        Span::raw("   "),
+        Span::styled(confirm, Style::default().fg(theme.success).add_modifier(Modifier::BOLD)),
+        Span::raw("   "),
+        Span::styled(" [Esc] Cancel ", Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
+    ]))
+    .alignment(Alignment::Center);
+    f.render_widget(buttons, chunks[labels.len() + 1]);
+}
+
+fn draw_input_popup(f: &mut Frame, theme: &Theme, title: &str, prompt: &str, input: &str, confirm: &str) {
     let area = centered_rect(60, 25, f.area());
     
     // Clear area
@@ -260,13 +655,13 @@ fn draw_create_popup(f: &mut Frame, app: &mut App) {
     let block = Block::default()
         .borders(Borders::ALL)
         .border_type(BorderType::Double)
-        .border_style(Style::default().fg(PALETTE_ACCENT))
+        .border_style(Style::default().fg(theme.accent))
         .title(Line::from(vec![
-            Span::styled(" ➕ CREATE SNAPSHOT ", Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-            Span::styled(SLANT_RIGHT, Style::default().fg(PALETTE_ACCENT).bg(PALETTE_BG_DARK)),
+            Span::styled(title, Style::default().fg(theme.bg_dark).bg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(SLANT_RIGHT, Style::default().fg(theme.accent).bg(theme.bg_dark)),
         ]))
         .title_alignment(Alignment::Left)
-        .style(Style::default().bg(PALETTE_BG_DARK));
+        .style(Style::default().bg(theme.bg_dark));
         
     let inner_area = block.inner(area);
     f.render_widget(block, area);
@@ -282,386 +677,2129 @@ fn draw_create_popup(f: &mut Frame, app: &mut App) {
         .margin(1)
         .split(inner_area);
         
-    let prompt = Paragraph::new("Enter description for the new snapshot:")
-        .style(Style::default().fg(PALETTE_FG))
+    let prompt = Paragraph::new(prompt)
+        .style(Style::default().fg(theme.fg))
         .alignment(Alignment::Center);
     f.render_widget(prompt, chunks[0]);
     
-    let input = Paragraph::new(format!("{}█", app.create_input))
-        .style(Style::default().fg(PALETTE_SECONDARY).bg(PALETTE_BG_LIGHTER))
-        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(PALETTE_GRAY)));
+    let input = Paragraph::new(input_view(input, chunks[1].width.saturating_sub(2)))
+        .style(Style::default().fg(theme.secondary).bg(theme.bg_lighter))
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(theme.gray)));
     f.render_widget(input, chunks[1]);
     
     let buttons = Paragraph::new(Line::from(vec![
-        Span::styled(" [Enter] Create ", Style::default().fg(PALETTE_SUCCESS).add_modifier(Modifier::BOLD)),
+        Span::styled(confirm, Style::default().fg(theme.success).add_modifier(Modifier::BOLD)),
         Span::raw("   "),
-        Span::styled(" [Esc] Cancel ", Style::default().fg(PALETTE_ERROR).add_modifier(Modifier::BOLD)),
+        Span::styled(" [Esc] Cancel ", Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
     ]))
     .alignment(Alignment::Center);
     f.render_widget(buttons, chunks[3]);
 }
 
-fn draw_apply_popup(f: &mut Frame, _app: &mut App) {
-    draw_popup(
-        f,
-        "⚡ APPLY SNAPSHOT ⚡",
-        "Are you sure you want to rollback to this snapshot?\n\nSystem will need a reboot to take effect.\n\n[Enter] Confirm  [Esc] Cancel",
-        PALETTE_WARNING,
-    );
-}
-
-fn draw_loading_screen(f: &mut Frame, app: &mut App) {
-    let spinner = app.spinner_frames[app.spinner_state];
-    let text = vec![
-        Line::from(Span::styled("Snapper TUI", Style::default().fg(PALETTE_SECONDARY).add_modifier(Modifier::BOLD))),
-        Line::from(""),
-        Line::from(Span::styled(format!("{} {}", app.loading_message, spinner), Style::default().fg(PALETTE_WARNING))),
-    ];
-    
-    let block = Paragraph::new(text)
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).style(Style::default().bg(PALETTE_BG_DARK)));
-    
-    // Center the loading box
-    let area = centered_rect(60, 20, f.area());
-    f.render_widget(Clear, area); // Clear background
-    f.render_widget(block, area);
-}
-
-fn intersection(r1: Rect, r2: Rect) -> Rect {
-    let x = r1.x.max(r2.x);
-    let y = r1.y.max(r2.y);
-    let width = (r1.x + r1.width).min(r2.x + r2.width).saturating_sub(x);
-    let height = (r1.y + r1.height).min(r2.y + r2.height).saturating_sub(y);
-    Rect { x, y, width, height }
+// The panel the arrow keys move gets a thick, bold border; the others a
+// double one, or a plain line in dense view
+fn panel_border(app: &App, panel: Panel, color: Color) -> (BorderType, Style) {
+    if app.focused_panel == panel {
+        (BorderType::Thick, Style::default().fg(color).add_modifier(Modifier::BOLD))
+    } else if app.dense {
+        (BorderType::Plain, Style::default().fg(color))
+    } else {
+        (BorderType::Double, Style::default().fg(color))
+    }
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
+// The end of `text` plus the cursor, as much as fits in `width` columns.
+// The cursor always sits at the end, so a long text scrolls left and marks
+// the hidden start with `…`.
+fn input_view(text: &str, width: u16) -> String {
+    let width = width as usize;
+    let char_width = |c: char| Span::raw(c.to_string()).width();
+    if Span::raw(text).width() < width {
+        return format!("{}█", text);
+    }
+    // Room for the `…` and the cursor
+    let mut room = width.saturating_sub(2);
+    let mut start = text.len();
+    for (idx, c) in text.char_indices().rev() {
+        let w = char_width(c);
+        if w > room {
+            break;
+        }
+        room -= w;
+        start = idx;
+    }
+    format!("…{}█", &text[start..])
 }
 
-fn draw_header(f: &mut Frame, app: &mut App, area: Rect) {
-    let header_text = if app.filtering {
-        vec![
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Filter: ", Style::default().fg(PALETTE_SECONDARY).add_modifier(Modifier::BOLD)),
-                Span::styled(&app.filter_input, Style::default().fg(PALETTE_FG).bg(PALETTE_BG_LIGHTER)),
-                Span::styled(" █", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::SLOW_BLINK)),
-            ]),
-            Line::from(""),
-        ]
-    } else if !app.filter_input.is_empty() {
-        vec![
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Filter: ", Style::default().fg(PALETTE_SECONDARY).add_modifier(Modifier::BOLD)),
-                Span::styled(&app.filter_input, Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(""),
-        ]
-    } else {
-        vec![
-            Line::from(""), // Empty line for spacing
-            Line::from(vec![
-                Span::styled("  🔮 SNAPPER ", Style::default().fg(PALETTE_PRIMARY).add_modifier(Modifier::BOLD)),
-                Span::styled("TUI ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled("⚡ ", Style::default().fg(PALETTE_WARNING)),
-            ]),
-            Line::from(vec![
-                Span::styled("  Cyberpunk Edition ", Style::default().fg(PALETTE_SECONDARY).add_modifier(Modifier::ITALIC)),
-            ]),
-            Line::from(""), // Empty line for spacing
-        ]
+fn draw_apply_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let message = match app.get_target_for_apply() {
+        Some(number) if app.config.confirm_apply_by_number => {
+            let hint = if app.apply_confirmed() { "[Enter] Confirm  [Esc] Cancel" } else { "[Esc] Cancel" };
+            format!(
+                "Rollback to snapshot {}?\n\nSystem will need a reboot to take effect.\n\nType {} to confirm: {}_\n\n{}",
+                number, number, app.apply_confirm_input, hint
+            )
+        }
+        _ => String::from("Are you sure you want to rollback to this snapshot?\n\nSystem will need a reboot to take effect.\n\n[Enter] Confirm  [Esc] Cancel"),
     };
-
-    let header = Paragraph::new(header_text)
-        .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Double)
-                .border_style(Style::default().fg(PALETTE_PRIMARY))
-                .style(Style::default().bg(PALETTE_BG_DARK))
-        );
-    f.render_widget(header, area);
-}
-
-fn draw_main(f: &mut Frame, app: &mut App, area: Rect) {
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50), // Snapshot list
-            Constraint::Length(1),      // Gap
-            Constraint::Min(0),         // Right Panel (Details + Status)
-        ])
-        .split(area);
-
-    draw_snapshot_table(f, app, chunks[0]);
-    // chunks[1] is gap
-    draw_right_panel(f, app, chunks[2]);
+    draw_popup(f, theme, "⚡ APPLY SNAPSHOT ⚡", &message, theme.warning);
 }
 
-fn draw_right_panel(f: &mut Frame, app: &mut App, area: Rect) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(40), // Details
-            Constraint::Length(1),      // Gap
-            Constraint::Min(0),         // Status
-        ])
-        .split(area);
-
-    draw_details_panel(f, app, chunks[0]);
-    // chunks[1] is gap
-    draw_status_panel(f, app, chunks[2]);
-}
+fn draw_diff_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let area = centered_rect(96, 94, f.area());
+    f.render_widget(Clear, area);
 
-fn draw_snapshot_table(f: &mut Frame, app: &mut App, area: Rect) {
-    use crate::app::{format_size, SortKey};
-    
-    // Modern header with primary color and sort indicators
-    let header_cells = vec![
-        Cell::from(format!("📸 #{}", app.get_sort_indicator(SortKey::Number)))
-            .style(Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_PRIMARY).add_modifier(Modifier::BOLD)),
-        Cell::from(format!("🏷️ Type{}", app.get_sort_indicator(SortKey::Type)))
-            .style(Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_PRIMARY).add_modifier(Modifier::BOLD)),
-        Cell::from(format!("📅 Date{}", app.get_sort_indicator(SortKey::Date)))
-            .style(Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_PRIMARY).add_modifier(Modifier::BOLD)),
-        Cell::from(format!("👤 User{}", app.get_sort_indicator(SortKey::User)))
-            .style(Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_PRIMARY).add_modifier(Modifier::BOLD)),
-        Cell::from(format!("💾 Space{}", app.get_sort_indicator(SortKey::UsedSpace)))
-            .style(Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_PRIMARY).add_modifier(Modifier::BOLD)),
-        Cell::from("📝 Description")
-            .style(Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_PRIMARY).add_modifier(Modifier::BOLD)),
-    ];
-    let header = Row::new(header_cells)
-        .style(Style::default().bg(PALETTE_PRIMARY))
-        .height(1);
+    let range = app
+        .status_range
+        .as_ref()
+        .map(|r| format!(" ({}..{})", r.pre, r.post))
+        .unwrap_or_default();
 
-    let snapshots = app.get_filtered_snapshots();
-    
-    // Zebra striping with modern colors
-    let rows: Vec<Row> = snapshots.iter().enumerate().map(|(idx, item)| {
-        let is_selected = app.selected_indices.contains(&idx);
-        let selection_marker = if is_selected { "✅ " } else { "" };
-        
-        let cells = vec![
-            Cell::from(format!("{}{}", selection_marker, item.number)),
-            Cell::from(item.snapshot_type.clone()),
-            Cell::from(item.date.clone()),
-            Cell::from(item.user.clone()),
-            Cell::from(item.used_space.map(|s| format_size(s)).unwrap_or_default()),
-            Cell::from(item.description.clone()),
-        ];
-        // Zebra striping
-        let bg = if idx % 2 == 0 { PALETTE_BG_DARK } else { PALETTE_BG_LIGHTER };
-        Row::new(cells).height(1).style(Style::default().bg(bg).fg(PALETTE_FG))
-    }).collect();
+    let lines: Vec<Line> = app
+        .diff_text
+        .lines()
+        .map(|line| Line::from(Span::styled(line, diff_line_style(theme, line))))
+        .collect();
+    // Borders take a column on each side
+    app.diff_max_hscroll = max_hscroll(&lines, area.width.saturating_sub(2));
+    app.diff_hscroll = app.diff_hscroll.min(app.diff_max_hscroll);
 
-    let t = Table::new(
-        rows,
-        [
-            Constraint::Length(8),
-            Constraint::Length(10),
-            Constraint::Length(22),
-            Constraint::Length(12),
-            Constraint::Length(12),
-            Constraint::Min(10),
-        ],
-    )
-    .header(header)
+    let para = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Double)
-                .border_style(Style::default().fg(PALETTE_SECONDARY))
+                .border_style(Style::default().fg(theme.secondary))
                 .title(Line::from(vec![
-                    Span::styled(" 📦 SNAPSHOTS ", Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_SECONDARY).add_modifier(Modifier::BOLD)),
-                    Span::styled(SLANT_RIGHT, Style::default().fg(PALETTE_SECONDARY).bg(PALETTE_BG_DARK)),
+                    Span::styled(format!(" 🧬 DIFF {}{} ", app.diff_path, range), Style::default().fg(theme.bg_dark).bg(theme.secondary).add_modifier(Modifier::BOLD)),
+                    Span::styled(SLANT_RIGHT, Style::default().fg(theme.secondary).bg(theme.bg_dark)),
                 ]))
                 .title_alignment(Alignment::Left)
-                .style(Style::default().bg(PALETTE_BG_DARK))
+                .title_bottom(Line::from(Span::styled(" [↑/↓/←/→] Scroll  [U] Undo change  [Esc] Close ", Style::default().fg(theme.gray))).right_aligned())
+                .style(Style::default().bg(theme.bg_dark))
         )
-        .highlight_style(Style::default().bg(PALETTE_ACCENT).fg(PALETTE_BG_DARK).add_modifier(Modifier::BOLD))
-        .highlight_symbol("👉 ");
+        .scroll((app.diff_scroll, app.diff_hscroll));
+    f.render_widget(para, area);
+}
 
-    f.render_stateful_widget(t, area, &mut app.table_state);
+// Unified diff colouring: file headers, hunk markers, then added/removed lines
+fn diff_line_style(theme: &Theme, line: &str) -> Style {
+    if line.starts_with("+++") || line.starts_with("---") {
+        Style::default().fg(theme.gray).add_modifier(Modifier::BOLD)
+    } else if line.starts_with("@@") {
+        Style::default().fg(theme.secondary)
+    } else if line.starts_with('+') {
+        Style::default().fg(theme.success)
+    } else if line.starts_with('-') {
+        Style::default().fg(theme.error)
+    } else {
+        Style::default().fg(theme.fg)
+    }
 }
 
-fn draw_details_panel(f: &mut Frame, app: &mut App, area: Rect) {
-    let selected = app.get_selected_snapshot();
+// Newest first, each command under its time and outcome
+fn draw_history_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let area = centered_rect(80, 70, f.area());
+    f.render_widget(Clear, area);
 
-    let content = if let Some(snap) = selected {
-        let userdata_str = snap.userdata.as_ref().map(|m| {
-            m.iter()
-                .map(|(k, v)| format!("{}: {}", k, v))
-                .collect::<Vec<_>>()
-                .join(", ")
-        }).unwrap_or_default();
+    let entries = history::entries();
+    let mut lines: Vec<Line> = Vec::new();
+    for entry in entries.iter().rev() {
+        let color = if entry.succeeded() { theme.success } else { theme.error };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{}  ", entry.time), Style::default().fg(theme.gray)),
+            Span::styled(entry.result.clone(), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+        ]));
+        lines.push(Line::from(Span::styled(format!("  $ {}", entry.command_line()), Style::default().fg(theme.fg))));
+        for line in entry.stderr.lines() {
+            lines.push(Line::from(Span::styled(format!("    {}", line), Style::default().fg(theme.warning))));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled("No privileged commands have run yet.", Style::default().fg(theme.gray))));
+    }
 
-        vec![
-            Line::from(vec![
-                Span::styled("⚙️ Config: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(&snap.config, Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(vec![
-                Span::styled("📂 Subvolume: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(&snap.subvolume, Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(vec![
-                Span::styled("🔢 Number: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(snap.number.to_string(), Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(vec![
-                Span::styled("🏷️ Type: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(&snap.snapshot_type, Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(vec![
-                Span::styled("📅 Date: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(&snap.date, Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(vec![
-                Span::styled("👤 User: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(&snap.user, Style::default().fg(PALETTE_SUCCESS)),
-            ]),
-            Line::from(vec![
-                Span::styled("🧹 Cleanup: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(snap.cleanup.as_deref().unwrap_or("-"), Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(vec![
-                Span::styled("📝 Description: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(&snap.description, Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(vec![
-                Span::styled("💾 Used Space: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(snap.used_space.map(|s| s.to_string()).unwrap_or_default(), Style::default().fg(PALETTE_FG)),
-            ]),
-            Line::from(vec![
-                Span::styled("📋 Userdata: ", Style::default().fg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                Span::styled(userdata_str, Style::default().fg(PALETTE_FG)),
-            ]),
-        ]
-    } else {
-        vec![Line::from(Span::styled("No snapshot selected.", Style::default().fg(PALETTE_GRAY).add_modifier(Modifier::ITALIC)))]
-    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(Style::default().fg(theme.primary))
+        .title(Line::from(vec![
+            Span::styled(format!(" 📜 HISTORY ({}) ", entries.len()), Style::default().fg(theme.bg_dark).bg(theme.primary).add_modifier(Modifier::BOLD)),
+            Span::styled(SLANT_RIGHT, Style::default().fg(theme.primary).bg(theme.bg_dark)),
+        ]))
+        .title_alignment(Alignment::Left)
+        .title_bottom(Line::from(Span::styled(" [↑/↓] Scroll  [any key] Close ", Style::default().fg(theme.gray))).right_aligned())
+        .style(Style::default().bg(theme.bg_dark));
+    let inner = block.inner(area);
 
-    let para = Paragraph::new(content)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Double)
-                .border_style(Style::default().fg(PALETTE_ACCENT))
-                .title(Line::from(vec![
-                    Span::styled(" 🔍 DETAILS ", Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_ACCENT).add_modifier(Modifier::BOLD)),
-                    Span::styled(SLANT_RIGHT, Style::default().fg(PALETTE_ACCENT).bg(PALETTE_BG_DARK)),
-                ]))
-                .title_alignment(Alignment::Left)
-                .style(Style::default().bg(PALETTE_BG_DARK))
-        )
-        .wrap(Wrap { trim: true })
-        .scroll((app.details_scroll as u16, 0));
+    let para = Paragraph::new(lines).wrap(Wrap { trim: false });
+    let max_scroll = max_scroll(para.line_count(inner.width), inner.height);
+    let scroll = app.history_scroll.min(max_scroll);
 
-    f.render_widget(para, area);
+    f.render_widget(para.block(block).scroll((scroll, 0)), area);
+    app.history_max_scroll = max_scroll;
+    app.history_scroll = scroll;
 }
 
-fn draw_status_panel(f: &mut Frame, app: &mut App, area: Rect) {
-    let mut title = String::from(" ℹ️ STATUS ");
-    if app.loading {
-        title.push_str(&format!(" {}", app.spinner_frames[app.spinner_state]));
+fn draw_help_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let area = centered_rect(60, 70, f.area());
+    f.render_widget(Clear, area);
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (section, keys) in HELP_SECTIONS {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(*section, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))));
+        for (key, action) in keys.iter() {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<12}", key), Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD)),
+                Span::styled(*action, Style::default().fg(theme.fg)),
+            ]));
+        }
+    }
+
+    // The markers come from the theme, so this section isn't in HELP_SECTIONS
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Legend", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))));
+    for (marker, meaning) in [
+        (theme.default_marker, "Default snapshot (booted next)"),
+        (theme.active_marker, "Active snapshot (running now, row in bold)"),
+        (ORPHAN_MARKER.trim(), "Pre snapshot with no post yet (operation running or interrupted)"),
+        (CREATED_MARKER.trim(), "Created with this TUI (tagged in its userdata)"),
+    ] {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<12}", marker), Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD)),
+            Span::styled(meaning, Style::default().fg(theme.fg)),
+        ]));
+    }
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(Style::default().fg(theme.primary))
+        .title(Line::from(vec![
+            Span::styled(" ❓ HELP ", Style::default().fg(theme.bg_dark).bg(theme.primary).add_modifier(Modifier::BOLD)),
+            Span::styled(SLANT_RIGHT, Style::default().fg(theme.primary).bg(theme.bg_dark)),
+        ]))
+        .title_alignment(Alignment::Left)
+        .title_bottom(Line::from(Span::styled(" [↑/↓] Scroll  [any key] Close ", Style::default().fg(theme.gray))).right_aligned())
+        .style(Style::default().bg(theme.bg_dark));
+    let inner = block.inner(area);
+
+    let para = Paragraph::new(lines);
+    let max_scroll = max_scroll(para.line_count(inner.width), inner.height);
+    let scroll = app.help_scroll.min(max_scroll);
+
+    f.render_widget(para.block(block).scroll((scroll, 0)), area);
+    app.help_max_scroll = max_scroll;
+    app.help_scroll = scroll;
+}
+
+fn draw_undo_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    const MAX_LISTED: usize = 6;
+
+    let pre = app.status_range.as_ref().map(|r| r.pre).unwrap_or_default();
+    let mut message = format!("Revert {} file(s) to their state in snapshot {}?\n\n", app.undo_targets.len(), pre);
+    for path in app.undo_targets.iter().take(MAX_LISTED) {
+        message.push_str(path);
+        message.push('\n');
+    }
+    if app.undo_targets.len() > MAX_LISTED {
+        message.push_str(&format!("…and {} more\n", app.undo_targets.len() - MAX_LISTED));
     }
+    message.push_str("\n[Enter] Confirm  [Esc] Cancel");
+
+    draw_popup(
+        f,
+        theme,
+        "↩️ UNDO CHANGES ↩️",
+        &message,
+        theme.warning,
+    );
+}
+
+fn draw_theme_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let area = centered_rect(40, 30, f.area());
+    f.render_widget(Clear, area);
+
+    let lines: Vec<Line> = THEMES
+        .iter()
+        .enumerate()
+        .map(|(idx, preset)| {
+            let active = preset.name == theme.name;
+            let marker = if idx == app.theme_cursor { "👉 " } else { "   " };
+            let check = if active { " ✓" } else { "" };
+            let style = if idx == app.theme_cursor {
+                theme.highlight(Style::default().fg(theme.bg_dark).bg(theme.accent).add_modifier(Modifier::BOLD))
+            } else {
+                Style::default().fg(theme.fg)
+            };
+            Line::from(vec![
+                Span::styled(format!("{}{}{}", marker, preset.name, check), style),
+                Span::raw("  "),
+                // Swatch of the preset's main colours
+                Span::styled("██", Style::default().fg(preset.primary)),
+                Span::styled("██", Style::default().fg(preset.secondary)),
+                Span::styled("██", Style::default().fg(preset.accent)),
+                Span::styled("██", Style::default().fg(preset.success)),
+            ])
+        })
+        .collect();
+
+    let para = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(theme.primary))
+            .title(Line::from(vec![
+                Span::styled(" 🎨 THEME ", Style::default().fg(theme.bg_dark).bg(theme.primary).add_modifier(Modifier::BOLD)),
+                Span::styled(SLANT_RIGHT, Style::default().fg(theme.primary).bg(theme.bg_dark)),
+            ]))
+            .title_alignment(Alignment::Left)
+            .title_bottom(Line::from(Span::styled(" [Enter] Apply  [Esc] Cancel ", Style::default().fg(theme.gray))).right_aligned())
+            .style(Style::default().bg(theme.bg_dark)),
+    );
+    f.render_widget(para, area);
+}
+
+fn draw_cleanup_popup(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let area = centered_rect(50, 30, f.area());
+    f.render_widget(Clear, area);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Delete snapshots of '{}' that its cleanup policy no longer keeps:", app.info_config_name()),
+            Style::default().fg(theme.fg),
+        )),
+        Line::from(""),
+    ];
+    lines.extend(CLEANUP_ALGORITHMS.iter().enumerate().map(|(idx, algorithm)| {
+        let (marker, style) = if idx == app.cleanup_cursor {
+            ("👉 ", theme.highlight(Style::default().fg(theme.bg_dark).bg(theme.accent).add_modifier(Modifier::BOLD)))
+        } else {
+            ("   ", Style::default().fg(theme.fg))
+        };
+        Line::from(Span::styled(format!("{}{}", marker, algorithm), style))
+    }));
+
+    let para = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(theme.warning))
+            .title(Line::from(vec![
+                Span::styled(" 🧹 CLEANUP ", Style::default().fg(theme.bg_dark).bg(theme.warning).add_modifier(Modifier::BOLD)),
+                Span::styled(SLANT_RIGHT, Style::default().fg(theme.warning).bg(theme.bg_dark)),
+            ]))
+            .title_alignment(Alignment::Left)
+            .title_bottom(Line::from(Span::styled(" [Enter] Run  [Esc] Cancel ", Style::default().fg(theme.gray))).right_aligned())
+            .style(Style::default().bg(theme.bg_dark)),
+    );
+    f.render_widget(para, area);
+}
 
-    let mut lines: Vec<Line> = vec![
-        Line::from(Span::styled(&app.message, Style::default().fg(if app.loading { PALETTE_WARNING } else { PALETTE_SUCCESS }))),
+fn draw_loading_screen(f: &mut Frame, theme: &Theme, app: &mut App) {
+    let spinner = app.spinner_frames[app.spinner_state];
+    let mut text = vec![
+        Line::from(Span::styled("Snapper TUI", Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled(format!("{} {}", app.loading_message, spinner), Style::default().fg(theme.warning))),
         Line::from(""),
     ];
+    // Operations that know their size get a gauge on the next row
+    let gauge_row = text.len() as u16;
+    if app.loading_progress.is_some() {
+        text.push(Line::from(""));
+        text.push(Line::from(""));
+    }
+    // Counting up tells a slow operation from a hung one
+    let elapsed = app.loading_start.map_or(0, |start| start.elapsed().as_secs());
+    if elapsed > 0 {
+        text.push(Line::from(Span::styled(format!("⏱ {}", format_elapsed(elapsed)), Style::default().fg(theme.gray))));
+    }
+    if app.config.slow_hint_secs.is_some_and(|secs| elapsed >= secs) {
+        let style = Style::default().fg(theme.warning).add_modifier(Modifier::BOLD);
+        text.push(Line::from(Span::styled("Still working. Press [Esc] to cancel it.", style)));
+    } else {
+        text.push(Line::from(Span::styled("[Esc] Cancel", Style::default().fg(theme.gray))));
+    }
+    
+    let outer = Block::default().borders(Borders::ALL).border_type(BorderType::Rounded).style(Style::default().bg(theme.bg_dark));
     
-    for line in app.status_text.lines() {
-        lines.push(Line::from(Span::styled(line, Style::default().fg(PALETTE_FG))));
+    // Center the loading box, tall enough for every line on small terminals
+    let mut area = centered_rect(60, 20, f.area());
+    let needed = (text.len() as u16 + 2).min(f.area().height);
+    if area.height < needed {
+        area.y = f.area().y + (f.area().height - needed) / 2;
+        area.height = needed;
+    }
+    let inner = outer.inner(area);
+    let block = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .block(outer);
+    f.render_widget(Clear, area); // Clear background
+    f.render_widget(block, area);
+
+    if let Some((done, total)) = app.loading_progress
+        && gauge_row < inner.height
+    {
+        let ratio = if total == 0 { 1.0 } else { done.min(total) as f64 / total as f64 };
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(theme.accent).bg(theme.bg_lighter))
+            .label(Span::styled(format!("{}/{}", done, total), Style::default().fg(theme.fg).add_modifier(Modifier::BOLD)))
+            .ratio(ratio);
+        let row = Rect { y: inner.y + gauge_row, height: 1, ..inner };
+        f.render_widget(gauge, row.inner(Margin { horizontal: 2, vertical: 0 }));
     }
+}
+
+// `42s`, or `3m 05s` from a minute on
+fn format_elapsed(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        _ => format!("{}m {:02}s", secs / 60, secs % 60),
+    }
+}
+
+fn draw_too_small(f: &mut Frame, theme: &Theme, area: Rect) {
+    let text = vec![
+        Line::from(Span::styled(
+            format!("Terminal too small (need ≥{}x{})", MIN_WIDTH, MIN_HEIGHT),
+            Style::default().fg(theme.warning).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(format!("now {}x{}", area.width, area.height), Style::default().fg(theme.gray))),
+    ];
+    // Wrapping may take a few more lines in a very narrow pane
+    let top = area.height.saturating_sub(2) / 2;
+    let body = Rect { y: area.y + top, height: area.height - top, ..area };
+    f.render_widget(Block::default().style(Style::default().bg(theme.bg_dark)), area);
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center).wrap(Wrap { trim: true }), body);
+}
 
-    let status = Paragraph::new(lines)
+fn draw_fatal_screen(f: &mut Frame, theme: &Theme, error: &str) {
+    let text = vec![
+        Line::from(Span::styled("Snapper TUI", Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled(format!("❌ {}", error), Style::default().fg(theme.error).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled("[Q] Quit", Style::default().fg(theme.gray))),
+    ];
+
+    let block = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Double)
-                .border_style(Style::default().fg(PALETTE_WARNING))
-                .title(Line::from(vec![
-                    Span::styled(title, Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_WARNING).add_modifier(Modifier::BOLD)),
-                    Span::styled(SLANT_RIGHT, Style::default().fg(PALETTE_WARNING).bg(PALETTE_BG_DARK)),
-                ]))
-                .title_alignment(Alignment::Left)
-                .style(Style::default().bg(PALETTE_BG_DARK))
-        )
-        .wrap(Wrap { trim: true })
-        .scroll((app.status_scroll as u16, 0));
-    f.render_widget(status, area);
+                .border_style(Style::default().fg(theme.error))
+                .style(Style::default().bg(theme.bg_dark)),
+        );
+
+    f.render_widget(Clear, f.area());
+    f.render_widget(block, centered_rect(70, 30, f.area()));
 }
 
-fn draw_actions_bar(f: &mut Frame, area: Rect) {
-    let actions_text = vec![
-        Span::styled(" ⚡ ACTIONS: ", Style::default().fg(PALETTE_PRIMARY).add_modifier(Modifier::BOLD)),
-        
-        // Create
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_ACCENT).bg(PALETTE_BG_DARK)),
-        Span::styled(" [C]reate ➕ ", Style::default().bg(PALETTE_ACCENT).fg(PALETTE_BG_DARK).add_modifier(Modifier::BOLD)),
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_ACCENT)),
-        Span::raw(" "),
-
-        // Delete
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_ERROR).bg(PALETTE_BG_DARK)),
-        Span::styled(" [D]elete 🗑️  ", Style::default().bg(PALETTE_ERROR).fg(PALETTE_BG_DARK).add_modifier(Modifier::BOLD)),
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_ERROR)),
-        Span::raw(" "),
-
-        // Apply
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_SUCCESS).bg(PALETTE_BG_DARK)),
-        Span::styled(" [A]pply ↩️  ", Style::default().bg(PALETTE_SUCCESS).fg(PALETTE_BG_DARK).add_modifier(Modifier::BOLD)),
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_SUCCESS)),
-        Span::raw(" "),
-
-        // Filter
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_PRIMARY).bg(PALETTE_BG_DARK)),
-        Span::styled(" [/] Filter 🔍 ", Style::default().bg(PALETTE_PRIMARY).fg(PALETTE_BG_DARK).add_modifier(Modifier::BOLD)),
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_PRIMARY)),
-        Span::raw(" "),
-
-        // Status
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_SECONDARY).bg(PALETTE_BG_DARK)),
-        Span::styled(" [S]tatus ℹ️  ", Style::default().bg(PALETTE_SECONDARY).fg(PALETTE_BG_DARK).add_modifier(Modifier::BOLD)),
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_SECONDARY)),
-        Span::raw(" "),
-
-        // Refresh
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_WARNING).bg(PALETTE_BG_DARK)),
-        Span::styled(" [R]efresh 🔄 ", Style::default().bg(PALETTE_WARNING).fg(PALETTE_BG_DARK).add_modifier(Modifier::BOLD)),
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_WARNING)),
-        Span::raw(" "),
-
-        // Quit
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_GRAY).bg(PALETTE_BG_DARK)),
-        Span::styled(" [Q]uit 🚪 ", Style::default().bg(PALETTE_GRAY).fg(PALETTE_BG_DARK).add_modifier(Modifier::BOLD)),
-        Span::styled(SLANT_LEFT, Style::default().fg(PALETTE_BG_DARK).bg(PALETTE_GRAY)),
-    ];
-    
-    let actions = Paragraph::new(Line::from(actions_text))
+fn intersection(r1: Rect, r2: Rect) -> Rect {
+    let x = r1.x.max(r2.x);
+    let y = r1.y.max(r2.y);
+    let width = (r1.x + r1.width).min(r2.x + r2.width).saturating_sub(x);
+    let height = (r1.y + r1.height).min(r2.y + r2.height).saturating_sub(y);
+    Rect { x, y, width, height }
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
+
+// " 🥾 booted #7 · default #9 ", or `None` when neither is a snapshot
+fn boot_lineage_label(lineage: BootLineage) -> Option<String> {
+    let describe = |number: Option<u32>| number.map_or(String::from("subvolume"), |n| format!("#{}", n));
+    if lineage.booted.is_none() && lineage.default.is_none() {
+        return None;
+    }
+    let mut label = format!(" 🥾 booted {} · default {} ", describe(lineage.booted), describe(lineage.default));
+    if lineage.booted != lineage.default {
+        label.push_str("(after reboot) ");
+    }
+    Some(label)
+}
+
+// "  3 of 120  " after the filter input
+fn filter_count_span<'a>(theme: &Theme, app: &App) -> Span<'a> {
+    let (matches, total) = app.filter_counts();
+    let color = if matches == 0 { theme.error } else { theme.gray };
+    Span::styled(format!("  {} of {}  ", matches, total), Style::default().fg(color))
+}
+
+// The case and whole-word toggles next to the filter prompt; `editing`
+// also lists the ones that are off, with their keys
+fn filter_option_spans<'a>(theme: &Theme, app: &App, editing: bool) -> Vec<Span<'a>> {
+    let options = [("^S", " Aa ", app.filter_case_sensitive), ("^W", " Word ", app.filter_whole_word)];
+    options
+        .into_iter()
+        .filter(|&(_, _, on)| on || editing)
+        .flat_map(|(key, label, on)| {
+            let style = match on {
+                true => theme.highlight(Style::default().fg(theme.bg_dark).bg(theme.accent).add_modifier(Modifier::BOLD)),
+                false => Style::default().fg(theme.gray),
+            };
+            let key = editing.then(|| Span::styled(format!("{} ", key), Style::default().fg(theme.gray)));
+            key.into_iter().chain([Span::styled(label, style), Span::raw(" ")])
+        })
+        .collect()
+}
+
+// Why the Space column is blank for the highlighted snapshot's config; the
+// key to fix it is only offered where setup-quota can work
+fn quota_hint(app: &App) -> Option<String> {
+    let config = app.info_config_name();
+    if !app.used_space_missing(&config) {
+        return None;
+    }
+    let btrfs = app.config_fs.get(&config).and_then(Option::as_ref).is_some_and(|fs| fs.is_btrfs());
+    let action = if btrfs { " [J]" } else { "" };
+    Some(format!(" ⚠ used-space unavailable — run snapper setup-quota{} ", action))
+}
+
+// The dense header has no bottom border, so what goes there shares its one
+// line with the top titles
+fn header_bottom<'a>(block: Block<'a>, dense: bool, line: Line<'a>) -> Block<'a> {
+    if dense { block.title_top(line) } else { block.title_bottom(line) }
+}
+
+fn draw_header(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    let header_text = if app.filtering {
+        vec![
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Filter: ", Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD)),
+                Span::styled(&app.filter_input, Style::default().fg(theme.fg).bg(theme.bg_lighter)),
+                Span::styled(" █", Style::default().fg(theme.accent).add_modifier(Modifier::SLOW_BLINK)),
+                filter_count_span(theme, app),
+            ]
+            .into_iter()
+            .chain(filter_option_spans(theme, app, true))
+            .collect::<Vec<_>>()),
+            Line::from(""),
+        ]
+    } else if !app.filter_input.is_empty() {
+        vec![
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Filter: ", Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD)),
+                Span::styled(&app.filter_input, Style::default().fg(theme.fg)),
+                filter_count_span(theme, app),
+            ]
+            .into_iter()
+            .chain(filter_option_spans(theme, app, false))
+            .collect::<Vec<_>>()),
+            Line::from(""),
+        ]
+    } else {
+        vec![
+            Line::from(""), // Empty line for spacing
+            Line::from(vec![
+                Span::styled("  🔮 SNAPPER ", Style::default().fg(theme.primary).add_modifier(Modifier::BOLD)),
+                Span::styled("TUI ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+                Span::styled("⚡ ", Style::default().fg(theme.warning)),
+            ]),
+            Line::from(vec![
+                Span::styled("  Cyberpunk Edition ", Style::default().fg(theme.secondary).add_modifier(Modifier::ITALIC)),
+            ]),
+            Line::from(""), // Empty line for spacing
+        ]
+    };
+
+    let mut block = Block::default().style(Style::default().bg(theme.bg_dark));
+    if !app.dense {
+        block = block.borders(Borders::ALL).border_type(BorderType::Double).border_style(Style::default().fg(theme.primary));
+    }
+
+    if app.select_anchor.is_some() {
+        block = block.title(Line::from(Span::styled(
+            " -- VISUAL -- ",
+            Style::default().fg(theme.bg_dark).bg(theme.warning).add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    if let Some(destination) = &app.remote {
+        block = block.title(Line::from(Span::styled(
+            format!(" 🌐 {} ", destination),
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    // The bracket opened with a pre snapshot stays in sight until it's closed
+    if let Some((_, pre)) = &app.pending_pre {
+        block = block.title(Line::from(Span::styled(
+            format!(" ⏳ pre #{} open · [>] post ", pre),
+            Style::default().fg(theme.warning).add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    let selected = app.get_selected_count();
+    if selected > 0 {
+        block = block.title(Line::from(Span::styled(
+            format!(" ✅ {} selected ", selected),
+            Style::default().fg(theme.bg_dark).bg(theme.success).add_modifier(Modifier::BOLD),
+        )).right_aligned());
+    }
+
+    if let Some(label) = app.boot_lineage.and_then(boot_lineage_label) {
+        // A default other than the booted snapshot is a rollback waiting for a reboot
+        let pending = app.boot_lineage.is_some_and(|l| l.booted != l.default);
+        let color = if pending { theme.warning } else { theme.gray };
+        block = block.title(Line::from(Span::styled(label, Style::default().fg(color))).centered());
+    }
+
+    if app.read_only {
+        block = header_bottom(block, app.dense, Line::from(Span::styled(
+            format!(" 🔒 READ-ONLY: {} not found; listing and status only ", app.config.privilege.name()),
+            Style::default().fg(theme.bg_dark).bg(theme.warning).add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    if !app.hidden_types.is_empty() {
+        // In key order rather than the set's
+        let hidden: Vec<&str> = HIDEABLE_TYPES.into_iter().filter(|t| app.hidden_types.contains(*t)).collect();
+        block = header_bottom(block, app.dense, Line::from(Span::styled(
+            format!(" 🙈 hiding {} ", hidden.join(", ")),
+            Style::default().fg(theme.warning),
+        )).centered());
+    }
+
+    if let Some(hint) = quota_hint(app) {
+        block = header_bottom(block, app.dense, Line::from(Span::styled(hint, Style::default().fg(theme.warning))).centered());
+    }
+
+    if let Some((used, total)) = app.fs_usage
+        && total > 0
+    {
+        const BAR_WIDTH: usize = 10;
+        let ratio = used as f64 / total as f64;
+        let color = if ratio >= 0.95 {
+            theme.error
+        } else if ratio >= 0.80 {
+            theme.warning
+        } else {
+            theme.success
+        };
+        let filled = ((ratio * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+        block = header_bottom(block, app.dense, Line::from(vec![
+            Span::styled(" 💾 ", Style::default().fg(theme.gray)),
+            Span::styled("█".repeat(filled), Style::default().fg(color)),
+            Span::styled("░".repeat(BAR_WIDTH - filled), Style::default().fg(theme.bg_lighter)),
+            Span::styled(
+                format!(" {} / {} ({:.0}%) ", format_size(used), format_size(total), ratio * 100.0),
+                Style::default().fg(color),
+            ),
+        ]).right_aligned());
+    }
+
+    if let Some(refreshed) = app.last_refresh {
+        block = header_bottom(block, app.dense, Line::from(Span::styled(
+            format!(" 🔄 {}s ago ", refreshed.elapsed().as_secs()),
+            Style::default().fg(theme.gray),
+        )));
+    }
+
+    // The dense header's one line shows the filter while there is one and
+    // the titles otherwise
+    if app.dense {
+        if app.filtering || !app.filter_input.is_empty() {
+            let filter_line = header_text.into_iter().nth(1).unwrap_or_default();
+            f.render_widget(Paragraph::new(filter_line).style(Style::default().bg(theme.bg_dark)), area);
+        } else {
+            f.render_widget(block, area);
+        }
+        return;
+    }
+
+    let header = Paragraph::new(header_text)
         .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL).border_type(BorderType::Double).border_style(Style::default().fg(PALETTE_GRAY)).style(Style::default().bg(PALETTE_BG_DARK)));
-    f.render_widget(actions, area);
+        .block(block);
+    f.render_widget(header, area);
+}
+
+fn draw_main(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    if f.area().width < STACKED_WIDTH {
+        draw_stacked(f, theme, app, area);
+        return;
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(50), // Snapshot list
+            Constraint::Length(1),      // Gap
+            Constraint::Min(0),         // Right Panel (Details + Status)
+        ])
+        .split(area);
+
+    app.hit_areas.table = chunks[0];
+    draw_snapshot_table(f, theme, app, chunks[0]);
+    // chunks[1] is gap
+    draw_right_panel(f, theme, app, chunks[2]);
+}
+
+// Narrow layout: the table across the full width, with details and status
+// sharing one tabbed panel below it; Tab switches between them
+fn draw_stacked(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(50), // Snapshot list
+            Constraint::Length(1),      // Tabs
+            Constraint::Min(0),         // Details or status
+        ])
+        .split(area);
+
+    app.hit_areas.table = chunks[0];
+    draw_snapshot_table(f, theme, app, chunks[0]);
+
+    let selected = if app.side_tab == Panel::Status { 1 } else { 0 };
+    let tabs = Tabs::new(vec![" 🔍 Details ", " 📊 Status "])
+        .select(selected)
+        .style(Style::default().fg(theme.gray).bg(theme.bg_dark))
+        .highlight_style(theme.highlight(Style::default().fg(theme.bg_dark).bg(theme.secondary).add_modifier(Modifier::BOLD)))
+        .divider("│");
+    f.render_widget(tabs, chunks[1]);
+
+    // Only the visible panel takes mouse scrolling
+    if app.side_tab == Panel::Status {
+        app.hit_areas.details = Rect::default();
+        app.hit_areas.status = chunks[2];
+        if app.browse.is_some() {
+            draw_browse_panel(f, theme, app, chunks[2]);
+        } else {
+            draw_status_panel(f, theme, app, chunks[2]);
+        }
+    } else {
+        app.hit_areas.details = chunks[2];
+        app.hit_areas.status = Rect::default();
+        if app.show_config_info {
+            draw_config_panel(f, theme, app, chunks[2]);
+        } else {
+            draw_details_panel(f, theme, app, chunks[2]);
+        }
+    }
+}
+
+fn draw_right_panel(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(40), // Details
+            Constraint::Length(1),      // Gap
+            Constraint::Min(0),         // Status
+        ])
+        .split(area);
+    app.hit_areas.details = chunks[0];
+    app.hit_areas.status = chunks[2];
+
+    if app.show_config_info {
+        draw_config_panel(f, theme, app, chunks[0]);
+    } else {
+        draw_details_panel(f, theme, app, chunks[0]);
+    }
+    // chunks[1] is gap
+    if app.browse.is_some() {
+        draw_browse_panel(f, theme, app, chunks[2]);
+    } else {
+        draw_status_panel(f, theme, app, chunks[2]);
+    }
+}
+
+fn draw_snapshot_table(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    let display_rows = app.get_display_rows();
+
+    // The label column only takes room once something in view has a label
+    let show_labels = display_rows.iter().any(|&row| app.row_label(row).is_some());
+    let columns: Vec<Column> =
+        app.config.columns.iter().copied().filter(|&column| show_labels || column != Column::Label).collect();
+
+    // Modern header with primary color and sort indicators
+    let header_titles: Vec<String> = columns.iter().map(|&column| column_title(app, column)).collect();
+    let header = Row::new(header_titles.iter().map(|title| {
+        Cell::from(title.clone()).style(Style::default().fg(theme.bg_dark).bg(theme.primary).add_modifier(Modifier::BOLD))
+    }))
+    .style(Style::default().bg(theme.primary))
+    .height(1);
+
+    // Only the rows that fit in the viewport are built each frame
+    let viewport = area.height.saturating_sub(3) as usize; // Borders + header
+    let selected = app.table_state.selected().map(|i| i.min(display_rows.len().saturating_sub(1)));
+    let (start, end) = visible_window(selected, app.table_state.offset(), display_rows.len(), viewport);
+
+    // Tokens narrow by other fields, so a highlight would only mislead
+    let filter = app.filter();
+    let highlight = !filter.text.is_empty() && !filter.has_tokens();
+
+    // Sized from every filtered row so columns don't jump while scrolling,
+    // and only measured again once something they depend on changes
+    let orphans = app.orphan_pre_numbers();
+    let widths_key = WidthsKey::of(app, &columns, &header_titles, display_rows.len());
+    let widths = match &app.table_widths {
+        Some((key, widths)) if *key == widths_key => widths.clone(),
+        _ => column_widths(
+            &columns,
+            &header_titles,
+            (0..display_rows.len()).map(|idx| row_texts(theme, app, &display_rows, idx, &orphans, &columns)),
+        ),
+    };
+    let constraints: Vec<Constraint> = columns
+        .iter()
+        .zip(&widths)
+        .map(|(&column, &width)| match column {
+            Column::Description => Constraint::Min(10),
+            _ => Constraint::Length(width),
+        })
+        .collect();
+
+    // Zebra striping with modern colors
+    let rows: Vec<Row> = (start..end).map(|idx| {
+        let row = display_rows[idx];
+        let texts = row_texts(theme, app, &display_rows, idx, &orphans, &columns);
+        let cells = columns.iter().zip(texts).map(|(&column, text)| match column {
+            Column::Type => Cell::from(type_cell(theme, row, &orphans)),
+            Column::Delta => Cell::from(Span::styled(text, delta_style(theme, row_delta(app, row)))),
+            Column::Label => Cell::from(Span::styled(text, Style::default().fg(theme.secondary))),
+            Column::Description if highlight => Cell::from(highlight_matches(theme, text, &filter)),
+            _ => Cell::from(text),
+        });
+        // Zebra striping
+        let bg = if idx % 2 == 0 { theme.bg_dark } else { theme.bg_lighter };
+        let style = if row.snapshots().iter().any(|s| s.active) {
+            Style::default().bg(bg).fg(theme.accent).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().bg(bg).fg(theme.fg)
+        };
+        Row::new(cells).height(1).style(style)
+    }).collect();
+
+    // Where the header cells land, laid out as the table lays out its
+    // columns: inside the borders, after the highlight symbol's gutter
+    let inner = area.inner(Margin { horizontal: 1, vertical: 1 });
+    let gutter = if selected.is_some() { Span::raw(HIGHLIGHT_SYMBOL).width() as u16 } else { 0 };
+    let header_area = Rect { x: inner.x + gutter.min(inner.width), width: inner.width.saturating_sub(gutter), height: 1, ..inner };
+    let cells = Layout::horizontal(constraints.clone()).spacing(1).split(header_area);
+    let sort_headers: Vec<(Rect, SortKey)> =
+        columns.iter().zip(cells.iter()).filter_map(|(column, &rect)| Some((rect, column.sort_key()?))).collect();
+
+    // Totals for the filtered view, i.e. what a bulk delete would touch
+    let summary = match app.filtered_summary() {
+        (1, Some(space)) => format!(" 1 snapshot · {} ", format_size(space)),
+        (1, None) => String::from(" 1 snapshot "),
+        (count, Some(space)) => format!(" {} snapshots · {} ", count, format_size(space)),
+        (count, None) => format!(" {} snapshots ", count),
+    };
+
+    let (border_type, border_style) = panel_border(app, Panel::Table, theme.secondary);
+    let t = Table::new(rows, constraints)
+    .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(border_type)
+                .border_style(border_style)
+                .title(Line::from(vec![
+                    Span::styled(" 📦 SNAPSHOTS ", Style::default().fg(theme.bg_dark).bg(theme.secondary).add_modifier(Modifier::BOLD)),
+                    Span::styled(SLANT_RIGHT, Style::default().fg(theme.secondary).bg(theme.bg_dark)),
+                ]))
+                .title_alignment(Alignment::Left)
+                .title_bottom(Line::from(Span::styled(summary, Style::default().fg(theme.gray))).right_aligned())
+                .style(Style::default().bg(theme.bg_dark))
+        )
+        .highlight_style(theme.highlight(Style::default().bg(theme.accent).fg(theme.bg_dark).add_modifier(Modifier::BOLD)))
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
+
+    // The table only sees the window, so selection is relative to it
+    let mut window_state = TableState::default().with_selected(selected.map(|i| i - start));
+    f.render_stateful_widget(t, area, &mut window_state);
+    *app.table_state.offset_mut() = start;
+    app.hit_areas.sort_headers = sort_headers;
+    app.table_widths = Some((widths_key, widths));
+
+    if let Some(message) = empty_state_message(app) {
+        // Below the borders and the header row
+        let inner = area.inner(Margin { horizontal: 1, vertical: 1 });
+        let body = Rect { y: inner.y + 1, height: inner.height.saturating_sub(1), ..inner };
+        let lines = vec![Line::from("📭"), Line::from(""), Line::from(message)];
+        let top = body.height.saturating_sub(lines.len() as u16) / 2;
+        let text_area = Rect { y: body.y + top, height: body.height - top, ..body };
+        f.render_widget(
+            Paragraph::new(lines)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(theme.gray).add_modifier(Modifier::ITALIC)),
+            text_area,
+        );
+    }
+}
+
+// What to show in place of an empty table: nothing while the list is
+// still loading, else why no rows are left
+fn empty_state_message(app: &App) -> Option<String> {
+    if app.loading || !app.get_filtered_snapshots().is_empty() {
+        return None;
+    }
+    if app.snapshots.is_empty() {
+        let create = app.keymap.key_for(Action::Create).map_or(String::from("c"), keys::key_name);
+        return Some(format!("No snapshots found. Press [{}] to create one.", create));
+    }
+    if !app.filter_input.is_empty() {
+        return Some(format!("No snapshots match \"{}\". Press [Esc] to clear the filter.", app.filter_input));
+    }
+    let config = app.active_config.as_deref().unwrap_or_default();
+    Some(format!("The {} config has no snapshots.", config))
+}
+
+// The header of `column`, with the sort indicator when it sorts the table
+fn column_title(app: &App, column: Column) -> String {
+    let title = match column {
+        Column::Number => "📸 #",
+        Column::Type => "🏷️ Type",
+        Column::Date => "📅 Date",
+        Column::User => "👤 User",
+        Column::Space => "💾 Space",
+        Column::Delta => "Δ",
+        Column::Label => "🔖 Label",
+        Column::Description => "📝 Description",
+        Column::Config => "⚙️ Config",
+        Column::Cleanup => "🧹 Cleanup",
+        Column::Subvolume => "📂 Subvolume",
+    };
+    match column.sort_key() {
+        Some(key) => format!("{}{}", title, app.get_sort_indicator(key)),
+        None => title.to_string(),
+    }
+}
+
+// Cell texts of row `idx` of the snapshot table, one per column. A pair
+// shows its pre's fields.
+fn row_texts(
+    theme: &Theme,
+    app: &App,
+    display_rows: &[DisplayRow],
+    idx: usize,
+    orphans: &HashSet<(&str, u32)>,
+    columns: &[Column],
+) -> Vec<String> {
+    let row = display_rows[idx];
+    let is_selected = row.snapshots().iter().all(|s| app.is_selected(s));
+    let selection_marker = if is_selected { "✅ " } else { "" };
+    let created_marker = if row.snapshots().iter().any(|s| app.created_here(s)) { CREATED_MARKER } else { "" };
+    let first = row.snapshots()[0];
+
+    columns
+        .iter()
+        .map(|&column| match (column, row) {
+            (Column::Number, DisplayRow::Single(item)) => {
+                // Members of an expanded pair are drawn as its branches
+                let branch = match idx.checked_sub(1).map(|i| display_rows[i]) {
+                    Some(DisplayRow::Pair { expanded: true, .. }) => "├ ",
+                    _ => match idx.checked_sub(2).map(|i| display_rows[i]) {
+                        Some(DisplayRow::Pair { expanded: true, .. }) => "└ ",
+                        _ => "",
+                    },
+                };
+                format!("{}{}{}{}", selection_marker, branch, item.number, created_marker)
+            }
+            (Column::Number, DisplayRow::Pair { pre, post, expanded }) => {
+                let fold = if expanded { "▾" } else { "▸" };
+                format!("{}{}{}..{}{}", selection_marker, fold, pre.number, post.number, created_marker)
+            }
+            (Column::Type, DisplayRow::Single(item)) => {
+                format!("{}{}{}", item.snapshot_type, snapshot_markers(theme, &[item]), orphan_marker(item, orphans))
+            }
+            (Column::Type, DisplayRow::Pair { pre, post, .. }) => format!("pre/post{}", snapshot_markers(theme, &[pre, post])),
+            (Column::Space, DisplayRow::Single(item)) => item.used_space.map(format_size).unwrap_or_default(),
+            (Column::Space, DisplayRow::Pair { pre, post, .. }) => match (pre.used_space, post.used_space) {
+                (None, None) => String::new(),
+                (a, b) => format_size(a.unwrap_or(0) + b.unwrap_or(0)),
+            },
+            (Column::Date, _) => app.display_date(&first.date),
+            (Column::User, _) => first.user.clone(),
+            (Column::Delta, _) => format_delta(row_delta(app, row)),
+            (Column::Label, _) => app.row_label(row).unwrap_or_default().to_string(),
+            (Column::Description, _) => first.description.clone(),
+            (Column::Config, _) => first.config.clone(),
+            (Column::Cleanup, _) => first.cleanup.clone().unwrap_or_default(),
+            (Column::Subvolume, _) => first.subvolume.clone(),
+        })
+        .collect()
+}
+
+// `text` with the parts the filter's free text matched picked out.
+fn highlight_matches<'a>(theme: &Theme, text: String, filter: &Filter) -> Line<'a> {
+    let style = Style::default().fg(theme.warning).add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans = Vec::new();
+    let mut shown = 0;
+    for (start, end) in filter.match_ranges(&text) {
+        spans.push(Span::raw(text[shown..start].to_string()));
+        spans.push(Span::styled(text[start..end].to_string(), style));
+        shown = end;
+    }
+    spans.push(Span::raw(text[shown..].to_string()));
+    Line::from(spans)
+}
+
+// The Type column's badge or badges, then the default/active markers. The
+// text matches `row_texts` so the column width still fits.
+fn type_cell<'a>(theme: &Theme, row: DisplayRow<'a>, orphans: &HashSet<(&str, u32)>) -> Line<'a> {
+    let mut spans = match row {
+        DisplayRow::Single(item) => vec![type_style(theme, &item.snapshot_type)],
+        DisplayRow::Pair { .. } => vec![type_style(theme, "pre"), Span::raw("/"), type_style(theme, "post")],
+    };
+    spans.push(Span::raw(snapshot_markers(theme, &row.snapshots())));
+    // A folded pair has its post, so only single rows can be orphans
+    if let DisplayRow::Single(item) = row {
+        spans.push(Span::styled(orphan_marker(item, orphans), Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)));
+    }
+    Line::from(spans)
+}
+
+// Flags a pre snapshot still waiting for its post
+fn orphan_marker(snap: &Snapshot, orphans: &HashSet<(&str, u32)>) -> &'static str {
+    if orphans.contains(&(snap.config.as_str(), snap.number)) { ORPHAN_MARKER } else { "" }
+}
+
+// The snapshot type coloured by kind; types snapper may add later keep the
+// row's colour.
+fn type_style<'a>(theme: &Theme, snapshot_type: &'a str) -> Span<'a> {
+    let color = match snapshot_type {
+        "pre" => theme.secondary,
+        "post" => theme.accent,
+        "single" => theme.gray,
+        _ => return Span::raw(snapshot_type),
+    };
+    Span::styled(snapshot_type, Style::default().fg(color).add_modifier(Modifier::BOLD))
+}
+
+// Widest a column grows; longer values are cut off. The description
+// takes whatever the others leave.
+fn column_max_width(column: Column) -> u16 {
+    match column {
+        Column::Number => 14,
+        Column::Type => 16,
+        Column::Date => 24,
+        Column::User | Column::Config => 16,
+        Column::Space | Column::Delta | Column::Cleanup => 12,
+        Column::Label | Column::Subvolume => 24,
+        Column::Description => u16::MAX,
+    }
+}
+
+// Widths of the columns: the widest header or cell, capped.
+/// What the table's column widths are measured from. Measuring formats
+/// every filtered row, so the widths are kept until one of these changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WidthsKey {
+    list_generation: u64,
+    rows: usize,
+    columns: Vec<Column>,
+    headers: Vec<String>,
+    active_config: Option<String>,
+    filter: (String, bool, bool),
+    hidden_types: HashSet<String>,
+    group_pairs: bool,
+    expanded_pairs: HashSet<(String, u32)>,
+    selected: usize,
+    created_tag_key: String,
+    // Relative dates read differently as time passes
+    relative_minute: Option<u64>,
+}
+
+impl WidthsKey {
+    fn of(app: &App, columns: &[Column], headers: &[String], rows: usize) -> Self {
+        let minute = || SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 60);
+        WidthsKey {
+            list_generation: app.list_generation,
+            rows,
+            columns: columns.to_vec(),
+            headers: headers.to_vec(),
+            active_config: app.active_config.clone(),
+            filter: (app.filter_input.clone(), app.filter_case_sensitive, app.filter_whole_word),
+            hidden_types: app.hidden_types.clone(),
+            group_pairs: app.group_pairs,
+            expanded_pairs: app.expanded_pairs.clone(),
+            selected: app.selected_numbers.len(),
+            created_tag_key: app.config.created_tag_key.clone(),
+            relative_minute: app.relative_dates.then(minute),
+        }
+    }
+}
+
+fn column_widths(columns: &[Column], headers: &[String], rows: impl Iterator<Item = Vec<String>>) -> Vec<u16> {
+    let width = |text: &str| Span::raw(text).width() as u16;
+    let mut widths: Vec<u16> = headers.iter().map(|header| width(header)).collect();
+    for row in rows {
+        for (column, text) in widths.iter_mut().zip(&row) {
+            *column = (*column).max(width(text));
+        }
+    }
+    widths.iter().zip(columns).map(|(&width, &column)| width.min(column_max_width(column))).collect()
+}
+
+// Space growth of a row over the snapshot before it; a folded pair adds
+// up both halves, i.e. the post against what preceded the pre
+fn row_delta(app: &App, row: DisplayRow) -> Option<i64> {
+    row.snapshots()
+        .iter()
+        .filter_map(|s| app.space_deltas.get(&(s.config.clone(), s.number)))
+        .fold(None, |total, &delta| Some(total.unwrap_or(0) + delta))
+}
+
+fn format_delta(delta: Option<i64>) -> String {
+    match delta {
+        None => String::from("—"),
+        Some(0) => String::from("0B"),
+        Some(d) if d > 0 => format!("+{}", format_size(d as u64)),
+        Some(d) => format!("-{}", format_size(d.unsigned_abs())),
+    }
+}
+
+// Growth in the warning colour, shrinking in the success colour
+fn delta_style(theme: &Theme, delta: Option<i64>) -> Style {
+    match delta {
+        Some(d) if d > 0 => Style::default().fg(theme.warning),
+        Some(d) if d < 0 => Style::default().fg(theme.success),
+        _ => Style::default().fg(theme.gray),
+    }
+}
+
+// Default and active markers for the Type column, " ★●" style.
+fn snapshot_markers(theme: &Theme, snapshots: &[&Snapshot]) -> String {
+    let mut markers = String::new();
+    if snapshots.iter().any(|s| s.default) {
+        markers.push_str(theme.default_marker);
+    }
+    if snapshots.iter().any(|s| s.active) {
+        markers.push_str(theme.active_marker);
+    }
+    if !markers.is_empty() {
+        markers.insert(0, ' ');
+    }
+    markers
+}
+
+// Rows `[start, end)` to draw: the previous offset is kept where possible and
+// nudged just enough to keep the selected row on screen, like `Table` does.
+fn visible_window(selected: Option<usize>, offset: usize, len: usize, height: usize) -> (usize, usize) {
+    let height = height.max(1);
+    let mut start = offset.min(len.saturating_sub(height));
+    if let Some(selected) = selected {
+        if selected < start {
+            start = selected;
+        } else if selected >= start + height {
+            start = selected + 1 - height;
+        }
+    }
+    (start, (start + height).min(len))
+}
+
+fn draw_details_panel(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    let width = area.width.saturating_sub(2); // Inside the borders
+    let mut content = match app.get_selected_row() {
+        Some(DisplayRow::Pair { pre, post, .. }) => {
+            let heading = |text: &'static str| {
+                Line::from(Span::styled(text, Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD)))
+            };
+            let mut lines = vec![heading("── Pre ──")];
+            lines.extend(snapshot_detail_lines(theme, pre, width));
+            lines.push(Line::from(""));
+            lines.push(heading("── Post ──"));
+            lines.extend(snapshot_detail_lines(theme, post, width));
+            lines
+        }
+        Some(DisplayRow::Single(snap)) => snapshot_detail_lines(theme, snap, width),
+        None => vec![Line::from(Span::styled("No snapshot selected.", Style::default().fg(theme.gray).add_modifier(Modifier::ITALIC)))],
+    };
+    if let Some(browse) = &app.browse {
+        content.push(Line::from(""));
+        content.push(Line::from(vec![
+            Span::styled("📁 Mounted: ", Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("#{} at {}", browse.number, browse.root.display()), Style::default().fg(theme.fg)),
+        ]));
+    }
+
+    let (border_type, border_style) = panel_border(app, Panel::Details, theme.accent);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(border_type)
+        .border_style(border_style)
+        .title(Line::from(vec![
+            Span::styled(" 🔍 DETAILS ", Style::default().fg(theme.bg_dark).bg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(SLANT_RIGHT, Style::default().fg(theme.accent).bg(theme.bg_dark)),
+        ]))
+        .title_alignment(Alignment::Left)
+        .style(Style::default().bg(theme.bg_dark));
+    let inner = block.inner(area);
+
+    let para = Paragraph::new(content).wrap(Wrap { trim: true });
+    let max_scroll = max_scroll(para.line_count(inner.width), inner.height);
+    let scroll = app.details_scroll.min(max_scroll);
+
+    f.render_widget(para.block(block).scroll((scroll, 0)), area);
+    app.details_max_scroll = max_scroll;
+    app.details_scroll = scroll;
+}
+
+fn draw_browse_panel(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    let Some(browse) = app.browse.as_ref() else {
+        return;
+    };
+    let shown = browse.file.as_ref().map(|(path, _)| path).unwrap_or(&browse.dir);
+    let relative = shown.strip_prefix(&browse.root).unwrap_or(shown);
+    let title = format!(" 📁 #{}: /{} ", browse.number, relative.display());
+
+    let (border_type, border_style) = panel_border(app, Panel::Status, theme.secondary);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(border_type)
+        .border_style(border_style)
+        .title(Line::from(vec![
+            Span::styled(title, Style::default().fg(theme.bg_dark).bg(theme.secondary).add_modifier(Modifier::BOLD)),
+            Span::styled(SLANT_RIGHT, Style::default().fg(theme.secondary).bg(theme.bg_dark)),
+        ]))
+        .title_alignment(Alignment::Left)
+        // Stands in for the status panel, so carry its latest message
+        .title_bottom(Line::from(Span::styled(
+            format!(" {} ", app.message.lines().next().unwrap_or_default()),
+            Style::default().fg(if app.loading { theme.warning } else { theme.success }),
+        )))
+        .title_bottom(Line::from(Span::styled(
+            " [Enter] Open  [Backspace] Up  [Esc] Unmount ",
+            Style::default().fg(theme.gray),
+        )).right_aligned())
+        .style(Style::default().bg(theme.bg_dark));
+    let inner = block.inner(area);
+
+    let para = match &browse.file {
+        Some((_, text)) => {
+            let para = Paragraph::new(text.as_str()).style(Style::default().fg(theme.fg));
+            let max_scroll = max_scroll(para.line_count(inner.width), inner.height);
+            para.scroll((browse.file_scroll.min(max_scroll), 0))
+        }
+        None if browse.entries.is_empty() => Paragraph::new(Span::styled(
+            "(empty directory)",
+            Style::default().fg(theme.gray).add_modifier(Modifier::ITALIC),
+        )),
+        None => {
+            let lines: Vec<Line> = browse
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(idx, entry)| {
+                    let (icon, color) = if entry.is_dir { ("📁 ", theme.secondary) } else { ("📄 ", theme.fg) };
+                    let mut style = Style::default().fg(color);
+                    if idx == browse.cursor {
+                        style = theme.highlight(style.bg(theme.bg_lighter).add_modifier(Modifier::BOLD));
+                    }
+                    Line::from(Span::styled(format!("{}{}", icon, entry.name), style))
+                })
+                .collect();
+            // Keep the cursor on screen
+            let scroll = browse.cursor.saturating_sub(inner.height.saturating_sub(1) as usize);
+            Paragraph::new(lines).scroll((scroll as u16, 0))
+        }
+    };
+    f.render_widget(para.block(block), area);
+}
+
+// Parameters that decide when snapper's cleanup deletes snapshots, listed
+// ahead of the rest of the config.
+const CLEANUP_POLICY_KEYS: &[&str] = &[
+    "NUMBER_CLEANUP",
+    "NUMBER_LIMIT",
+    "NUMBER_LIMIT_IMPORTANT",
+    "NUMBER_MIN_AGE",
+    "TIMELINE_CREATE",
+    "TIMELINE_CLEANUP",
+    "TIMELINE_LIMIT_HOURLY",
+    "TIMELINE_LIMIT_DAILY",
+    "TIMELINE_LIMIT_WEEKLY",
+    "TIMELINE_LIMIT_MONTHLY",
+    "TIMELINE_LIMIT_QUARTERLY",
+    "TIMELINE_LIMIT_YEARLY",
+    "TIMELINE_MIN_AGE",
+    "EMPTY_PRE_POST_CLEANUP",
+    "EMPTY_PRE_POST_MIN_AGE",
+    "SPACE_LIMIT",
+    "FREE_LIMIT",
+];
+
+fn draw_config_panel(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    let name = app.info_config_name();
+    let note = |text: String| vec![Line::from(Span::styled(text, Style::default().fg(theme.gray).add_modifier(Modifier::ITALIC)))];
+    let content = match app.config_info.get(&name) {
+        Some(Some(Ok(values))) => config_info_lines(theme, values),
+        Some(Some(Err(e))) => note(format!("Could not read config: {}", e)),
+        _ => note(String::from("Loading config...")),
+    };
+
+    let (border_type, border_style) = panel_border(app, Panel::Details, theme.accent);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(border_type)
+        .border_style(border_style)
+        .title(Line::from(vec![
+            Span::styled(format!(" ⚙️ CONFIG: {} ", name), Style::default().fg(theme.bg_dark).bg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(SLANT_RIGHT, Style::default().fg(theme.accent).bg(theme.bg_dark)),
+        ]))
+        .title_alignment(Alignment::Left)
+        .style(Style::default().bg(theme.bg_dark));
+    let inner = block.inner(area);
+
+    // Shares the details scroll offset since the two panels take turns
+    let para = Paragraph::new(content).wrap(Wrap { trim: true });
+    let max_scroll = max_scroll(para.line_count(inner.width), inner.height);
+    let scroll = app.details_scroll.min(max_scroll);
+
+    f.render_widget(para.block(block).scroll((scroll, 0)), area);
+    app.details_max_scroll = max_scroll;
+    app.details_scroll = scroll;
+}
+
+fn config_info_lines<'a>(theme: &Theme, values: &'a HashMap<String, String>) -> Vec<Line<'a>> {
+    let line = |key: &'a str, value: &'a str, key_color| {
+        Line::from(vec![
+            Span::styled(format!("{}: ", key), Style::default().fg(key_color).add_modifier(Modifier::BOLD)),
+            Span::styled(value, Style::default().fg(theme.fg)),
+        ])
+    };
+
+    let mut lines: Vec<Line> = CLEANUP_POLICY_KEYS
+        .iter()
+        .filter_map(|key| values.get_key_value(*key))
+        .map(|(key, value)| line(key, value, theme.accent))
+        .collect();
+
+    let mut rest: Vec<_> = values.iter().filter(|(key, _)| !CLEANUP_POLICY_KEYS.contains(&key.as_str())).collect();
+    rest.sort();
+    if !lines.is_empty() && !rest.is_empty() {
+        lines.push(Line::from(""));
+    }
+    lines.extend(rest.into_iter().map(|(key, value)| line(key, value, theme.gray)));
+    lines
+}
+
+// Inner width from which the details panel puts its short fields side by side
+const DETAILS_TWO_COLUMN_WIDTH: u16 = 70;
+
+fn snapshot_detail_lines<'a>(theme: &Theme, snap: &'a Snapshot, width: u16) -> Vec<Line<'a>> {
+    let userdata_str = snap.userdata.as_ref().map(|m| {
+        m.iter()
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }).unwrap_or_default();
+
+    let date = match snap.timestamp.or_else(|| data::parse_date(&snap.date)) {
+        Some(secs) => format!("{} ({})", data::format_local_time(secs), format_relative(&snap.date)),
+        None => snap.date.clone(),
+    };
+
+    let fields = vec![
+        Line::from(vec![
+            Span::styled("⚙️ Config: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(&snap.config, Style::default().fg(theme.fg)),
+        ]),
+        Line::from(vec![
+            Span::styled("📂 Subvolume: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(&snap.subvolume, Style::default().fg(theme.fg)),
+        ]),
+        Line::from(vec![
+            Span::styled("🔢 Number: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(snap.number.to_string(), Style::default().fg(theme.fg)),
+        ]),
+        Line::from(vec![
+            Span::styled("🏷️ Type: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(&snap.snapshot_type, Style::default().fg(theme.fg)),
+        ]),
+        Line::from(vec![
+            Span::styled("📅 Date: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(date, Style::default().fg(theme.fg)),
+        ]),
+        Line::from(vec![
+            Span::styled("👤 User: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(&snap.user, Style::default().fg(theme.success)),
+        ]),
+        Line::from(vec![
+            Span::styled("🧹 Cleanup: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(snap.cleanup.as_deref().unwrap_or("-"), Style::default().fg(theme.fg)),
+        ]),
+        Line::from(vec![
+            Span::styled("💾 Used Space: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled(snap.used_space.map(format_size_with_bytes).unwrap_or_default(), Style::default().fg(theme.fg)),
+        ]),
+    ];
+    let mut lines = if width >= DETAILS_TWO_COLUMN_WIDTH { two_columns(fields, width) } else { fields };
+    // Free text always gets the full width to wrap in
+    lines.push(Line::from(vec![
+        Span::styled("📝 Description: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+        Span::styled(&snap.description, Style::default().fg(theme.fg)),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("📋 Userdata: ", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+        Span::styled(userdata_str, Style::default().fg(theme.fg)),
+    ]));
+    // Only with a snapper newer or older than the fields the TUI knows
+    if let Some(extra) = extra_fields(snap) {
+        lines.push(Line::from(vec![
+            Span::styled("🧩 Extra fields: ", Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)),
+            Span::styled(extra, Style::default().fg(theme.fg)),
+        ]));
+    }
+    lines
+}
+
+// The fields snapper printed that `Snapshot` has no place for, by name;
+// strings are shown without their JSON quotes
+fn extra_fields(snap: &Snapshot) -> Option<String> {
+    let mut fields: Vec<(&String, &serde_json::Value)> = snap.extra.iter().collect();
+    if fields.is_empty() {
+        return None;
+    }
+    fields.sort_by_key(|&(name, _)| name);
+    let text = fields
+        .into_iter()
+        .map(|(name, value)| match value {
+            serde_json::Value::String(text) => format!("{}={}", name, text),
+            other => format!("{}={}", name, other),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(text)
+}
+
+// Pairs up consecutive lines into rows of two columns. A pair that doesn't
+// fit its columns stays on two lines so nothing is cut off.
+fn two_columns(lines: Vec<Line<'_>>, width: u16) -> Vec<Line<'_>> {
+    let column = width as usize / 2;
+    let mut rows = Vec::new();
+    let mut lines = lines.into_iter();
+    while let Some(left) = lines.next() {
+        match lines.next() {
+            Some(right) if left.width() < column && right.width() <= width as usize - column => {
+                let pad = column - left.width();
+                let mut spans = left.spans;
+                spans.push(Span::raw(" ".repeat(pad)));
+                spans.extend(right.spans);
+                rows.push(Line::from(spans));
+            }
+            Some(right) => rows.extend([left, right]),
+            None => rows.push(left),
+        }
+    }
+    rows
+}
+
+fn draw_status_panel(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    let mut title = match &app.status_range {
+        Some(range) if app.status_compare => format!(" ℹ️ COMPARE #{} ↔ #{} ", range.pre, range.post),
+        _ => String::from(" ℹ️ STATUS "),
+    };
+    if app.status_streaming() {
+        title.push_str(&format!("({} lines…) ", app.status_text.lines().count()));
+    }
+    if !app.status_wrap {
+        title.push_str("⇆ ");
+    }
+    if app.loading {
+        title.push_str(&format!(" {}", app.spinner_frames[app.spinner_state]));
+    }
+
+    // Messages may carry detail lines, e.g. per-snapshot delete errors
+    let message_style = Style::default().fg(if app.loading { theme.warning } else { theme.success });
+    let mut lines: Vec<Line> = app
+        .message
+        .lines()
+        .map(|line| Line::from(Span::styled(line, message_style)))
+        .collect();
+    lines.push(Line::from(""));
+    
+    for (idx, line) in app.status_text.lines().enumerate() {
+        let marked = app.status_marked.contains(&idx);
+        let color = match marked {
+            true => theme.accent,
+            false => status_change_color(theme, line).unwrap_or(theme.fg),
+        };
+        let mut style = Style::default().fg(color);
+        if app.status_range.is_some() && idx == app.status_cursor {
+            style = theme.highlight(style.bg(theme.bg_lighter).add_modifier(Modifier::BOLD));
+        }
+        let marker = if marked { "● " } else { "" };
+        lines.push(Line::from(vec![Span::styled(marker, style), Span::styled(line, style)]));
+    }
+
+    let (border_type, border_style) = panel_border(app, Panel::Status, theme.warning);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(border_type)
+        .border_style(border_style)
+        .title(Line::from(vec![
+            Span::styled(title, Style::default().fg(theme.bg_dark).bg(theme.warning).add_modifier(Modifier::BOLD)),
+            Span::styled(SLANT_RIGHT, Style::default().fg(theme.warning).bg(theme.bg_dark)),
+        ]))
+        .title_alignment(Alignment::Left)
+        .title_bottom(Line::from(Span::styled(
+            if app.status_range.is_some() { " [ ] Move  [X] Mark  [U] Undo  [Enter] Diff " } else { "" },
+            Style::default().fg(theme.gray),
+        )).right_aligned())
+        .title_bottom(status_legend(theme, app.status_range.is_some()))
+        .style(Style::default().bg(theme.bg_dark));
+    let inner = block.inner(area);
+
+    // Unwrapped, columns line up and the panel scrolls sideways instead
+    app.status_max_hscroll = if app.status_wrap { 0 } else { max_hscroll(&lines, inner.width) };
+    let hscroll = app.status_hscroll.min(app.status_max_hscroll);
+    let status = match app.status_wrap {
+        true => Paragraph::new(lines).wrap(Wrap { trim: true }),
+        false => Paragraph::new(lines),
+    };
+    let max_scroll = max_scroll(status.line_count(inner.width), inner.height);
+    let scroll = app.status_scroll.min(max_scroll);
+
+    f.render_widget(status.block(block).scroll((scroll, hscroll)), area);
+    app.status_max_scroll = max_scroll;
+    app.status_scroll = scroll;
+    app.status_hscroll = hscroll;
+}
+
+// Colour of a `snapper status` line by its change code: created, deleted,
+// content changed, type changed, or only metadata (permissions, owner, …)
+fn status_change_color(theme: &Theme, line: &str) -> Option<Color> {
+    let (code, _) = data::parse_status_line(line)?;
+    match code.chars().next()? {
+        '+' => Some(theme.success),
+        '-' => Some(theme.error),
+        'c' => Some(theme.warning),
+        't' => Some(theme.secondary),
+        '.' if code.chars().any(|c| c != '.') => Some(theme.primary),
+        _ => None,
+    }
+}
+
+// `+ new  - gone  c changed  t type  . meta` in the colours the listing uses
+fn status_legend<'a>(theme: &Theme, shown: bool) -> Line<'a> {
+    if !shown {
+        return Line::default();
+    }
+    let entries = [
+        ("+", "new", theme.success),
+        ("-", "gone", theme.error),
+        ("c", "changed", theme.warning),
+        ("t", "type", theme.secondary),
+        (".", "meta", theme.primary),
+    ];
+    let mut spans = vec![Span::raw(" ")];
+    for (code, meaning, color) in entries {
+        spans.push(Span::styled(code, Style::default().fg(color).add_modifier(Modifier::BOLD)));
+        spans.push(Span::styled(format!(" {} ", meaning), Style::default().fg(theme.gray)));
+    }
+    Line::from(spans)
+}
+
+// How far unwrapped `lines` can scroll sideways in `width` columns
+fn max_hscroll(lines: &[Line], width: u16) -> u16 {
+    let widest = lines.iter().map(Line::width).max().unwrap_or(0);
+    u16::try_from(widest).unwrap_or(u16::MAX).saturating_sub(width)
+}
+
+fn draw_actions_bar(f: &mut Frame, theme: &Theme, app: &mut App, area: Rect) {
+    // Read-only mode grays out whatever needs root
+    let writable = |color| if app.read_only { theme.gray } else { color };
+    let buttons = [
+        (FooterAction::Create, " [c]reate ➕ ", writable(theme.accent)),
+        (FooterAction::Delete, " [D]elete 🗑️  ", writable(theme.error)),
+        // Grayed out where snapper can't roll back
+        (FooterAction::Apply, " [A]pply ↩️  ", if app.apply_supported() { writable(theme.success) } else { theme.gray }),
+        (FooterAction::Filter, " [/] Filter 🔍 ", theme.primary),
+        (FooterAction::Status, " [S]tatus ℹ️  ", theme.secondary),
+        (FooterAction::Refresh, " [R]efresh 🔄 ", theme.warning),
+        (FooterAction::Quit, " [Q]uit 🚪 ", theme.gray),
+    ];
+
+    let mut actions_text = vec![Span::styled(" ⚡ ACTIONS: ", Style::default().fg(theme.primary).add_modifier(Modifier::BOLD))];
+    // Button column ranges relative to the start of the line
+    let mut spans_at = Vec::new();
+    for (idx, (action, label, color)) in buttons.into_iter().enumerate() {
+        if idx > 0 {
+            actions_text.push(Span::raw(" "));
+        }
+        let start: usize = actions_text.iter().map(Span::width).sum();
+        actions_text.push(Span::styled(SLANT_LEFT, Style::default().fg(color).bg(theme.bg_dark)));
+        actions_text.push(Span::styled(label, Style::default().bg(color).fg(theme.bg_dark).add_modifier(Modifier::BOLD)));
+        actions_text.push(Span::styled(SLANT_LEFT, Style::default().fg(theme.bg_dark).bg(color)));
+        let end: usize = actions_text.iter().map(Span::width).sum();
+        spans_at.push((action, start, end));
+    }
+    let line = Line::from(actions_text);
+
+    // One borderless line in dense view
+    let block = Block::default().style(Style::default().bg(theme.bg_dark));
+    let block = if app.dense {
+        block
+    } else {
+        block.borders(Borders::ALL).border_type(BorderType::Double).border_style(Style::default().fg(theme.gray))
+    };
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Centred by hand so the button positions are known; a line wider than
+    // the bar is cut off on the right
+    let x = inner.x + inner.width.saturating_sub(line.width() as u16) / 2;
+    let text_area = Rect { x, width: inner.right() - x, ..inner };
+    f.render_widget(Paragraph::new(line), text_area);
+
+    app.hit_areas.footer_buttons = spans_at
+        .into_iter()
+        .filter_map(|(action, start, end)| {
+            let left = text_area.x.saturating_add(start as u16);
+            let right = text_area.x.saturating_add(end as u16).min(text_area.right());
+            (left < right).then(|| (Rect { x: left, width: right - left, ..text_area }, action))
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::DEFAULT_COLUMNS;
+    use crate::config::Config;
+    use crate::data::{ConfigFs, Snapshot};
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_visible_window_follows_selection() {
+        assert_eq!(visible_window(Some(0), 0, 100, 10), (0, 10));
+        assert_eq!(visible_window(Some(15), 0, 100, 10), (6, 16));
+        // The offset only moves once the selection leaves the window
+        assert_eq!(visible_window(Some(10), 6, 100, 10), (6, 16));
+        assert_eq!(visible_window(Some(3), 6, 100, 10), (3, 13));
+        // A stale offset is pulled back when the list shrinks
+        assert_eq!(visible_window(Some(4), 90, 5, 10), (0, 5));
+        assert_eq!(visible_window(None, 0, 0, 10), (0, 0));
+    }
+
+    #[test]
+    fn test_boot_lineage_label() {
+        let label = |booted, default| boot_lineage_label(BootLineage { booted, default });
+        assert_eq!(label(Some(7), Some(7)).as_deref(), Some(" 🥾 booted #7 · default #7 "));
+        assert_eq!(label(Some(7), Some(9)).as_deref(), Some(" 🥾 booted #7 · default #9 (after reboot) "));
+        assert_eq!(label(None, Some(9)).as_deref(), Some(" 🥾 booted subvolume · default #9 (after reboot) "));
+        assert_eq!(label(None, None), None);
+    }
+
+    #[test]
+    fn test_snapshot_markers() {
+        let theme = crate::theme::DRACULA;
+        let current = Snapshot { number: 1, default: true, active: true, ..Default::default() };
+        let plain = Snapshot { number: 2, ..Default::default() };
+        assert_eq!(snapshot_markers(&theme, &[&current]), " ★●");
+        assert_eq!(snapshot_markers(&theme, &[&plain]), "");
+        // A pair is marked when either half is
+        let next = Snapshot { number: 3, default: true, ..Default::default() };
+        assert_eq!(snapshot_markers(&theme, &[&plain, &next]), " ★");
+    }
+
+    #[test]
+    fn test_filter_matches_are_highlighted_and_counted() {
+        let theme = crate::theme::DRACULA;
+        let line = highlight_matches(&theme, String::from("zypper install Vim"), &Filter::parse("vim"));
+        let texts: Vec<&str> = line.spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(texts, vec!["zypper install ", "Vim", ""]);
+        assert_eq!(line.spans[1].style.fg, Some(theme.warning));
+        assert_eq!(highlight_matches(&theme, String::from("timeline"), &Filter::parse("vim")).spans.len(), 1);
+
+        let mut app = App::with_config(Config::default());
+        app.snapshots = ["timeline", "zypper install vim", "vim update"]
+            .iter()
+            .map(|description| Snapshot { description: description.to_string(), ..Default::default() })
+            .collect();
+        app.filter_input = String::from("vim");
+        assert_eq!(app.filter_counts(), (2, 3));
+        assert_eq!(filter_count_span(&theme, &app).content, "  2 of 3  ");
+    }
+
+    #[test]
+    fn test_about_warns_about_old_snapper() {
+        let mut app = App::with_config(Config::default());
+        assert!(about_text(&app).contains("snapper: checking…"));
+        assert!(about_text(&app).contains(env!("CARGO_PKG_VERSION")));
+        app.snapper_version = Some(Ok(String::from("0.10.4")));
+        assert!(about_text(&app).contains("snapper: 0.10.4\n"));
+        app.snapper_version = Some(Ok(String::from("0.5.6")));
+        assert!(about_text(&app).contains("too old for --jsonout"));
+    }
+
+    #[test]
+    fn test_type_badges() {
+        let theme = crate::theme::DRACULA;
+        assert_eq!(type_style(&theme, "pre").style.fg, Some(theme.secondary));
+        assert_eq!(type_style(&theme, "post").style.fg, Some(theme.accent));
+        assert_eq!(type_style(&theme, "single").style.fg, Some(theme.gray));
+        assert_eq!(type_style(&theme, "weekly").style.fg, None);
+
+        let pre = Snapshot { snapshot_type: String::from("pre"), default: true, ..Default::default() };
+        let post = Snapshot { snapshot_type: String::from("post"), ..Default::default() };
+        let line = type_cell(&theme, DisplayRow::Pair { pre: &pre, post: &post, expanded: false }, &HashSet::new());
+        assert_eq!(line.to_string(), "pre/post ★");
+        assert_eq!(type_cell(&theme, DisplayRow::Single(&post), &HashSet::new()).to_string(), "post");
+        let orphans = HashSet::from([("", 0)]);
+        assert_eq!(type_cell(&theme, DisplayRow::Single(&pre), &orphans).to_string(), "pre ★ ⏳");
+    }
+
+    #[test]
+    fn test_column_widths_follow_content() {
+        let headers = ["#", "Type", "Date", "User", "Space", "Δ", "Label", "Description"].map(String::from);
+        let row = |number: &str, user: &str| {
+            [number, "single", "2023-10-01 08:00:00", user, "1.2 MiB", "+12.0K", "known good", "x"].map(String::from).to_vec()
+        };
+
+        let widths = column_widths(&DEFAULT_COLUMNS, &headers, [row("1", "root"), row("1024", "bob")].into_iter());
+        assert_eq!(widths, [4, 6, 19, 4, 7, 6, 10, 11]);
+        // Long values are capped so the description keeps some room
+        let widths = column_widths(&DEFAULT_COLUMNS, &headers, [row("1", "a-very-long-service-account")].into_iter());
+        assert_eq!(widths[3], column_max_width(Column::User));
+        // An empty view is sized by the headers
+        assert_eq!(column_widths(&DEFAULT_COLUMNS, &headers, std::iter::empty()), [1, 4, 4, 4, 5, 1, 5, 11]);
+    }
+
+    #[test]
+    fn test_configured_columns_drive_table_and_header_clicks() {
+        let config = Config { columns: vec![Column::Config, Column::Date, Column::Number], ..Config::default() };
+        let mut app = App::with_config(config);
+        app.dismiss_splash();
+        app.loading = false;
+        app.snapshots = vec![Snapshot { number: 4, config: String::from("home"), ..Default::default() }];
+        app.table_state.select(Some(0));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let header_y = app.hit_areas.table.y + 1;
+        let header: String = (0..100).map(|x| buffer[(x, header_y)].symbol().to_string()).collect();
+        let (config, date, number) = (header.find("Config").unwrap(), header.find("Date").unwrap(), header.find('#').unwrap());
+        assert!(config < date && date < number, "{}", header);
+        assert!(!header.contains("Type") && !header.contains("Description"));
+
+        // Config doesn't sort; the others sort by what they show, wherever they are
+        let sorts: Vec<SortKey> = app.hit_areas.sort_headers.iter().map(|&(_, key)| key).collect();
+        assert_eq!(sorts, [SortKey::Date, SortKey::Number]);
+        for &(rect, key) in &app.hit_areas.sort_headers {
+            assert_eq!(rect.y, header_y);
+            assert_eq!(app.hit_areas.sort_key_at(rect.x, header_y), Some(key));
+            // The cell starts where its title is drawn
+            assert_ne!(buffer[(rect.x, header_y)].symbol(), " ");
+            assert_eq!(buffer[(rect.x - 1, header_y)].symbol(), " ");
+        }
+    }
+
+    #[test]
+    fn test_status_lines_coloured_by_change() {
+        let theme = THEMES[0];
+        assert_eq!(status_change_color(&theme, "+..... /etc/new.conf"), Some(theme.success));
+        assert_eq!(status_change_color(&theme, "-..... /etc/old.conf"), Some(theme.error));
+        assert_eq!(status_change_color(&theme, "c..... /etc/fstab"), Some(theme.warning));
+        assert_eq!(status_change_color(&theme, "t..... /etc/localtime"), Some(theme.secondary));
+        assert_eq!(status_change_color(&theme, "..p... /etc/shadow"), Some(theme.primary));
+        assert_eq!(status_change_color(&theme, "...... /etc/same"), None);
+        assert_eq!(status_change_color(&theme, "❌ Status failed"), None);
+    }
+
+    #[test]
+    fn test_delta_sign_and_colour() {
+        let theme = THEMES[0];
+        assert_eq!(format_delta(None), "—");
+        assert_eq!(format_delta(Some(0)), "0B");
+        assert_eq!(format_delta(Some(2048)), "+2.0K");
+        assert_eq!(format_delta(Some(-3 * 1024 * 1024)), "-3.0M");
+        assert_eq!(delta_style(&theme, Some(1)).fg, Some(theme.warning));
+        assert_eq!(delta_style(&theme, Some(-1)).fg, Some(theme.success));
+        assert_eq!(delta_style(&theme, None).fg, Some(theme.gray));
+    }
+
+    #[test]
+    fn test_scrolled_table_highlights_selected_row() {
+        let mut app = App::with_config(Config::default());
+        app.snapshots = (0..5000)
+            .map(|number| Snapshot { number, ..Default::default() })
+            .collect();
+        app.table_state.select(Some(2500));
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        let theme = app.theme;
+        terminal
+            .draw(|f| {
+                let area = f.area();
+                draw_snapshot_table(f, &theme, &mut app, area)
+            })
+            .unwrap();
+
+        // 20 rows minus borders and header leaves 17 visible rows
+        assert_eq!(app.table_state.offset(), 2500 + 1 - 17);
+        let buffer = terminal.backend().buffer();
+        let last_row: String = (0..80).map(|x| buffer[(x, 18)].symbol().to_string()).collect();
+        assert!(last_row.contains("👉"), "{}", last_row);
+        assert!(last_row.contains("2500"), "{}", last_row);
+    }
+
+    #[test]
+    fn test_details_use_two_columns_when_wide() {
+        let mut app = App::with_config(Config::default());
+        app.snapshots = vec![Snapshot {
+            number: 7,
+            config: String::from("root"),
+            subvolume: String::from("/"),
+            description: "zypper ".repeat(20) + "end",
+            ..Default::default()
+        }];
+        app.table_state.select(Some(0));
+
+        let theme = app.theme;
+        let render = |app: &mut App, width: u16| {
+            let mut terminal = Terminal::new(TestBackend::new(width, 20)).unwrap();
+            terminal
+                .draw(|f| {
+                    let area = f.area();
+                    draw_details_panel(f, &theme, app, area)
+                })
+                .unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            (0..20)
+                .map(|y| (0..width).map(|x| buffer[(x, y)].symbol().to_string()).collect::<String>())
+                .collect::<Vec<_>>()
+        };
+
+        let wide = render(&mut app, 100);
+        let config_row = wide.iter().find(|row| row.contains("Config:")).unwrap();
+        assert!(config_row.contains("Subvolume:"), "{}", config_row);
+        // The description still wraps onto the rows below instead of being cut
+        let description = wide.iter().position(|row| row.contains("Description:")).unwrap();
+        assert!(wide[description + 1..].iter().any(|row| row.contains("end")), "{:?}", wide);
+
+        let narrow = render(&mut app, 40);
+        let config_row = narrow.iter().find(|row| row.contains("Config:")).unwrap();
+        assert!(!config_row.contains("Subvolume:"), "{}", config_row);
+    }
+
+    #[test]
+    fn test_empty_table_explains_why() {
+        let mut app = App::with_config(Config::default());
+        assert_eq!(empty_state_message(&app), None, "still loading");
+        app.loading = false;
+        assert_eq!(empty_state_message(&app).as_deref(), Some("No snapshots found. Press [c] to create one."));
+
+        app.snapshots = vec![Snapshot { number: 1, config: String::from("root"), description: String::from("timeline"), ..Default::default() }];
+        assert_eq!(empty_state_message(&app), None);
+        app.filter_input = String::from("zypper");
+        assert_eq!(
+            empty_state_message(&app).as_deref(),
+            Some("No snapshots match \"zypper\". Press [Esc] to clear the filter.")
+        );
+        app.filter_input.clear();
+        app.active_config = Some(String::from("home"));
+        assert_eq!(empty_state_message(&app).as_deref(), Some("The home config has no snapshots."));
+
+        let mut terminal = Terminal::new(TestBackend::new(60, 12)).unwrap();
+        let theme = app.theme;
+        terminal
+            .draw(|f| {
+                let area = f.area();
+                draw_snapshot_table(f, &theme, &mut app, area)
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: String = (0..12)
+            .flat_map(|y| (0..60).map(move |x| (x, y)))
+            .map(|pos| buffer[pos].symbol().to_string())
+            .collect();
+        assert!(screen.contains("The home config has no snapshots."), "{}", screen);
+    }
+
+    #[test]
+    fn test_loading_screen_shows_progress_gauge() {
+        let mut app = App::with_config(Config::default());
+        app.loading_message = String::from("Deleting 4 snapshot(s)...");
+        app.loading_progress = Some((1, 4));
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let theme = app.theme;
+        terminal.draw(|f| draw_loading_screen(f, &theme, &mut app)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let screen: String = (0..24)
+            .flat_map(|y| (0..80).map(move |x| (x, y)))
+            .map(|pos| buffer[pos].symbol().to_string())
+            .collect();
+        assert!(screen.contains("1/4"), "{}", screen);
+    }
+
+    #[test]
+    fn test_loading_screen_counts_up_and_suggests_cancelling() {
+        let mut app = App::with_config(Config::default());
+        app.loading_message = String::from("Rolling back...");
+        let screen = |app: &mut App| {
+            let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+            let theme = app.theme;
+            terminal.draw(|f| draw_loading_screen(f, &theme, app)).unwrap();
+            terminal.backend().buffer().content().iter().map(|cell| cell.symbol().to_string()).collect::<String>()
+        };
+
+        app.loading_start = Some(Instant::now() - Duration::from_secs(5));
+        let text = screen(&mut app);
+        assert!(text.contains("⏱ 5s") && text.contains("[Esc] Cancel"), "{}", text);
+
+        app.loading_start = Some(Instant::now() - Duration::from_secs(95));
+        let text = screen(&mut app);
+        assert!(text.contains("⏱ 1m 35s") && text.contains("Still working"), "{}", text);
+
+        app.config.slow_hint_secs = None;
+        assert!(!screen(&mut app).contains("Still working"));
+    }
+
+    #[test]
+    fn test_click_areas_fit_a_narrow_terminal() {
+        let mut app = App::with_config(Config::default());
+        app.dismiss_splash();
+        app.snapshots = (1..=3).map(|number| Snapshot { number, ..Default::default() }).collect();
+
+        let mut terminal = Terminal::new(TestBackend::new(MIN_WIDTH, 20)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+
+        let screen = Rect::new(0, 0, MIN_WIDTH, 20);
+        let buttons = &app.hit_areas.footer_buttons;
+        assert!(!buttons.is_empty());
+        assert_eq!(buttons[0].1, FooterAction::Create);
+        for &(rect, action) in buttons {
+            assert!(rect.width > 0 && screen.union(rect) == screen, "{:?}", rect);
+            assert_eq!(app.hit_areas.footer_action_at(rect.x, rect.y), Some(action));
+        }
+        // Buttons that don't fit aren't clickable rather than overlapping
+        assert!(buttons.len() < 7);
+        assert_eq!(app.hit_areas.footer_action_at(0, 0), None);
+        // Too narrow to split, so details sits below the table
+        assert!(app.hit_areas.table.width > 0 && app.hit_areas.table.bottom() <= app.hit_areas.details.top());
+    }
+
+    #[test]
+    fn test_narrow_terminal_tabs_details_and_status() {
+        let mut app = App::with_config(Config::default());
+        app.dismiss_splash();
+        app.loading = false;
+        app.snapshots = (1..=3).map(|number| Snapshot { number, ..Default::default() }).collect();
+        let mut terminal = Terminal::new(TestBackend::new(STACKED_WIDTH - 1, 30)).unwrap();
+
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let (table, details) = (app.hit_areas.table, app.hit_areas.details);
+        assert!(table.width == details.width && table.bottom() < details.top(), "{:?} {:?}", table, details);
+        assert_eq!(app.hit_areas.status, Rect::default());
+
+        // Tab onto status swaps the panel, and mouse scrolling with it
+        app.cycle_focus(false);
+        app.cycle_focus(false);
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        assert_eq!(app.hit_areas.status, details);
+        assert_eq!(app.hit_areas.details, Rect::default());
+
+        // Wide enough, both panels sit beside the table again
+        let mut terminal = Terminal::new(TestBackend::new(STACKED_WIDTH, 30)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        assert!(app.hit_areas.table.right() <= app.hit_areas.details.left());
+        assert!(app.hit_areas.details.bottom() <= app.hit_areas.status.top());
+    }
+
+    #[test]
+    fn test_small_terminal_shows_notice_until_enlarged() {
+        let mut app = App::with_config(Config::default());
+        app.dismiss_splash();
+        app.snapshots = (1..=3).map(|number| Snapshot { number, ..Default::default() }).collect();
+        let screen_text = |terminal: &Terminal<TestBackend>| {
+            let buffer = terminal.backend().buffer();
+            buffer.content().iter().map(|cell| cell.symbol().to_string()).collect::<String>()
+        };
+
+        for (width, height) in [(20, 5), (MIN_WIDTH - 1, 30), (100, MIN_HEIGHT - 1)] {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal.draw(|f| draw(f, &mut app)).unwrap();
+            assert!(screen_text(&terminal).contains("small"), "{}x{}", width, height);
+            assert!(app.hit_areas.footer_buttons.is_empty());
+        }
+
+        let mut terminal = Terminal::new(TestBackend::new(MIN_WIDTH, MIN_HEIGHT)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        assert!(!screen_text(&terminal).contains("Terminal too small"));
+        assert!(!app.hit_areas.footer_buttons.is_empty());
+    }
+
+    #[test]
+    fn test_no_color_draws_only_default_colours() {
+        let mut app = App::with_config(Config::default());
+        app.dismiss_splash();
+        app.theme = app.theme.monochrome();
+        app.loading = false;
+        app.snapshots = (1..=3).map(|number| Snapshot { number, ..Default::default() }).collect();
+        app.table_state.select(Some(1));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        for cell in buffer.content() {
+            assert_eq!((cell.fg, cell.bg), (Color::Reset, Color::Reset), "{:?}", cell);
+        }
+        // The selection is still visible without colour
+        assert!(buffer.content().iter().any(|cell| cell.modifier.contains(Modifier::REVERSED)));
+    }
+
+    #[test]
+    fn test_extra_fields_listed_by_name() {
+        let mut snap = Snapshot::default();
+        assert_eq!(extra_fields(&snap), None);
+        snap.extra.insert(String::from("read-only"), serde_json::json!(true));
+        snap.extra.insert(String::from("mount"), serde_json::json!("/mnt"));
+        assert_eq!(extra_fields(&snap).as_deref(), Some("mount=/mnt, read-only=true"));
+    }
+
+    #[test]
+    fn test_quota_hint_offers_key_only_on_btrfs() {
+        let mut app = App::with_config(Config::default());
+        let snap = Snapshot { config: String::from("root"), number: 1, ..Default::default() };
+        app.set_snapshots(vec![snap.clone()]);
+        assert_eq!(quota_hint(&app).as_deref(), Some(" ⚠ used-space unavailable — run snapper setup-quota "));
+        app.config_fs.insert(String::from("root"), Some(ConfigFs { fstype: String::from("btrfs") }));
+        assert!(quota_hint(&app).is_some_and(|hint| hint.contains("[J]")));
+        app.set_snapshots(vec![Snapshot { used_space: Some(4096), ..snap }]);
+        assert_eq!(quota_hint(&app), None);
+    }
+
+    #[test]
+    fn test_delete_listing_names_each_target() {
+        let snaps: Vec<Snapshot> = (1..=103)
+            .map(|number| Snapshot { number, snapshot_type: String::from("single"), description: format!("s{}", number), ..Default::default() })
+            .collect();
+        let untitled = Snapshot { number: 7, snapshot_type: String::from("pre"), ..Default::default() };
+        assert_eq!(delete_listing(&[&snaps[0], &untitled], false), "#1 single · s1\n#7 pre · (no description)\n");
+        let home = Snapshot { config: String::from("home"), ..untitled.clone() };
+        assert_eq!(delete_listing(&[&home], true), "home #7 pre · (no description)\n");
+
+        let all: Vec<&Snapshot> = snaps.iter().collect();
+        let listing = delete_listing(&all, false);
+        assert!(listing.contains("#100 single · s100\n…and 3 more\n"), "{}", listing);
+        assert!(!listing.contains("#101"));
+    }
+
+    #[test]
+    fn test_column_widths_are_measured_again_only_on_change() {
+        let mut app = App::with_config(Config::default());
+        app.dismiss_splash();
+        app.loading = false;
+        app.set_snapshots(vec![Snapshot { config: String::from("root"), number: 1, ..Default::default() }]);
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let (_, measured) = app.table_widths.clone().unwrap();
+
+        // An unchanged table keeps the cached widths
+        let stale = vec![1; measured.len()];
+        app.table_widths.as_mut().unwrap().1 = stale.clone();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        assert_eq!(app.table_widths.as_ref().unwrap().1, stale);
+
+        app.set_snapshots(vec![Snapshot { config: String::from("root"), number: 1, user: String::from("a-long-user"), ..Default::default() }]);
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let widths = &app.table_widths.as_ref().unwrap().1;
+        assert_ne!(widths, &stale);
+        assert!(widths.iter().zip(&measured).any(|(new, old)| new > old));
+    }
+
+    #[test]
+    fn test_long_delete_popup_scrolls() {
+        let mut app = App::with_config(Config::default());
+        app.dismiss_splash();
+        app.loading = false;
+        app.set_snapshots((1..=40).map(|number| Snapshot { config: String::from("root"), number, ..Default::default() }).collect());
+        app.selected_numbers.extend((1..=40).map(|number| (String::from("root"), number)));
+        app.open_delete_popup();
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        assert!(app.delete_max_scroll > 0);
+
+        for _ in 0..100 {
+            app.scroll_delete(false);
+        }
+        assert_eq!(app.delete_scroll, app.delete_max_scroll);
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("[Enter] Confirm"), "the end of the message is reachable");
+        assert!(!screen.contains("#1 "));
+    }
+
+    #[test]
+    fn test_dense_view_fits_more_rows() {
+        let mut app = App::with_config(Config::default());
+        app.dismiss_splash();
+        app.loading = false;
+        app.set_snapshots((1..=40).map(|number| Snapshot { config: String::from("root"), number, ..Default::default() }).collect());
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let roomy = app.hit_areas.table;
+
+        app.toggle_dense();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let dense = app.hit_areas.table;
+        assert_eq!(dense.y, 1, "one header line and no gap above the table");
+        assert_eq!(dense.height, roomy.height + 10);
+        let footer = app.hit_areas.footer_buttons.first().map(|(rect, _)| rect.y);
+        assert_eq!(footer, Some(29));
+        assert!(app.preferences().dense);
+    }
+
+    #[test]
+    fn test_boot_critical_warning_names_each_role() {
+        let active = Snapshot { number: 3, active: true, ..Default::default() };
+        let default = Snapshot { number: 5, default: true, ..Default::default() };
+        let both = Snapshot { number: 7, active: true, default: true, ..Default::default() };
+        assert_eq!(boot_critical_warning(&[]), None);
+        assert_eq!(boot_critical_warning(&[&active]).as_deref(), Some("⚠ This includes the ACTIVE snapshot (#3)."));
+        assert_eq!(
+            boot_critical_warning(&[&active, &default]).as_deref(),
+            Some("⚠ This includes the ACTIVE snapshot (#3) and the DEFAULT snapshot (#5).")
+        );
+        assert_eq!(boot_critical_warning(&[&both]).as_deref(), Some("⚠ This includes the ACTIVE and DEFAULT snapshot (#7)."));
+    }
+
+    #[test]
+    fn test_input_view_keeps_cursor_visible() {
+        assert_eq!(input_view("short", 10), "short█");
+        // Exactly full would push the cursor out
+        assert_eq!(input_view("ninechars", 9), "…nechars█");
+
+        let long = "before upgrading the kernel and the bootloader";
+        let view = input_view(long, 20);
+        assert_eq!(Span::raw(view.as_str()).width(), 20);
+        assert!(view.starts_with('…') && view.ends_with("bootloader█"), "{}", view);
+
+        // Wide characters are never split
+        let view = input_view("快照快照快照", 6);
+        assert_eq!(view, "…快照█");
+    }
 }